@@ -0,0 +1,107 @@
+use rust_imgui as imgui;
+use rust_imgui::ImVec4;
+use rust_imgui::ImguiTextInputBuffer;
+use ::gba::Gba;
+use ::gba::cheats::{Cheat, CheatFormat, CHEAT_FORMATS};
+
+const CHEAT_ERROR_COL: ImVec4 = ImVec4 {x: 0.957, y: 0.263, z: 0.212, w: 1.000}; // F44336
+
+pub struct CheatManager {
+	name_input_buffer: ImguiTextInputBuffer,
+	code_input_buffer: ImguiTextInputBuffer,
+	format: CheatFormat,
+
+	/// Why the last cheat couldn't be added.
+	error: Option<String>,
+}
+
+impl CheatManager {
+	pub fn new() -> CheatManager {
+		CheatManager {
+			name_input_buffer: ImguiTextInputBuffer::new(64),
+			code_input_buffer: ImguiTextInputBuffer::new(1024),
+			format: CheatFormat::GameShark,
+			error: None,
+		}
+	}
+
+	pub fn render(&mut self, gba: &mut Gba) {
+		let mut changed = false;
+		let mut remove = None;
+
+		for idx in 0..gba.cheats.cheats().len() {
+			let mut enabled = gba.cheats.cheats()[idx].enabled;
+			if imgui::checkbox(imstr!("{}###cheat_{}", gba.cheats.cheats()[idx].name, idx), &mut enabled) {
+				gba.cheats.set_enabled(idx, enabled);
+				changed = true;
+			}
+			imgui::same_line();
+			imgui::text_disabled(imstr!("{}", gba.cheats.cheats()[idx].format.name()));
+			imgui::same_line();
+			if imgui::button_def(imstr!("Remove###remove_cheat_{}", idx)) {
+				remove = Some(idx);
+			}
+			if let Some(e) = gba.cheats.error(idx) {
+				render_error(e);
+			}
+		}
+
+		if let Some(idx) = remove {
+			gba.cheats.remove(idx);
+			changed = true;
+		}
+
+		imgui::text_disabled(imstr!("Codes are saved to {}", gba.cheats.path().display()));
+		imgui::input_text(imstr!("Name"), self.name_input_buffer.as_slice(), imgui::ImGuiInputTextFlags_EnterReturnsTrue, None, None);
+		imgui::input_text(imstr!("Codes"), self.code_input_buffer.as_slice(), imgui::ImGuiInputTextFlags_EnterReturnsTrue, None, None);
+
+		if imgui::button_def(imstr!("{}###cheat_format", self.format.name())) {
+			imgui::open_popup(imstr!("cheat_format_popup"));
+		}
+		if imgui::begin_popup(imstr!("cheat_format_popup")) {
+			for format in CHEAT_FORMATS.iter() {
+				if imgui::menu_item(imstr!("{}", format.name())) { self.format = *format; }
+			}
+			imgui::end_popup();
+		}
+
+		imgui::same_line();
+		if imgui::button_def(imstr!("Add Cheat")) {
+			let name = match self.name_input_buffer.as_str().trim() {
+				"" => format!("Cheat {}", gba.cheats.cheats().len() + 1),
+				name => name.to_string()
+			};
+			let cheat = Cheat {
+				name: name,
+				format: self.format,
+				code: self.code_input_buffer.as_str().to_string(),
+				enabled: true,
+			};
+			match gba.cheats.add(cheat) {
+				Ok(_) => {
+					self.name_input_buffer.clear();
+					self.code_input_buffer.clear();
+					self.error = None;
+					changed = true;
+				},
+				Err(e) => self.error = Some(e)
+			}
+		}
+
+		if let Some(ref e) = self.error {
+			render_error(e);
+		}
+
+		if changed {
+			if let Err(e) = gba.cheats.save() {
+				console_error!("{}", e);
+			}
+		}
+	}
+}
+
+fn render_error(e: &str) {
+	imgui::push_style_color(imgui::ImGuiCol::Text, CHEAT_ERROR_COL);
+	imgui::text(imstr!("{}", e));
+	imgui::pop_style_color(1);
+}
@@ -0,0 +1,72 @@
+use rust_imgui as imgui;
+use rust_imgui::ImVec4;
+use ::gba::Gba;
+use ::pyrite::profiles;
+use ::pyrite::settings::{self, is_setting};
+
+const OVERRIDDEN_COL: ImVec4 = ImVec4 {x: 1.000, y: 0.596, z: 0.000, w: 1.000}; // FF9800
+
+/// Values longer than this (like the bindings) are cut off.
+const MAX_VALUE_LENGTH: usize = 40;
+
+fn short_value(value: String) -> String {
+	if value.chars().count() > MAX_VALUE_LENGTH {
+		format!("{}...", value.chars().take(MAX_VALUE_LENGTH).collect::<String>())
+	} else {
+		value
+	}
+}
+
+/// Shows every setting and whether the running game overrides it.
+pub fn render_game_profile(gba: &mut Gba) {
+	let game_code = match profiles::active_game_code() {
+		Some(code) => code,
+		None => {
+			imgui::text_disabled(imstr!("The loaded ROM has no game code so it can't have a profile."));
+			return
+		}
+	};
+
+	imgui::text(imstr!("{} ({})", gba.game_title(), game_code));
+	imgui::text_disabled(imstr!("Saved to {}", profiles::profile_path(&game_code).display()));
+	imgui::text_disabled(imstr!("Overridden settings are changed in the settings window as usual, but only for this game."));
+	imgui::text_disabled(imstr!("Cheats are kept for each game too, in the cheats window."));
+
+	let current = match settings::to_object(::pyrite::get_settings()) {
+		Ok(o) => o,
+		Err(e) => {
+			imgui::text(imstr!("{}", e));
+			return
+		}
+	};
+
+	let mut toggled = None;
+	imgui::columns(3, imstr!("game_profile_table"), true);
+	for (key, value) in current.iter() {
+		if !is_setting(key) { continue }
+		let mut overridden = profiles::is_overridden(key);
+		if imgui::checkbox(imstr!("{}###override_{}", key, key), &mut overridden) {
+			toggled = Some((key.clone(), overridden));
+		}
+		imgui::next_column();
+
+		if overridden {
+			imgui::push_style_color(imgui::ImGuiCol::Text, OVERRIDDEN_COL);
+			imgui::text(imstr!("{}", short_value(value.to_string())));
+			imgui::pop_style_color(1);
+		} else {
+			imgui::text_disabled(imstr!("{}", short_value(value.to_string())));
+		}
+		imgui::next_column();
+
+		if let Some(global) = profiles::global_value(key) {
+			imgui::text_disabled(imstr!("global: {}", short_value(global.to_string())));
+		}
+		imgui::next_column();
+	}
+	imgui::columns(1, imstr!("game_profile_table_end"), false);
+
+	if let Some((key, overridden)) = toggled {
+		profiles::set_overridden(&key, overridden);
+	}
+}
@@ -1,6 +1,10 @@
 pub mod console;
 pub mod memory_editor;
 pub mod profiler;
+pub mod save_slots;
+pub mod cheats;
+pub mod ram_search;
+pub mod game_profile;
 
 use rust_imgui as imgui;
 use rust_imgui::ImVec4;
@@ -10,8 +14,13 @@ use ::gba::core::memory::*;
 use self::console::ImGuiConsole;
 use self::memory_editor::MemoryEditor;
 use self::profiler::ProfilerGUI;
+use self::save_slots::SaveSlotBrowser;
+use self::cheats::CheatManager;
+use self::ram_search::RamSearch;
 use std::marker::PhantomData;
 use ::util::sync_unsafe_cell::SyncUnsafeCell;
+use ::pyrite::bindings::{BINDINGS, default_bindings, default_gamepad_bindings, key_name, key_names, unbind, unbind_gamepad};
+use ::gba::device::gamepad::input_name;
 
 pub const CONSOLE_COLOR_NORMAL: ImVec4 = ImVec4 { x: 1.0, y: 1.0, z: 1.0, w: 1.0 }; // #FFFFFF
 pub const CONSOLE_COLOR_WARNING: ImVec4 = ImVec4 { x: 1.0, y: 0.922, z: 0.231, w: 1.0 }; // #FFEB3B
@@ -34,6 +43,17 @@ pub struct DebugData {
 	pub memory_window: MemoryEditor,
 	pub memory_window_opened: bool,
 
+	pub ram_search_window: RamSearch,
+	pub ram_search_window_opened: bool,
+
+	pub save_slots_window: SaveSlotBrowser,
+	pub save_slots_window_opened: bool,
+
+	pub cheats_window: CheatManager,
+	pub cheats_window_opened: bool,
+
+	pub game_profile_window_opened: bool,
+
 	pub frame_build_time: f64,
 	pub frame_render_time: f64,
 	pub full_frame_time: f64,
@@ -69,6 +89,9 @@ pub struct DebugData {
 	pub swi_count: u32,
 
 	pub unlock_profiler: bool,
+
+	/// The binding that the next key pressed is added to.
+	pub rebinding: Option<&'static str>,
 }
 
 impl DebugData {
@@ -80,6 +103,17 @@ impl DebugData {
 			memory_window: MemoryEditor::new(),
 			memory_window_opened: false,
 
+			ram_search_window: RamSearch::new(),
+			ram_search_window_opened: false,
+
+			save_slots_window: SaveSlotBrowser::new(),
+			save_slots_window_opened: false,
+
+			cheats_window: CheatManager::new(),
+			cheats_window_opened: false,
+
+			game_profile_window_opened: false,
+
 			frame_build_time: 0.0,
 			frame_render_time: 0.0,
 			full_frame_time: 0.0,
@@ -113,6 +147,8 @@ impl DebugData {
 			dma_transfer_counter: 0,
 
 			unlock_profiler: false,
+
+			rebinding: None,
 		}
 	}
 }
@@ -197,6 +233,10 @@ pub fn render_debugger(gba: &mut Gba) {
 			debugger.memory_window_opened = true;
 		}
 
+		if imgui::menu_item(imstr!("RAM Search")) {
+			debugger.ram_search_window_opened = true;
+		}
+
 		if imgui::menu_item(imstr!("Profiler")) {
 			debugger.profiler_window_opened = true;
 		}
@@ -205,6 +245,48 @@ pub fn render_debugger(gba: &mut Gba) {
 			debugger.pyrite_settings_window_opened = true;
 		}
 
+		if imgui::menu_item(imstr!("Save States")) {
+			debugger.save_slots_window_opened = true;
+			debugger.save_slots_window.dirty = true;
+		}
+
+		if imgui::menu_item(imstr!("Cheats")) {
+			debugger.cheats_window_opened = true;
+		}
+
+		if imgui::menu_item(imstr!("Game Profile")) {
+			debugger.game_profile_window_opened = true;
+		}
+
+		if imgui::menu_item(imstr!("Fast Forward (Shift+{})###fast_forward_menu", key_names("Fast Forward"))) {
+			gba.extras.fast_forward_toggled = !gba.extras.fast_forward_toggled;
+		}
+
+		if imgui::menu_item(imstr!("Slow Motion ({})###slow_motion_menu", key_names("Slow Motion"))) {
+			gba.extras.slow_motion = !gba.extras.slow_motion;
+		}
+
+		if imgui::menu_item(imstr!("Screenshot ({})###screenshot_menu", key_names("Screenshot"))) {
+			gba.extras.request_screenshot = true;
+		}
+
+		if gba.device.recording() {
+			if imgui::menu_item(imstr!("Stop Recording")) {
+				gba.device.stop_recording();
+			}
+		} else if imgui::menu_item(imstr!("Start Recording")) {
+			gba.start_recording();
+		}
+
+		match gba.movie.as_ref().map(|movie| movie.frame) {
+			Some(frame) => if imgui::menu_item(imstr!("Stop Movie (frame {})", frame)) {
+				gba.stop_movie();
+			},
+			None => if imgui::menu_item(imstr!("Record Movie")) {
+				gba.start_movie();
+			}
+		}
+
 		imgui::end_popup();
 	}
 
@@ -241,11 +323,41 @@ pub fn render_debugger(gba: &mut Gba) {
 		imgui::end();
 	}
 
+	if debugger.ram_search_window_opened {
+		imgui::set_next_window_size(imgui::vec2(420.0, 400.0), imgui::ImGuiSetCond::FirstUseEver);
+		imgui::begin(imstr!("RAM Search"), &mut debugger.ram_search_window_opened, imgui::ImGuiWindowFlags_None);
+		debugger.ram_search_window.render(gba);
+		imgui::end();
+	}
+
+	if debugger.save_slots_window_opened {
+		imgui::begin(imstr!("Save States"), &mut debugger.save_slots_window_opened, imgui::ImGuiWindowFlags_None);
+		debugger.save_slots_window.render(gba);
+		imgui::end();
+	}
+
+	if debugger.cheats_window_opened {
+		imgui::begin(imstr!("Cheats"), &mut debugger.cheats_window_opened, imgui::ImGuiWindowFlags_None);
+		debugger.cheats_window.render(gba);
+		imgui::end();
+	}
+
+	if debugger.game_profile_window_opened {
+		imgui::set_next_window_size(imgui::vec2(480.0, 400.0), imgui::ImGuiSetCond::FirstUseEver);
+		imgui::begin(imstr!("Game Profile"), &mut debugger.game_profile_window_opened, imgui::ImGuiWindowFlags_None);
+		game_profile::render_game_profile(gba);
+		imgui::end();
+	}
+
 	if debugger.emulator_performance_opened {
 		imgui::begin(imstr!("Emulator Performance"), &mut debugger.emulator_performance_opened, imgui::ImGuiWindowFlags_None);
 		imgui::text(imstr!("Frame Build Time: {:.2}ms", debugger.frame_build_time));
 		imgui::text(imstr!("Frame Render Time: {:.2}ms", debugger.frame_render_time));
 		imgui::text(imstr!("Frame Time: {:.2}ms", debugger.full_frame_time));
+		imgui::text(imstr!("Speed: {:?}", ::gba::speed::Speed::current(&gba.extras)));
+		imgui::text(imstr!("Frame Skip: {}", gba.frame_skip.skip()));
+		imgui::text(imstr!("Rewind: {:.1}s ({:.1} MB)", gba.rewind.frames() as f32 / 60.0,
+			gba.rewind.memory_used() as f32 / (1024.0 * 1024.0)));
 		
 		debugger.emulator_delay_plot.render_histogram();
 
@@ -341,6 +453,123 @@ pub fn render_pyrite_settings(debugger: &mut DebugData) {
 		sc |= imgui::checkbox(imstr!("Channel A Enabled"), psetting_ptr!(channela_enabled));
 		sc |= imgui::checkbox(imstr!("Channel B Enabled"), psetting_ptr!(channelb_enabled));
 	}
+	if imgui::collapsing_header(imstr!("Speed Settings"), imstr!("settings_speed_clpshr"), false, true) {
+		sc |= imgui::checkbox(imstr!("Frame Limiter"), psetting_ptr!(frame_limit));
+
+		let mut fast_forward = psetting!(fast_forward_speed) as i32;
+		if imgui::slider_int(imstr!("Fast Forward"), &mut fast_forward, 0, 10, imstr!("%.0fx (0 = unlimited)")) {
+			sc |= true;
+			psetting!(fast_forward_speed, fast_forward as u32);
+		}
+
+		let mut slow_motion = psetting!(slow_motion_speed) as i32;
+		if imgui::slider_int(imstr!("Slow Motion"), &mut slow_motion, 10, 90, imstr!("%.0f%%")) {
+			sc |= true;
+			psetting!(slow_motion_speed, slow_motion as u32);
+		}
+
+		sc |= imgui::checkbox(imstr!("VSync (needs a restart)"), psetting_ptr!(vsync));
+
+		let mut frame_skip = psetting!(frame_skip) as i32;
+		if imgui::slider_int(imstr!("Frame Skip"), &mut frame_skip, 0, ::gba::speed::MAX_FRAME_SKIP as i32, imstr!("%.0f")) {
+			sc |= true;
+			psetting!(frame_skip, frame_skip as u32);
+		}
+		sc |= imgui::checkbox(imstr!("Automatic Frame Skip"), psetting_ptr!(auto_frame_skip));
+	}
+	if imgui::collapsing_header(imstr!("System Settings"), imstr!("settings_system_clpshr"), false, true) {
+		sc |= imgui::checkbox(imstr!("Skip BIOS (needs a restart)"), psetting_ptr!(skip_bios));
+	}
+	if imgui::collapsing_header(imstr!("Screenshot Settings"), imstr!("settings_screenshot_clpshr"), false, true) {
+		let mut scale = psetting!(screenshot_scale) as i32;
+		if imgui::slider_int(imstr!("Scale"), &mut scale, 1, ::pyrite::screenshot::MAX_SCALE as i32, imstr!("%.0fx")) {
+			sc |= true;
+			psetting!(screenshot_scale, scale as u32);
+		}
+	}
+	if imgui::collapsing_header(imstr!("Rewind Settings"), imstr!("settings_rewind_clpshr"), false, true) {
+		sc |= imgui::checkbox(imstr!("Rewind Enabled"), psetting_ptr!(rewind_enabled));
+
+		let mut buffer_size = psetting!(rewind_buffer_size) as i32;
+		if imgui::slider_int(imstr!("Memory"), &mut buffer_size, 8, 512, imstr!("%.0f MB")) {
+			sc |= true;
+			psetting!(rewind_buffer_size, buffer_size as u32);
+		}
+
+		let mut interval = psetting!(rewind_interval) as i32;
+		if imgui::slider_int(imstr!("Snapshot Interval"), &mut interval, 1, 10, imstr!("%.0f frames")) {
+			sc |= true;
+			psetting!(rewind_interval, interval as u32);
+		}
+	}
+	if imgui::collapsing_header(imstr!("Input Bindings"), imstr!("settings_bindings_clpshr"), false, true) {
+		imgui::text_disabled(imstr!("Click a key or gamepad input to unbind it, + to bind another one from either."));
+		let mut threshold = psetting!(gamepad_axis_threshold) as i32;
+		if imgui::slider_int(imstr!("Stick Threshold"), &mut threshold, 10, 90, imstr!("%.0f%%")) {
+			sc |= true;
+			psetting!(gamepad_axis_threshold, threshold as u32);
+		}
+
+		let mut turbo_rate = psetting!(turbo_rate) as i32;
+		if imgui::slider_int(imstr!("Turbo Rate"), &mut turbo_rate, 1, 10, imstr!("%.0f frames")) {
+			sc |= true;
+			psetting!(turbo_rate, turbo_rate as u32);
+		}
+
+		imgui::text_disabled(imstr!("Hold shift and press a macro key to record it, then press it again to stop."));
+		let mut clear_macro = None;
+		for (name, frames) in ::pyrite::get_settings().input_macros.iter() {
+			imgui::text(imstr!("{}: {} frames", name, frames.len()));
+			imgui::same_line();
+			if imgui::button_def(imstr!("Clear###clear_macro_{}", name)) {
+				clear_macro = Some(name.clone());
+			}
+		}
+		if let Some(name) = clear_macro {
+			::pyrite::get_settings().input_macros.remove(&name);
+			sc |= true;
+		}
+
+		imgui::columns(3, imstr!("bindings_table"), true);
+		for binding in BINDINGS.iter() {
+			imgui::text(imstr!("{}", binding.name));
+			imgui::next_column();
+
+			let keys = ::pyrite::get_settings().key_bindings.get(binding.name).cloned().unwrap_or(Vec::new());
+			for key in keys {
+				if imgui::button_def(imstr!("{}###unbind_{}_{}", key_name(key), binding.name, key)) {
+					unbind(binding.name, key);
+					sc |= true;
+				}
+				imgui::same_line();
+			}
+			imgui::next_column();
+
+			let inputs = ::pyrite::get_settings().gamepad_bindings.get(binding.name).cloned().unwrap_or(Vec::new());
+			for input in inputs {
+				if imgui::button_def(imstr!("{}###unbind_gamepad_{}_{}", input_name(input), binding.name, input)) {
+					unbind_gamepad(binding.name, input);
+					sc |= true;
+				}
+				imgui::same_line();
+			}
+
+			let listening = debugger.rebinding == Some(binding.name);
+			let label = if listening { "Press a key or button..." } else { "+" };
+			if imgui::button_def(imstr!("{}###bind_{}", label, binding.name)) {
+				debugger.rebinding = if listening { None } else { Some(binding.name) };
+			}
+			imgui::next_column();
+		}
+		imgui::columns(1, imstr!("bindings_table_end"), false);
+
+		if imgui::button_def(imstr!("Reset Bindings")) {
+			::pyrite::get_settings().key_bindings = default_bindings();
+			::pyrite::get_settings().gamepad_bindings = default_gamepad_bindings();
+			debugger.rebinding = None;
+			sc |= true;
+		}
+	}
 	if sc {
 		debugger.delay_saving_pyrite_settings = true;
 		::pyrite::settings_changed()
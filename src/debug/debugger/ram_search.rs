@@ -0,0 +1,258 @@
+use rust_imgui as imgui;
+use rust_imgui::ImVec4;
+use rust_imgui::ImguiTextInputBuffer;
+use ::gba::Gba;
+use ::gba::core::memory::*;
+use ::gba::cheats::codes::Size;
+use super::memory_editor::auto_radix_parse_u32;
+
+const SEARCH_ADDR_COL: ImVec4 = ImVec4 {x: 0.914, y: 0.118, z: 0.388, w: 1.000}; // E91E63
+
+/// Candidates are only listed once there are this few of them.
+const MAX_LISTED_CANDIDATES: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SearchFilter {
+	Unchanged,
+	Changed,
+	Increased,
+	Decreased,
+	EqualTo(u32)
+}
+
+/// Looks for game variables by narrowing down the WRAM and IWRAM addresses
+/// whose values change in a certain way between searches.
+pub struct RamSearch {
+	size: Size,
+	signed: bool,
+
+	/// Addresses that passed every filter so far.
+	candidates: Vec<u32>,
+
+	/// WRAM followed by IWRAM as they were after the last search.
+	snapshot: Vec<u8>,
+	started: bool,
+
+	watches: Vec<(u32, Size, bool)>,
+	value_input_buffer: ImguiTextInputBuffer,
+}
+
+/// Index of an address in a snapshot.
+fn snapshot_index(address: u32) -> usize {
+	if address < MEM_WRAM_C.start {
+		(address - MEM_WRAM_B.start) as usize
+	} else {
+		MEM_WRAM_B.size + (address - MEM_WRAM_C.start) as usize
+	}
+}
+
+fn take_snapshot(memory: &GbaMemory) -> Vec<u8> {
+	let mut snapshot = memory.get_region(MEM_WRAM_B).to_vec();
+	snapshot.extend_from_slice(memory.get_region(MEM_WRAM_C));
+	return snapshot;
+}
+
+fn read_value(memory: &GbaMemory, address: u32, size: Size) -> u32 {
+	match size {
+		Size::Byte => memory.read8(address) as u32,
+		Size::Half => memory.read16(address) as u32,
+		Size::Word => memory.read32(address)
+	}
+}
+
+fn snapshot_value(snapshot: &[u8], address: u32, size: Size) -> u32 {
+	let index = snapshot_index(address);
+	(0..size.bytes() as usize).fold(0, |acc, byte| acc | ((snapshot[index + byte] as u32) << (byte * 8)))
+}
+
+/// Values are compared as i64 so that signed and unsigned words fit.
+fn as_number(value: u32, size: Size, signed: bool) -> i64 {
+	if signed { size.signed(value) as i64 } else { value as i64 }
+}
+
+fn format_value(value: u32, size: Size, signed: bool) -> String {
+	format!("{} (0x{:0width$X})", as_number(value, size, signed), value, width = size.bytes() as usize * 2)
+}
+
+fn size_name(size: Size) -> &'static str {
+	match size {
+		Size::Byte => "8-bit",
+		Size::Half => "16-bit",
+		Size::Word => "32-bit"
+	}
+}
+
+impl RamSearch {
+	pub fn new() -> RamSearch {
+		RamSearch {
+			size: Size::Byte,
+			signed: false,
+			candidates: Vec::new(),
+			snapshot: Vec::new(),
+			started: false,
+			watches: Vec::new(),
+			value_input_buffer: ImguiTextInputBuffer::new(16),
+		}
+	}
+
+	/// Starts over with every aligned address as a candidate.
+	pub fn start(&mut self, memory: &GbaMemory) {
+		let step = self.size.bytes();
+		self.candidates.clear();
+		for region in [MEM_WRAM_B, MEM_WRAM_C].iter() {
+			let mut address = region.start;
+			while address + step - 1 <= region.end {
+				self.candidates.push(address);
+				address += step;
+			}
+		}
+		self.snapshot = take_snapshot(memory);
+		self.started = true;
+	}
+
+	/// Keeps the candidates that pass the filter and takes a new snapshot to compare the next search with.
+	pub fn filter(&mut self, memory: &GbaMemory, filter: SearchFilter) {
+		let size = self.size;
+		let signed = self.signed;
+		let snapshot = &self.snapshot;
+		self.candidates.retain(|&address| {
+			let current = read_value(memory, address, size);
+			let previous = snapshot_value(snapshot, address, size);
+			match filter {
+				SearchFilter::Unchanged => current == previous,
+				SearchFilter::Changed => current != previous,
+				SearchFilter::Increased => as_number(current, size, signed) > as_number(previous, size, signed),
+				SearchFilter::Decreased => as_number(current, size, signed) < as_number(previous, size, signed),
+				SearchFilter::EqualTo(value) => current == (value & size.mask())
+			}
+		});
+		self.snapshot = take_snapshot(memory);
+	}
+
+	/// Reads a number typed in for the current size, negative ones are allowed when searching signed values.
+	fn parse_value(&self, s: &str) -> Option<u32> {
+		let trimmed = s.trim();
+		if trimmed.starts_with("-") {
+			match trimmed[1..].parse::<u32>() {
+				Ok(value) if self.signed => Some((value as i64).wrapping_neg() as u32 & self.size.mask()),
+				_ => None
+			}
+		} else {
+			auto_radix_parse_u32(trimmed)
+		}
+	}
+
+	pub fn render(&mut self, gba: &mut Gba) {
+		if imgui::button_def(imstr!("{}###ram_search_size", size_name(self.size))) {
+			imgui::open_popup(imstr!("ram_search_size_popup"));
+		}
+		if imgui::begin_popup(imstr!("ram_search_size_popup")) {
+			for size in [Size::Byte, Size::Half, Size::Word].iter() {
+				if imgui::menu_item(imstr!("{}", size_name(*size))) && self.size != *size {
+					// The candidates are aligned to the old size.
+					self.size = *size;
+					self.started = false;
+				}
+			}
+			imgui::end_popup();
+		}
+		imgui::same_line();
+		imgui::checkbox(imstr!("Signed"), &mut self.signed);
+		imgui::same_line();
+		if imgui::button_def(imstr!("New Search")) {
+			self.start(&gba.cpu.memory);
+		}
+
+		if !self.started {
+			imgui::text_disabled(imstr!("Start a new search to snapshot WRAM and IWRAM."));
+		} else {
+			let mut filter = None;
+			if imgui::button_def(imstr!("Unchanged")) { filter = Some(SearchFilter::Unchanged); }
+			imgui::same_line();
+			if imgui::button_def(imstr!("Changed")) { filter = Some(SearchFilter::Changed); }
+			imgui::same_line();
+			if imgui::button_def(imstr!("Increased")) { filter = Some(SearchFilter::Increased); }
+			imgui::same_line();
+			if imgui::button_def(imstr!("Decreased")) { filter = Some(SearchFilter::Decreased); }
+
+			imgui::input_text(imstr!("###ram_search_value"), self.value_input_buffer.as_slice(),
+				imgui::ImGuiInputTextFlags_EnterReturnsTrue, None, None);
+			imgui::same_line();
+			if imgui::button_def(imstr!("Equal To")) {
+				match self.parse_value(self.value_input_buffer.as_str()) {
+					Some(value) => filter = Some(SearchFilter::EqualTo(value)),
+					None => console_warn!("`{}` isn't a value that can be searched for.", self.value_input_buffer.as_str())
+				}
+			}
+
+			if let Some(filter) = filter {
+				self.filter(&gba.cpu.memory, filter);
+			}
+
+			imgui::text(imstr!("{} candidates", self.candidates.len()));
+			if self.candidates.len() <= MAX_LISTED_CANDIDATES {
+				self.render_candidates(gba);
+			}
+		}
+
+		if imgui::collapsing_header(imstr!("Watch List"), imstr!("ram_search_watch_clpshr"), true, true) {
+			self.render_watches(gba);
+		}
+	}
+
+	fn render_candidates(&mut self, gba: &mut Gba) {
+		let mut watch = None;
+		imgui::columns(4, imstr!("ram_search_candidates"), true);
+		for &address in self.candidates.iter() {
+			imgui::push_style_color(imgui::ImGuiCol::Text, SEARCH_ADDR_COL);
+			imgui::text(imstr!("{:08X}", address));
+			imgui::pop_style_color(1);
+			imgui::next_column();
+			imgui::text(imstr!("{}", format_value(read_value(&gba.cpu.memory, address, self.size), self.size, self.signed)));
+			imgui::next_column();
+			imgui::text_disabled(imstr!("{}", format_value(snapshot_value(&self.snapshot, address, self.size), self.size, self.signed)));
+			imgui::next_column();
+			if imgui::button_def(imstr!("Watch###ram_search_watch_{:08X}", address)) {
+				watch = Some(address);
+			}
+			imgui::next_column();
+		}
+		imgui::columns(1, imstr!("ram_search_candidates_end"), false);
+
+		if let Some(address) = watch {
+			if !self.watches.iter().any(|&(a, _, _)| a == address) {
+				self.watches.push((address, self.size, self.signed));
+			}
+		}
+	}
+
+	fn render_watches(&mut self, gba: &mut Gba) {
+		let mut remove = None;
+		imgui::columns(4, imstr!("ram_search_watches"), true);
+		for (idx, &(address, size, signed)) in self.watches.iter().enumerate() {
+			let value = read_value(&gba.cpu.memory, address, size);
+			imgui::push_style_color(imgui::ImGuiCol::Text, SEARCH_ADDR_COL);
+			imgui::text(imstr!("{:08X}", address));
+			imgui::pop_style_color(1);
+			imgui::next_column();
+			imgui::text(imstr!("{} {}", format_value(value, size, signed), size_name(size)));
+			imgui::next_column();
+			let mut frozen = gba.cheats.is_frozen(address);
+			if imgui::checkbox(imstr!("Freeze###ram_search_freeze_{}", idx), &mut frozen) {
+				if frozen { gba.cheats.freeze(address, size, value); }
+				else { gba.cheats.unfreeze(address); }
+			}
+			imgui::next_column();
+			if imgui::button_def(imstr!("Remove###ram_search_remove_{}", idx)) {
+				remove = Some(idx);
+			}
+			imgui::next_column();
+		}
+		imgui::columns(1, imstr!("ram_search_watches_end"), false);
+
+		if let Some(idx) = remove {
+			gba.cheats.unfreeze(self.watches[idx].0);
+			self.watches.remove(idx);
+		}
+	}
+}
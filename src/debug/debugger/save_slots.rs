@@ -0,0 +1,107 @@
+use rust_imgui as imgui;
+use rust_imgui::ImVec4;
+use rust_imgui::ImGuiSelectableFlags_SpanAllColumns;
+use ::gba::Gba;
+use ::gba::serialization::{StateMeta, THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT};
+use ::pyrite::save_slots::*;
+
+const SLOT_SELECTED_COL: ImVec4 = ImVec4 {x: 1.000, y: 0.702, z: 0.000, w: 1.000}; // FFB300
+const SLOT_EMPTY_COL: ImVec4 = ImVec4 {x: 0.620, y: 0.620, z: 0.620, w: 1.000}; // 9E9E9E
+
+/// Size of each thumbnail pixel on screen.
+const THUMBNAIL_SCALE: f32 = 2.0;
+
+pub struct SaveSlotBrowser {
+	slots: Vec<SlotStatus>,
+	selected: usize,
+
+	/// The ROM that the slots were read for.
+	rom_name: String,
+
+	/// Set when a slot was written and the descriptions have to be read again.
+	pub dirty: bool,
+}
+
+impl SaveSlotBrowser {
+	pub fn new() -> SaveSlotBrowser {
+		SaveSlotBrowser {
+			slots: Vec::new(),
+			selected: 1,
+			rom_name: String::new(),
+			dirty: true,
+		}
+	}
+
+	pub fn render(&mut self, gba: &mut Gba) {
+		if self.dirty || self.rom_name != gba.extras.rom_name {
+			self.rom_name = gba.extras.rom_name.clone();
+			self.slots = read_slots(&self.rom_name);
+			self.dirty = false;
+		}
+
+		imgui::text_disabled(imstr!("The slot keys load a state, with Shift held they save one."));
+
+		for (idx, status) in self.slots.iter().enumerate() {
+			let slot = idx + 1;
+			let label = match *status {
+				SlotStatus::Empty => "Empty".to_string(),
+				SlotStatus::NoDescription => "Saved".to_string(),
+				SlotStatus::Used(ref meta) => {
+					format!("{}  [{}]", format_timestamp(meta.timestamp), format_play_time(meta.play_time))
+				},
+				SlotStatus::Unreadable(_) => "Unreadable".to_string(),
+			};
+
+			let color = if slot == self.selected {
+				Some(SLOT_SELECTED_COL)
+			} else if let SlotStatus::Empty = *status {
+				Some(SLOT_EMPTY_COL)
+			} else {
+				None
+			};
+			let colored = color.is_some();
+			if let Some(color) = color { imgui::push_style_color(imgui::ImGuiCol::Text, color); }
+			if imgui::selectable_fl(imstr!("F{:<2}  {}###save_slot_{}", slot, label, slot), ImGuiSelectableFlags_SpanAllColumns) {
+				self.selected = slot;
+			}
+			if colored { imgui::pop_style_color(1); }
+
+			if let SlotStatus::Unreadable(ref reason) = *status {
+				if imgui::is_item_hovered() {
+					imgui::begin_tooltip();
+					imgui::text(imstr!("{}", reason));
+					imgui::end_tooltip();
+				}
+			}
+		}
+
+		if imgui::button_def(imstr!("Save")) {
+			gba.extras.request_save_slot = Some(self.selected);
+		}
+		imgui::same_line();
+		if imgui::button_def(imstr!("Load")) {
+			gba.extras.request_load_slot = Some(self.selected);
+		}
+
+		if let Some(&SlotStatus::Used(ref meta)) = self.slots.get(self.selected - 1) {
+			render_thumbnail(meta);
+		}
+	}
+}
+
+/// ImGui has no textures set up for images, so every pixel is drawn as a rectangle.
+fn render_thumbnail(meta: &StateMeta) {
+	if meta.thumbnail.len() != THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT * 3 { return }
+
+	imgui::dummy(imgui::vec2(THUMBNAIL_WIDTH as f32 * THUMBNAIL_SCALE, THUMBNAIL_HEIGHT as f32 * THUMBNAIL_SCALE));
+	let mut origin = imgui::vec2(0.0, 0.0);
+	imgui::get_item_rect_min(&mut origin);
+
+	let dlist = imgui::get_window_draw_list().expect("Failed to get window draw list.");
+	for (idx, rgb) in meta.thumbnail.chunks(3).enumerate() {
+		let x = origin.x + (idx % THUMBNAIL_WIDTH) as f32 * THUMBNAIL_SCALE;
+		let y = origin.y + (idx / THUMBNAIL_WIDTH) as f32 * THUMBNAIL_SCALE;
+		let color = 0xFF000000 | ((rgb[2] as u32) << 16) | ((rgb[1] as u32) << 8) | (rgb[0] as u32);
+		dlist.add_rect_filled_simple(imgui::vec2(x, y), imgui::vec2(x + THUMBNAIL_SCALE, y + THUMBNAIL_SCALE), color);
+	}
+}
@@ -0,0 +1,615 @@
+//! Turns the codes of each cheat device into a list of operations.
+//!
+//! GameShark (Action Replay v1/v2) and Action Replay v3 codes are encrypted with TEA, each using
+//! its own default seeds. Codes that change those seeds (DEADFACE) aren't supported yet.
+//! CodeBreaker codes are only encrypted after a type 9 code, which holds the key for the codes
+//! after it in the same cheat. Master codes only tell the real device where to hook the game,
+//! nothing has to be hooked here because cheats are applied at the start of every frame.
+
+use super::CheatFormat;
+
+const GAMESHARK_SEEDS: [u32; 4] = [0x09F4FBBD, 0x9681884A, 0x352027E9, 0xF3DEE5A7];
+pub const ACTION_REPLAY_SEEDS: [u32; 4] = [0x7AA9648F, 0x7FAE6994, 0xC0EFAAD5, 0x42712C57];
+
+const TEA_DELTA: u32 = 0x9E3779B9;
+
+/// The value of the game ID code that starts most code lists.
+const GAME_ID_VALUE: u32 = 0x001DC0DE;
+
+/// Cheats that write to ROM are looked up with this added to their offset.
+pub const ROM_START: u32 = 0x08000000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Size {
+	Byte,
+	Half,
+	Word
+}
+
+impl Size {
+	pub fn bytes(&self) -> u32 {
+		match *self {
+			Size::Byte => 1,
+			Size::Half => 2,
+			Size::Word => 4
+		}
+	}
+
+	pub fn mask(&self) -> u32 {
+		match *self {
+			Size::Byte => 0xff,
+			Size::Half => 0xffff,
+			Size::Word => 0xffffffff
+		}
+	}
+
+	/// Sign extends a value of this size to 32 bits.
+	pub fn signed(&self, value: u32) -> i32 {
+		let shift = 32 - self.bytes() * 8;
+		((value << shift) as i32) >> shift
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WriteMode {
+	Set,
+	Add,
+	Or,
+	And
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Condition {
+	Equal,
+	NotEqual,
+	LessSigned,
+	GreaterSigned,
+	Less,
+	Greater,
+
+	/// True if any of the value's bits are set in memory.
+	And
+}
+
+impl Condition {
+	pub fn check(&self, size: Size, memory_value: u32, value: u32) -> bool {
+		match *self {
+			Condition::Equal => memory_value == value,
+			Condition::NotEqual => memory_value != value,
+			Condition::LessSigned => size.signed(memory_value) < size.signed(value),
+			Condition::GreaterSigned => size.signed(memory_value) > size.signed(value),
+			Condition::Less => memory_value < value,
+			Condition::Greater => memory_value > value,
+			Condition::And => (memory_value & value) != 0
+		}
+	}
+}
+
+/// What is skipped when a condition is false.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Skip {
+	/// This many of the operations that follow. Each code compiles to one operation so this is
+	/// also the number of codes.
+	Next(usize),
+
+	/// Everything up to the matching `Else` or `EndIf`.
+	Block,
+
+	/// The rest of the cheat.
+	Rest
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+	Write { address: u32, size: Size, value: u32, mode: WriteMode },
+
+	/// Sets each address to its value, for codes that write to a list of places.
+	Writes { size: Size, writes: Vec<(u32, u32)> },
+
+	/// Writes `count` values, moving the address and the value by their steps after each one.
+	Slide { address: u32, size: Size, value: u32, count: u32, address_step: u32, value_step: u32 },
+
+	/// Writes to the address stored at `pointer` plus `offset`.
+	PointerWrite { pointer: u32, offset: u32, size: Size, value: u32 },
+
+	If { address: u32, size: Size, condition: Condition, value: u32, skip: Skip },
+
+	/// True while all of the buttons in the mask are held.
+	IfButtons { buttons: u16, skip: Skip },
+
+	Else,
+	EndIf,
+
+	/// Replaces a halfword of the ROM, only reads see it.
+	RomPatch { offset: u32, value: u16 },
+
+	/// A code that does nothing here (game IDs, master codes, blank lines) but still counts
+	/// for a condition in front of it.
+	Nothing
+}
+
+/// Reads the codes of a cheat into operations.
+pub fn compile(format: CheatFormat, code: &str) -> Result<Vec<Op>, String> {
+	match format {
+		CheatFormat::GameShark => {
+			let lines = try!(parse_lines(code, 8));
+			gameshark_ops(&lines.iter().map(|&(a, v)| decrypt(a, v, &GAMESHARK_SEEDS)).collect::<Vec<_>>())
+		},
+		CheatFormat::ActionReplay => {
+			let lines = try!(parse_lines(code, 8));
+			action_replay_ops(&lines.iter().map(|&(a, v)| decrypt(a, v, &ACTION_REPLAY_SEEDS)).collect::<Vec<_>>())
+		},
+		CheatFormat::CodeBreaker => {
+			let lines = try!(parse_lines(code, 4));
+			codebreaker_ops(&decrypt_codebreaker(&lines))
+		}
+	}
+}
+
+/// Splits codes into an 8 digit address and a value with `value_digits` digits.
+/// The two halves can be separated by whitespace or not, codes don't have to be on separate lines.
+fn parse_lines(code: &str, value_digits: usize) -> Result<Vec<(u32, u32)>, String> {
+	let mut digits = Vec::new();
+	for token in code.split_whitespace() {
+		for c in token.chars() {
+			match c.to_digit(16) {
+				Some(d) => digits.push(d),
+				None => if c != ':' && c != '-' {
+					return Err(format!("`{}` isn't a hexadecimal number.", token))
+				}
+			}
+		}
+	}
+
+	let line_digits = 8 + value_digits;
+	if digits.is_empty() { return Err("There are no codes.".to_string()) }
+	if digits.len() % line_digits != 0 {
+		return Err(format!("Codes should have {} digits each.", line_digits))
+	}
+
+	Ok(digits.chunks(line_digits).map(|line| {
+		let address = line[..8].iter().fold(0u32, |acc, &d| (acc << 4) | d);
+		let value = line[8..].iter().fold(0u32, |acc, &d| (acc << 4) | d);
+		(address, value)
+	}).collect())
+}
+
+pub fn decrypt(address: u32, value: u32, seeds: &[u32; 4]) -> (u32, u32) {
+	let mut address = address;
+	let mut value = value;
+	let mut sum = TEA_DELTA.wrapping_mul(32);
+	for _ in 0..32 {
+		value = value.wrapping_sub(
+			(address << 4).wrapping_add(seeds[2]) ^ address.wrapping_add(sum) ^ (address >> 5).wrapping_add(seeds[3]));
+		address = address.wrapping_sub(
+			(value << 4).wrapping_add(seeds[0]) ^ value.wrapping_add(sum) ^ (value >> 5).wrapping_add(seeds[1]));
+		sum = sum.wrapping_sub(TEA_DELTA);
+	}
+	(address, value)
+}
+
+#[cfg(test)]
+pub fn encrypt(address: u32, value: u32, seeds: &[u32; 4]) -> (u32, u32) {
+	let mut address = address;
+	let mut value = value;
+	let mut sum = 0u32;
+	for _ in 0..32 {
+		sum = sum.wrapping_add(TEA_DELTA);
+		address = address.wrapping_add(
+			(value << 4).wrapping_add(seeds[0]) ^ value.wrapping_add(sum) ^ (value >> 5).wrapping_add(seeds[1]));
+		value = value.wrapping_add(
+			(address << 4).wrapping_add(seeds[2]) ^ address.wrapping_add(sum) ^ (address >> 5).wrapping_add(seeds[3]));
+	}
+	(address, value)
+}
+
+#[cfg(test)]
+pub fn encrypt_gameshark(address: u32, value: u32) -> (u32, u32) {
+	encrypt(address, value, &GAMESHARK_SEEDS)
+}
+
+#[cfg(test)]
+pub fn encrypt_action_replay(address: u32, value: u32) -> (u32, u32) {
+	encrypt(address, value, &ACTION_REPLAY_SEEDS)
+}
+
+/// The random number generator that CodeBreaker makes its keys with.
+struct CodeBreakerRng(u32);
+
+impl CodeBreakerRng {
+	fn step(&mut self) -> u32 {
+		self.0 = self.0.wrapping_mul(0x41C64E6D).wrapping_add(0x3039);
+		self.0
+	}
+
+	/// Puts together 32 bits from the top 15 bits of three steps.
+	fn next(&mut self) -> u32 {
+		let x = self.step() >> 16;
+		let y = (self.step() >> 16) & 0x7fff;
+		let z = (self.step() >> 16) & 0x7fff;
+		(x << 30) | (y << 15) | z
+	}
+
+	/// Starts over from the next number instead of the current state, `rounds` times.
+	fn skip(&mut self, rounds: u32) {
+		for _ in 0..rounds {
+			self.0 = self.next();
+		}
+	}
+}
+
+/// The key from a CodeBreaker seed code (9xxxxxxx yyyy). A code is encrypted by swapping its
+/// 48 bits around, XORing it with the seeds and mixing each byte into its neighbours.
+/// TODO check this against codes from a real CodeBreaker, the tests only decrypt codes they encrypted.
+struct CodeBreakerKey {
+	/// The bit that each bit is swapped with.
+	swaps: [u8; 48],
+	seeds: [u32; 4],
+
+	/// The seed code's address, its low two bytes are mixed into every byte.
+	mix: u32
+}
+
+impl CodeBreakerKey {
+	fn new(address: u32, value: u32) -> CodeBreakerKey {
+		let mut swaps = [0u8; 48];
+		for (idx, swap) in swaps.iter_mut().enumerate() {
+			*swap = idx as u8;
+		}
+		let mut rng = CodeBreakerRng((value & 0xff) ^ 0x1111);
+		for _ in 0..80 {
+			let a = (rng.next() % 48) as usize;
+			let b = (rng.next() % 48) as usize;
+			swaps.swap(a, b);
+		}
+
+		let mut rng = CodeBreakerRng(0x4EFAD1C3);
+		rng.skip((address >> 24) & 0xf);
+		let (seed2, seed3) = (rng.next(), rng.next());
+
+		let rounds = (value >> 8) & 0xff;
+		let mut rng = CodeBreakerRng(rounds ^ 0xF254);
+		rng.skip(rounds);
+		let (seed0, seed1) = (rng.next(), rng.next());
+
+		CodeBreakerKey { swaps: swaps, seeds: [seed0, seed1, seed2, seed3], mix: address }
+	}
+
+	fn decrypt(&self, address: u32, value: u32) -> (u32, u32) {
+		let mut bytes = codebreaker_bytes(address, value);
+		for bit in (0..48).rev() {
+			swap_bits(&mut bytes, bit, self.swaps[bit] as usize);
+		}
+		bytes = self.xor_seeds(bytes, 0);
+
+		let (low, high) = (self.mix as u8, (self.mix >> 8) as u8);
+		for idx in 0..5 {
+			bytes[idx] ^= high ^ bytes[idx + 1];
+		}
+		bytes[5] ^= high;
+		for idx in (0..6).rev() {
+			let previous = if idx > 0 { bytes[idx - 1] } else { 0 };
+			bytes[idx] ^= low ^ previous;
+		}
+
+		codebreaker_code(self.xor_seeds(bytes, 2))
+	}
+
+	#[cfg(test)]
+	fn encrypt(&self, address: u32, value: u32) -> (u32, u32) {
+		let mut bytes = self.xor_seeds(codebreaker_bytes(address, value), 2);
+
+		let (low, high) = (self.mix as u8, (self.mix >> 8) as u8);
+		for idx in 0..6 {
+			let previous = if idx > 0 { bytes[idx - 1] } else { 0 };
+			bytes[idx] ^= low ^ previous;
+		}
+		bytes[5] ^= high;
+		for idx in (0..5).rev() {
+			bytes[idx] ^= high ^ bytes[idx + 1];
+		}
+
+		bytes = self.xor_seeds(bytes, 0);
+		for bit in 0..48 {
+			swap_bits(&mut bytes, bit, self.swaps[bit] as usize);
+		}
+		codebreaker_code(bytes)
+	}
+
+	/// XORs the address with `seeds[first]` and the value with `seeds[first + 1]`.
+	fn xor_seeds(&self, bytes: [u8; 6], first: usize) -> [u8; 6] {
+		let (address, value) = codebreaker_code(bytes);
+		codebreaker_bytes(address ^ self.seeds[first], (value ^ self.seeds[first + 1]) & 0xffff)
+	}
+}
+
+/// A CodeBreaker code as 6 big endian bytes, the address followed by the value.
+fn codebreaker_bytes(address: u32, value: u32) -> [u8; 6] {
+	[(address >> 24) as u8, (address >> 16) as u8, (address >> 8) as u8, address as u8, (value >> 8) as u8, value as u8]
+}
+
+fn codebreaker_code(bytes: [u8; 6]) -> (u32, u32) {
+	let address = bytes[..4].iter().fold(0u32, |acc, &b| (acc << 8) | b as u32);
+	let value = ((bytes[4] as u32) << 8) | bytes[5] as u32;
+	(address, value)
+}
+
+/// Bit 0 is the lowest bit of the first byte.
+fn swap_bits(bytes: &mut [u8; 6], a: usize, b: usize) {
+	let bit_a = (bytes[a >> 3] >> (a & 7)) & 1;
+	let bit_b = (bytes[b >> 3] >> (b & 7)) & 1;
+	if bit_a != bit_b {
+		bytes[a >> 3] ^= 1 << (a & 7);
+		bytes[b >> 3] ^= 1 << (b & 7);
+	}
+}
+
+/// Takes out the seed codes (type 9) and decrypts every code that comes after one.
+pub fn decrypt_codebreaker(lines: &[(u32, u32)]) -> Vec<(u32, u32)> {
+	let mut key: Option<CodeBreakerKey> = None;
+	let mut decrypted = Vec::with_capacity(lines.len());
+	for &(address, value) in lines.iter() {
+		let (address, value) = match key {
+			Some(ref k) => k.decrypt(address, value),
+			None => (address, value)
+		};
+		if address >> 28 == 0x9 {
+			key = Some(CodeBreakerKey::new(address, value));
+		} else {
+			decrypted.push((address, value));
+		}
+	}
+	return decrypted;
+}
+
+/// Encrypts CodeBreaker codes with the key from the seed code, which comes first.
+#[cfg(test)]
+pub fn encrypt_codebreaker(seed: (u32, u32), lines: &[(u32, u32)]) -> Vec<(u32, u32)> {
+	let key = CodeBreakerKey::new(seed.0, seed.1);
+	let mut encrypted = vec![seed];
+	encrypted.extend(lines.iter().map(|&(a, v)| key.encrypt(a, v)));
+	return encrypted;
+}
+
+fn set(address: u32, size: Size, value: u32) -> Op {
+	Op::Write { address: address, size: size, value: value & size.mask(), mode: WriteMode::Set }
+}
+
+fn unknown_code(device: &str, address: u32, value: u32) -> String {
+	format!("{:08X} {:08X} isn't a {} code that is supported.", address, value, device)
+}
+
+/// GameShark and Action Replay v1/v2 codes, already decrypted.
+fn gameshark_ops(lines: &[(u32, u32)]) -> Result<Vec<Op>, String> {
+	let mut ops = Vec::new();
+	let mut idx = 0;
+	while idx < lines.len() {
+		let (address, value) = lines[idx];
+		idx += 1;
+
+		if address == 0xDEADFACE {
+			return Err("Codes that change the encryption seeds (DEADFACE) aren't supported.".to_string())
+		}
+		if value == GAME_ID_VALUE {
+			ops.push(Op::Nothing);
+			continue
+		}
+
+		let target = address & 0x0FFFFFFF;
+		match address >> 28 {
+			0x0 => ops.push(set(target, Size::Byte, value)),
+			0x1 => ops.push(set(target, Size::Half, value)),
+			0x2 => ops.push(set(target, Size::Word, value)),
+
+			// 3000cccc xxxxxxxx, writes the value to the cccc addresses in the lines that follow, two to a line.
+			0x3 => {
+				let count = (address & 0xffff) as usize;
+				let needed = (count + 1) / 2;
+				if idx + needed > lines.len() {
+					return Err(format!("{:08X} {:08X} needs {} more lines of addresses.", address, value, needed))
+				}
+				let writes = (0..count).map(|n| {
+					let (first, second) = lines[idx + n / 2];
+					(if n % 2 == 0 { first } else { second }, value)
+				}).collect();
+				ops.push(Op::Writes { size: Size::Word, writes: writes });
+				idx += needed;
+			},
+
+			// 6aaaaaaa 0000xxxx, aaaaaaa is the offset in halfwords.
+			0x6 => ops.push(Op::RomPatch { offset: (target << 1) & 0x01FFFFFF, value: value as u16 }),
+
+			// Daaaaaaa 0000xxxx, runs the next code if the halfword is equal.
+			0xD => ops.push(Op::If {
+				address: target, size: Size::Half, condition: Condition::Equal,
+				value: value & 0xffff, skip: Skip::Next(1)
+			}),
+
+			// E0zzxxxx aaaaaaaa, runs the next zz codes if the halfword is equal.
+			0xE => ops.push(Op::If {
+				address: value & 0x0FFFFFFF, size: Size::Half, condition: Condition::Equal,
+				value: address & 0xffff, skip: Skip::Next(((address >> 16) & 0xff) as usize)
+			}),
+
+			// Master code.
+			0xF => ops.push(Op::Nothing),
+
+			_ => return Err(unknown_code("GameShark", address, value))
+		}
+	}
+	return Ok(ops);
+}
+
+/// Action Replay v3 addresses keep the region in bits 20-23.
+fn action_replay_address(address: u32) -> u32 {
+	((address & 0x00F00000) << 4) | (address & 0x000FFFFF)
+}
+
+/// Action Replay v3 codes, already decrypted.
+/// The top byte of the address is the code type: bits 1-2 are the size, bits 3-5 the condition
+/// and bits 6-7 either what a write does or how much a condition skips.
+fn action_replay_ops(lines: &[(u32, u32)]) -> Result<Vec<Op>, String> {
+	let mut ops = Vec::new();
+	let mut idx = 0;
+	while idx < lines.len() {
+		let (address, value) = lines[idx];
+		idx += 1;
+
+		if value == GAME_ID_VALUE {
+			ops.push(Op::Nothing);
+			continue
+		}
+
+		// Special codes, the type is in the value instead.
+		if address == 0 {
+			match value >> 24 {
+				0x00 if value == 0 => ops.push(Op::Nothing),
+				0x40 => ops.push(Op::EndIf),
+				0x60 => ops.push(Op::Else),
+
+				// 00000000 18aaaaaa, the next line holds the halfword to write in its address.
+				0x18 | 0x1A | 0x1C | 0x1E => {
+					if idx >= lines.len() {
+						return Err(format!("{:08X} {:08X} needs the value on the next line.", address, value))
+					}
+					let (patch, _) = lines[idx];
+					idx += 1;
+					ops.push(Op::RomPatch { offset: (value & 0x00FFFFFF) << 1, value: patch as u16 });
+				},
+
+				_ => return Err(unknown_code("Action Replay", address, value))
+			}
+			continue
+		}
+
+		let kind = address >> 24;
+		let target = action_replay_address(address);
+		let size = match (kind >> 1) & 3 {
+			0 => Size::Byte,
+			1 => Size::Half,
+			2 => Size::Word,
+			_ => return Err(unknown_code("Action Replay", address, value))
+		};
+
+		let condition = match (kind >> 3) & 7 {
+			0 => None,
+			1 => Some(Condition::Equal),
+			2 => Some(Condition::NotEqual),
+			3 => Some(Condition::LessSigned),
+			4 => Some(Condition::GreaterSigned),
+			5 => Some(Condition::Less),
+			6 => Some(Condition::Greater),
+			_ => Some(Condition::And)
+		};
+
+		match condition {
+			Some(condition) => {
+				let skip = match kind >> 6 {
+					0 => Skip::Next(1),
+					1 => Skip::Next(2),
+					2 => Skip::Block,
+					_ => Skip::Rest
+				};
+				ops.push(Op::If { address: target, size: size, condition: condition, value: value & size.mask(), skip: skip });
+			},
+
+			None => match (kind >> 6, size) {
+				// 00aaaaaa xxxxxxyy and 02aaaaaa xxxxyyyy fill xxxxxx + 1 values.
+				(0, Size::Byte) => ops.push(Op::Slide {
+					address: target, size: size, value: value & 0xff,
+					count: (value >> 8) + 1, address_step: 1, value_step: 0
+				}),
+				(0, Size::Half) => ops.push(Op::Slide {
+					address: target, size: size, value: value & 0xffff,
+					count: (value >> 16) + 1, address_step: 2, value_step: 0
+				}),
+				(0, Size::Word) => ops.push(set(target, size, value)),
+
+				// 40aaaaaa xxyyyyyy and 42aaaaaa xxxxyyyy write to the pointer plus an offset in values.
+				(1, Size::Byte) => ops.push(Op::PointerWrite { pointer: target, offset: value >> 8, size: size, value: value & 0xff }),
+				(1, Size::Half) => ops.push(Op::PointerWrite { pointer: target, offset: (value >> 16) << 1, size: size, value: value & 0xffff }),
+				(1, Size::Word) => ops.push(Op::PointerWrite { pointer: target, offset: 0, size: size, value: value }),
+
+				(2, _) => ops.push(Op::Write { address: target, size: size, value: value & size.mask(), mode: WriteMode::Add }),
+
+				// C4aaaaaa, the master code.
+				(3, Size::Word) => ops.push(Op::Nothing),
+
+				_ => return Err(unknown_code("Action Replay", address, value))
+			}
+		}
+	}
+	return Ok(ops);
+}
+
+/// CodeBreaker codes, which aren't encrypted unless they start with a type 9 code.
+fn codebreaker_ops(lines: &[(u32, u32)]) -> Result<Vec<Op>, String> {
+	let mut ops = Vec::new();
+	let mut idx = 0;
+	while idx < lines.len() {
+		let (address, value) = lines[idx];
+		idx += 1;
+
+		let target = address & 0x0FFFFFFF;
+		let half_if = |condition| Op::If {
+			address: target, size: Size::Half, condition: condition, value: value, skip: Skip::Next(1)
+		};
+		let modify = |mode| Op::Write { address: target, size: Size::Half, value: value, mode: mode };
+
+		match address >> 28 {
+			// Game ID and hook codes.
+			0x0 | 0x1 => ops.push(Op::Nothing),
+
+			0x2 => ops.push(modify(WriteMode::Or)),
+			0x3 => ops.push(set(target, Size::Byte, value)),
+
+			// 4aaaaaaa xxxx followed by iiiicccc ssss, writes cccc halfwords starting at xxxx
+			// adding iiii to the value and ssss to the address after each one.
+			0x4 => {
+				if idx >= lines.len() {
+					return Err(format!("{:08X} {:04X} needs a second line.", address, value))
+				}
+				let (counts, address_step) = lines[idx];
+				idx += 1;
+				ops.push(Op::Slide {
+					address: target, size: Size::Half, value: value, count: counts & 0xffff,
+					address_step: address_step, value_step: counts >> 16
+				});
+			},
+
+			// 5aaaaaaa cccc followed by cccc bytes, six to a line.
+			0x5 => {
+				let count = value as usize;
+				let needed = (count + 5) / 6;
+				if idx + needed > lines.len() {
+					return Err(format!("{:08X} {:04X} needs {} more lines of bytes.", address, value, needed))
+				}
+				let writes = (0..count).map(|n| {
+					let (high, low) = lines[idx + n / 6];
+					let byte = match n % 6 {
+						b @ 0...3 => high >> (24 - b * 8),
+						b => low >> (8 - (b - 4) * 8)
+					};
+					(target + n as u32, byte & 0xff)
+				}).collect();
+				ops.push(Op::Writes { size: Size::Byte, writes: writes });
+				idx += needed;
+			},
+
+			0x6 => ops.push(modify(WriteMode::And)),
+			0x7 => ops.push(half_if(Condition::Equal)),
+			0x8 => ops.push(set(target, Size::Half, value)),
+			0xA => ops.push(half_if(Condition::NotEqual)),
+			0xB => ops.push(half_if(Condition::Greater)),
+			0xC => ops.push(half_if(Condition::Less)),
+
+			// D0000020 xxxx, runs the next code while the buttons are held.
+			0xD if target == 0x20 => ops.push(Op::IfButtons { buttons: value as u16, skip: Skip::Next(1) }),
+
+			0xE => ops.push(modify(WriteMode::Add)),
+			0xF => ops.push(half_if(Condition::And)),
+			_ => return Err(format!("{:08X} {:04X} isn't a CodeBreaker code that is supported.", address, value))
+		}
+	}
+	return Ok(ops);
+}
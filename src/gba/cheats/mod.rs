@@ -0,0 +1,299 @@
+//! Cheat codes for GameShark, Action Replay and CodeBreaker devices.
+//!
+//! Every cheat is compiled into a list of operations (see `codes.rs`) which are run at the
+//! start of every frame. Memory written by a cheat is frozen until the next frame: writes
+//! from the game to it are dropped, and ROM patches are only seen by reads (see `GbaMemory::cheat_write8`).
+//! Cheats are turned off while a movie is recording or playing because movies don't store them.
+//!
+//! The cheats for each game are kept as JSON in `data/cheats`, see `cheats_path`.
+
+pub mod codes;
+
+#[cfg(test)]
+mod tests;
+
+use std::io::prelude::*;
+use std::fs::{File, create_dir_all};
+use std::path::{Path, PathBuf};
+use rustc_serialize::json;
+use super::core::memory::*;
+use self::codes::{Op, Size, Skip, WriteMode, ROM_START};
+
+#[derive(RustcEncodable, RustcDecodable, Debug, Clone, Copy, PartialEq)]
+pub enum CheatFormat {
+	/// GameShark and Action Replay v1/v2, they use the same codes.
+	GameShark,
+
+	ActionReplay,
+	CodeBreaker
+}
+
+pub const CHEAT_FORMATS: [CheatFormat; 3] = [CheatFormat::GameShark, CheatFormat::ActionReplay, CheatFormat::CodeBreaker];
+
+impl CheatFormat {
+	pub fn name(&self) -> &'static str {
+		match *self {
+			CheatFormat::GameShark => "GameShark / Action Replay v1/v2",
+			CheatFormat::ActionReplay => "Action Replay v3",
+			CheatFormat::CodeBreaker => "CodeBreaker"
+		}
+	}
+}
+
+#[derive(RustcEncodable, RustcDecodable, Debug, Clone)]
+pub struct Cheat {
+	pub name: String,
+	pub format: CheatFormat,
+
+	/// The codes as they were typed in.
+	pub code: String,
+
+	pub enabled: bool
+}
+
+pub const CHEATS_DIR: &'static str = "data/cheats";
+
+/// Cheats are kept for each game by the game code in its header, so every copy and revision of
+/// a game shares them. ROMs without a game code (homebrew, test ROMs) use their file name instead.
+pub fn cheats_path(game_code: &str, rom_name: &str) -> PathBuf {
+	let game_code = game_code.trim();
+	let name = if !game_code.is_empty() {
+		game_code
+	} else if !rom_name.is_empty() {
+		rom_name
+	} else {
+		"pyrite"
+	};
+	Path::new(CHEATS_DIR).join(format!("{}.json", name))
+}
+
+/// Where the cheats for a ROM were kept before they were shared by the game code.
+fn old_cheats_path(rom_name: &str) -> PathBuf {
+	let rom_name = if rom_name.is_empty() { "pyrite" } else { rom_name };
+	Path::new(::pyrite::save_slots::SAVE_STATE_DIR).join(rom_name).join("cheats.json")
+}
+
+pub struct CheatEngine {
+	cheats: Vec<Cheat>,
+
+	/// The operations of each cheat or why its codes couldn't be read.
+	compiled: Vec<Result<Vec<Op>, String>>,
+
+	/// Values held in place from the RAM search, they aren't saved with the cheats.
+	freezes: Vec<(u32, Size, u32)>,
+
+	/// Where the cheats are saved, see `cheats_path`.
+	path: PathBuf
+}
+
+impl CheatEngine {
+	pub fn new() -> CheatEngine {
+		CheatEngine {
+			cheats: Vec::new(),
+			compiled: Vec::new(),
+			freezes: Vec::new(),
+			path: cheats_path("", "")
+		}
+	}
+
+	pub fn cheats(&self) -> &[Cheat] {
+		&self.cheats
+	}
+
+	/// Why a cheat's codes couldn't be read, it's never applied if they can't.
+	pub fn error(&self, index: usize) -> Option<&str> {
+		match self.compiled.get(index) {
+			Some(&Err(ref e)) => Some(&e[..]),
+			_ => None
+		}
+	}
+
+	/// Replaces the cheats with the ones saved for a game, a game without any just has none.
+	/// Cheats that were saved for the ROM's file name are picked up if the game has none yet.
+	pub fn load(&mut self, game_code: &str, rom_name: &str) -> Result<(), String> {
+		self.path = cheats_path(game_code, rom_name);
+		self.cheats.clear();
+		self.compiled.clear();
+
+		let path = if self.path.exists() { self.path.clone() } else { old_cheats_path(rom_name) };
+		if !path.exists() { return Ok(()) }
+
+		let mut buffer = String::new();
+		let mut f = try!(File::open(&path).map_err(|e| format!("Failed to open {}: {}", path.display(), e)));
+		try!(f.read_to_string(&mut buffer).map_err(|e| format!("Failed to read {}: {}", path.display(), e)));
+		let cheats: Vec<Cheat> = try!(json::decode(&buffer).map_err(|e| format!("Failed to read {}: {}", path.display(), e)));
+		for cheat in cheats.into_iter() {
+			self.push(cheat);
+		}
+		return Ok(());
+	}
+
+	/// Where the cheats are saved.
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	pub fn save(&self) -> Result<(), String> {
+		let path = &self.path;
+		if let Some(dir) = path.parent() {
+			try!(create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e)));
+		}
+		let mut f = try!(File::create(&path).map_err(|e| format!("Failed to create {}: {}", path.display(), e)));
+		try!(write!(f, "{}", json::as_pretty_json(&self.cheats)).map_err(|e| format!("Failed to write {}: {}", path.display(), e)));
+		return Ok(());
+	}
+
+	/// Adds a cheat if its codes can be read.
+	pub fn add(&mut self, cheat: Cheat) -> Result<(), String> {
+		try!(codes::compile(cheat.format, &cheat.code));
+		self.push(cheat);
+		return Ok(());
+	}
+
+	fn push(&mut self, cheat: Cheat) {
+		self.compiled.push(codes::compile(cheat.format, &cheat.code));
+		self.cheats.push(cheat);
+	}
+
+	pub fn remove(&mut self, index: usize) {
+		if index < self.cheats.len() {
+			self.cheats.remove(index);
+			self.compiled.remove(index);
+		}
+	}
+
+	pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+		if let Some(cheat) = self.cheats.get_mut(index) {
+			cheat.enabled = enabled;
+		}
+	}
+
+	/// Keeps a value in memory like a cheat that writes it would.
+	pub fn freeze(&mut self, address: u32, size: Size, value: u32) {
+		self.unfreeze(address);
+		self.freezes.push((address, size, value & size.mask()));
+	}
+
+	pub fn unfreeze(&mut self, address: u32) {
+		self.freezes.retain(|&(a, _, _)| a != address);
+	}
+
+	pub fn is_frozen(&self, address: u32) -> bool {
+		self.freezes.iter().any(|&(a, _, _)| a == address)
+	}
+
+	/// Runs every enabled cheat, called at the start of each frame.
+	pub fn apply(&self, memory: &mut GbaMemory) {
+		memory.clear_cheats();
+		for (cheat, compiled) in self.cheats.iter().zip(self.compiled.iter()) {
+			if !cheat.enabled { continue }
+			if let Ok(ref ops) = *compiled {
+				run(ops, memory);
+			}
+		}
+		for &(address, size, value) in self.freezes.iter() {
+			write(memory, address, size, value);
+		}
+	}
+}
+
+fn read(memory: &GbaMemory, address: u32, size: Size) -> u32 {
+	match size {
+		Size::Byte => memory.read8(address) as u32,
+		Size::Half => memory.read16(address) as u32,
+		Size::Word => memory.read32(address)
+	}
+}
+
+fn write(memory: &mut GbaMemory, address: u32, size: Size, value: u32) {
+	for byte in 0..size.bytes() {
+		memory.cheat_write8(address.wrapping_add(byte), (value >> (byte * 8)) as u8);
+	}
+}
+
+/// Finds the `Else` or `EndIf` that ends the block that starts after `start`.
+fn end_of_block(ops: &[Op], start: usize, stop_at_else: bool) -> usize {
+	let mut depth = 0;
+	for idx in (start + 1)..ops.len() {
+		match ops[idx] {
+			Op::If { skip: Skip::Block, .. } | Op::IfButtons { skip: Skip::Block, .. } => depth += 1,
+			Op::Else if depth == 0 && stop_at_else => return idx,
+			Op::EndIf if depth == 0 => return idx,
+			Op::EndIf => depth -= 1,
+			_ => {}
+		}
+	}
+	return ops.len();
+}
+
+fn run(ops: &[Op], memory: &mut GbaMemory) {
+	let buttons = !memory.get_reg(ioreg::KEYINPUT) & 0x3ff;
+
+	let mut idx = 0;
+	while idx < ops.len() {
+		let skip = match ops[idx] {
+			Op::Write { address, size, value, mode } => {
+				let value = match mode {
+					WriteMode::Set => value,
+					WriteMode::Add => read(memory, address, size).wrapping_add(value),
+					WriteMode::Or => read(memory, address, size) | value,
+					WriteMode::And => read(memory, address, size) & value
+				};
+				write(memory, address, size, value);
+				None
+			},
+
+			Op::Slide { address, size, value, count, address_step, value_step } => {
+				let mut address = address;
+				let mut value = value;
+				for _ in 0..count {
+					write(memory, address, size, value);
+					address = address.wrapping_add(address_step);
+					value = value.wrapping_add(value_step);
+				}
+				None
+			},
+
+			Op::Writes { size, ref writes } => {
+				for &(address, value) in writes.iter() {
+					write(memory, address, size, value);
+				}
+				None
+			},
+
+			Op::PointerWrite { pointer, offset, size, value } => {
+				let address = memory.read32(pointer).wrapping_add(offset);
+				write(memory, address, size, value);
+				None
+			},
+
+			Op::If { address, size, condition, value, skip } => {
+				if condition.check(size, read(memory, address, size), value) { None } else { Some(skip) }
+			},
+
+			Op::IfButtons { buttons: wanted, skip } => {
+				if (buttons & wanted) == wanted { None } else { Some(skip) }
+			},
+
+			// The true side of an if ends here.
+			Op::Else => {
+				idx = end_of_block(ops, idx, false);
+				None
+			},
+
+			Op::EndIf | Op::Nothing => None,
+
+			Op::RomPatch { offset, value } => {
+				write(memory, ROM_START + offset, Size::Half, value as u32);
+				None
+			}
+		};
+
+		match skip {
+			None => idx += 1,
+			Some(Skip::Next(count)) => idx += count + 1,
+			Some(Skip::Block) => idx = end_of_block(ops, idx, true) + 1,
+			Some(Skip::Rest) => return
+		}
+	}
+}
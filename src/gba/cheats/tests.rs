@@ -0,0 +1,209 @@
+use super::super::core::memory::*;
+use super::codes::{self, encrypt_gameshark, encrypt_action_replay, encrypt_codebreaker, decrypt_codebreaker};
+use super::{Cheat, CheatEngine, CheatFormat};
+
+fn gameshark(lines: &[(u32, u32)]) -> String {
+	lines.iter().map(|&(a, v)| {
+		let (a, v) = encrypt_gameshark(a, v);
+		format!("{:08X} {:08X}", a, v)
+	}).collect::<Vec<_>>().join("\n")
+}
+
+fn action_replay(lines: &[(u32, u32)]) -> String {
+	lines.iter().map(|&(a, v)| {
+		let (a, v) = encrypt_action_replay(a, v);
+		format!("{:08X}{:08X}", a, v)
+	}).collect::<Vec<_>>().join(" ")
+}
+
+fn engine_with(format: CheatFormat, code: String) -> CheatEngine {
+	let mut engine = CheatEngine::new();
+	engine.add(Cheat { name: "test".to_string(), format: format, code: code, enabled: true }).unwrap();
+	return engine;
+}
+
+#[test]
+fn gameshark_codes_freeze_memory_for_a_frame() {
+	let mut memory = GbaMemory::new();
+	let engine = engine_with(CheatFormat::GameShark, gameshark(&[
+		(0xF8000100, 0x00000001), // master code
+		(0x12000010, 0x0000BEEF),
+		(0x03007F00, 0x00000042)
+	]));
+
+	engine.apply(&mut memory);
+	assert_eq!(memory.read16(0x02000010), 0xBEEF);
+	assert_eq!(memory.read8(0x03007F00), 0x42);
+
+	// The game can't change frozen memory until the cheats are cleared.
+	memory.write16(0x02000010, 0x1234);
+	assert_eq!(memory.read16(0x02000010), 0xBEEF);
+	memory.clear_cheats();
+	memory.write16(0x02000010, 0x1234);
+	assert_eq!(memory.read16(0x02000010), 0x1234);
+}
+
+#[test]
+fn rom_patches_are_only_seen_by_reads() {
+	let mut memory = GbaMemory::new();
+	memory.set_rom(vec![0u8; 0x200]);
+	let engine = engine_with(CheatFormat::GameShark, gameshark(&[(0x60000080, 0x0000ABCD)]));
+
+	engine.apply(&mut memory);
+	assert_eq!(memory.read16(0x08000100), 0xABCD);
+	assert!(memory.rom_writes().is_empty(), "Save states shouldn't store cheats.");
+	memory.clear_cheats();
+	assert_eq!(memory.read16(0x08000100), 0);
+}
+
+#[test]
+fn action_replay_blocks_take_one_side() {
+	let code = action_replay(&[
+		(0x88200010, 0x00000005), // if the byte at 02000010 is 5
+		(0x04200020, 0x11111111),
+		(0x00000000, 0x60000000), // else
+		(0x04200020, 0x22222222),
+		(0x00000000, 0x40000000)  // end if
+	]);
+	let engine = engine_with(CheatFormat::ActionReplay, code);
+
+	let mut memory = GbaMemory::new();
+	memory.write8(0x02000010, 5);
+	engine.apply(&mut memory);
+	assert_eq!(memory.read32(0x02000020), 0x11111111);
+
+	memory.clear_cheats();
+	memory.write8(0x02000010, 6);
+	engine.apply(&mut memory);
+	assert_eq!(memory.read32(0x02000020), 0x22222222);
+}
+
+#[test]
+fn codebreaker_conditions_and_buttons() {
+	let engine = engine_with(CheatFormat::CodeBreaker, [
+		"72000000 0001", // if the halfword at 02000000 is 1
+		"32000002 0077",
+		"D0000020 0001", // while A is held
+		"82000004 1111",
+		"E2000006 0002"  // add 2
+	].join("\n"));
+
+	let mut memory = GbaMemory::new();
+	memory.set_reg(ioreg::KEYINPUT, 0x3ff);
+	engine.apply(&mut memory);
+	assert_eq!(memory.read8(0x02000002), 0);
+	assert_eq!(memory.read16(0x02000004), 0);
+	assert_eq!(memory.read16(0x02000006), 2);
+
+	memory.write16(0x02000000, 1);
+	memory.set_reg(ioreg::KEYINPUT, 0x3fe);
+	engine.apply(&mut memory);
+	assert_eq!(memory.read8(0x02000002), 0x77);
+	assert_eq!(memory.read16(0x02000004), 0x1111);
+	assert_eq!(memory.read16(0x02000006), 4);
+}
+
+#[test]
+fn conditions_skip_whole_codes() {
+	let codebreaker = engine_with(CheatFormat::CodeBreaker, [
+		"72000000 0001", // if the halfword at 02000000 is 1
+		"52000010 0008", // write 8 bytes
+		"11223344 5566",
+		"77880000 0000",
+		"32000002 0077"
+	].join("\n"));
+	let gameshark = engine_with(CheatFormat::GameShark, gameshark(&[
+		(0xD2000000, 0x00000001), // if the halfword at 02000000 is 1
+		(0x30000003, 0x12345678), // write the word to 3 addresses
+		(0x02000020, 0x02000024),
+		(0x02000028, 0x00000000),
+		(0x02000004, 0x00000077)
+	]));
+	let action_replay = engine_with(CheatFormat::ActionReplay, action_replay(&[
+		(0x48200000, 0x00000001), // if the byte at 02000000 is 1, run the next 2 codes
+		(0x00000000, 0x00000000),
+		(0x04200030, 0x11111111),
+		(0x04200034, 0x22222222)
+	]));
+
+	let mut memory = GbaMemory::new();
+	for engine in [&codebreaker, &gameshark, &action_replay].iter() {
+		engine.apply(&mut memory);
+	}
+	for address in 0x02000010..0x02000018 {
+		assert_eq!(memory.read8(address), 0);
+	}
+	assert_eq!(memory.read8(0x02000002), 0x77);
+	assert_eq!(memory.read32(0x02000024), 0);
+	assert_eq!(memory.read32(0x02000028), 0);
+	assert_eq!(memory.read8(0x02000004), 0x77);
+	assert_eq!(memory.read32(0x02000030), 0);
+	assert_eq!(memory.read32(0x02000034), 0x22222222);
+
+	memory.clear_cheats();
+	memory.write16(0x02000000, 1);
+	for engine in [&codebreaker, &gameshark, &action_replay].iter() {
+		engine.apply(&mut memory);
+	}
+	assert_eq!(memory.read8(0x02000010), 0x11);
+	assert_eq!(memory.read8(0x02000017), 0x88);
+	assert_eq!(memory.read32(0x02000024), 0x12345678);
+	assert_eq!(memory.read32(0x02000028), 0x12345678);
+	assert_eq!(memory.read32(0x02000030), 0x11111111);
+}
+
+#[test]
+fn tea_matches_the_published_test_vector() {
+	// GameShark and Action Replay v3 codes are plain TEA with the address and the value as the two halves.
+	assert_eq!(codes::decrypt(0x41EA3A0A, 0x94BAA940, &[0, 0, 0, 0]), (0, 0));
+}
+
+#[test]
+fn action_replay_codes_decrypt_like_the_device() {
+	// The published Action Replay v3 master code for Pokemon Emerald (BPEE).
+	let code = "D8BAE4D9 4864DCE5\nA86CDBA5 19BA49B3";
+	assert_eq!(codes::decrypt(0xD8BAE4D9, 0x4864DCE5, &codes::ACTION_REPLAY_SEEDS), (0xC40005EC, 0x00008401));
+	assert_eq!(codes::decrypt(0xA86CDBA5, 0x19BA49B3, &codes::ACTION_REPLAY_SEEDS), (0x45455042, 0x001DC0DE));
+	assert!(codes::compile(CheatFormat::ActionReplay, code).is_ok());
+}
+
+#[test]
+fn encrypted_codebreaker_codes_round_trip() {
+	let plain = [
+		(0x32000002, 0x0077),
+		(0x82000004, 0x1111),
+		(0x42000010, 0x0100), (0x00020003, 0x0004), // a slide over two lines
+		(0xE2000006, 0xFFFE),
+		(0x00000000, 0x0000),
+		(0xFFFFFFFF, 0xFFFF)
+	];
+	for &seed in [(0x9123ABCD, 0x0000), (0x9F00C0DE, 0x5A3C), (0x90000000, 0xFFFF)].iter() {
+		let encrypted = encrypt_codebreaker(seed, &plain);
+		assert!(encrypted[1..].iter().zip(plain.iter()).any(|(e, p)| e != p), "Nothing was encrypted with {:?}", seed);
+		assert_eq!(decrypt_codebreaker(&encrypted), plain.to_vec());
+	}
+}
+
+#[test]
+fn encrypted_codebreaker_codes_work_like_plain_ones() {
+	let encrypted = encrypt_codebreaker((0x9E5D1A2B, 0x4C01), &[(0x32000002, 0x0077), (0x82000004, 0x1111)]);
+	let code = encrypted.iter().map(|&(a, v)| format!("{:08X} {:04X}", a, v)).collect::<Vec<_>>().join("\n");
+	let engine = engine_with(CheatFormat::CodeBreaker, code);
+
+	let mut memory = GbaMemory::new();
+	engine.apply(&mut memory);
+	assert_eq!(memory.read8(0x02000002), 0x77);
+	assert_eq!(memory.read16(0x02000004), 0x1111);
+}
+
+#[test]
+fn unreadable_codes_are_rejected() {
+	let mut engine = CheatEngine::new();
+	let bad = |format, code: &str| Cheat { name: "bad".to_string(), format: format, code: code.to_string(), enabled: true };
+	assert!(engine.add(bad(CheatFormat::GameShark, "1234")).is_err());
+	assert!(engine.add(bad(CheatFormat::CodeBreaker, "nothex!! 0000")).is_err());
+	assert!(engine.add(bad(CheatFormat::CodeBreaker, "D0000030 0000")).is_err());
+	assert!(engine.cheats().is_empty());
+
+	assert!(codes::compile(CheatFormat::GameShark, &gameshark(&[(0xDEADFACE, 0x00001234)])).is_err());
+}
@@ -35,7 +35,8 @@ pub fn arm_alu_sbcs(cpu: &mut ArmCpu, lhs: u32, rhs: u32) -> u32 {
 
 pub fn arm_alu_rscs(cpu: &mut ArmCpu, lhs: u32, rhs: u32) -> u32 {
 	let res = arm_alu_rsc(cpu, lhs, rhs);
-	set_sub_flags(cpu, lhs, rhs, res);
+	// The operands are reversed so the flags are those of rhs - lhs.
+	set_sub_flags(cpu, rhs, lhs, res);
 	res
 }
 
@@ -53,7 +54,8 @@ pub fn arm_alu_subs(cpu: &mut ArmCpu, lhs: u32, rhs: u32) -> u32 {
 
 pub fn arm_alu_rsbs(cpu: &mut ArmCpu, lhs: u32, rhs: u32) -> u32 {
 	let res = rhs - lhs;
-	set_sub_flags(cpu, lhs, rhs, res);
+	// The operands are reversed so the flags are those of rhs - lhs.
+	set_sub_flags(cpu, rhs, lhs, res);
 	res
 }
 
@@ -182,9 +184,9 @@ pub fn arm_alu_rrr(lhs: u32, rhs: u32) -> u32 {
 	if rhs == 0 { return lhs }
 	// ROR by n where n is greater than 32 will give the same result and carry out as ROR by n-32; 
 	// therefore repeatedly subtract 32 from n until the amount is in the range 1 to 32 and see above.
-	let rhs = rhs & 31; // This might not be right?
-	// ROR by 32 has result equal to Rm, carry out equal to bit 31 of Rm.
-	if rhs == 32 { lhs }
+	let rhs = rhs & 31;
+	// ROR by 32 (or any multiple of it) has result equal to Rm, carry out equal to bit 31 of Rm.
+	if rhs == 0 { lhs }
 	else { lhs.arm_ror(rhs) }
 }
 
@@ -279,6 +281,7 @@ pub fn arm_alu_rri_s(cpu: &mut ArmCpu, lhs: u32, rhs: u32) -> u32 {
 	// The form of the shift field which might be expected to give ROR #0 
 	// is used to encode a special function of the barrel shifter, rotate right extended (RRX)
 	if rhs == 0 { return arm_alu_rrx_s(cpu, lhs) }
+	cpu.registers.putfi_c((lhs >> (rhs - 1)) & 1);
 	lhs.arm_ror(rhs)
 }
 
@@ -288,9 +291,9 @@ pub fn arm_alu_rrr_s(cpu: &mut ArmCpu, lhs: u32, rhs: u32) -> u32 {
 	if rhs == 0 { return lhs }
 	// ROR by n where n is greater than 32 will give the same result and carry out as ROR by n-32; 
 	// therefore repeatedly subtract 32 from n until the amount is in the range 1 to 32 and see above.
-	let rhs = rhs & 31; // This might not be right?
-	// ROR by 32 has result equal to Rm, carry out equal to bit 31 of Rm.
-	if rhs == 32 { cpu.registers.putfi_c(lhs & 0x80000000); lhs }
+	let rhs = rhs & 31;
+	// ROR by 32 (or any multiple of it) has result equal to Rm, carry out equal to bit 31 of Rm.
+	if rhs == 0 { cpu.registers.putfi_c(lhs & 0x80000000); lhs }
 	else {
 		cpu.registers.putfi_c((lhs >> (rhs - 1)) & 1);
 		lhs.arm_ror(rhs)
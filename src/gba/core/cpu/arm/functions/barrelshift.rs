@@ -73,7 +73,11 @@ pub fn arm_fn_op2_rrr(cpu: &mut ArmCpu, instr: u32) -> u32 {
 /// subject to a rotate right by twice the value in the rotate field. This enables many
 /// common constants to be generated, for example all powers of 2.
 pub fn arm_fn_op2_imm_s(cpu: &mut ArmCpu, instr: u32) -> u32 {
-	arm_fn_op2_imm(cpu, instr)
+	let value = arm_fn_op2_imm(cpu, instr);
+	// A rotate of zero passes the old carry through,
+	// otherwise the carry out is bit 31 of the rotated value.
+	if (instr & 0xf00) != 0 { cpu.registers.putfi_c(value & 0x80000000); }
+	value
 }
 
 pub fn arm_fn_op2_lli_s(cpu: &mut ArmCpu, instr: u32) -> u32 {
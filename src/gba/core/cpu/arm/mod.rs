@@ -1,10 +1,11 @@
 pub mod functions;
 pub mod arm_impl;
 
-#[cfg(not(feature = "instr-match"))]
+// Tests build both dispatchers so that they can be compared.
+#[cfg(any(test, not(feature = "instr-match")))]
 pub mod table;
 
-#[cfg(feature = "instr-match")]
+#[cfg(any(test, feature = "instr-match"))]
 pub mod table_match;
 
 use super::ArmCpu;
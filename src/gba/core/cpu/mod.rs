@@ -4,6 +4,9 @@ pub mod alu;
 pub mod clock;
 pub mod registers;
 
+#[cfg(test)]
+mod tests;
+
 use super::memory::*;
 use self::registers::*;
 use self::arm::execute_arm;
@@ -96,21 +99,21 @@ impl ArmCpu {
 	/// all of the correct data ends up on the correct data bus (basically byte)
 	pub fn mread8_al(&self, address: u32) -> u32 {
 		// # TODO alignment shouldn't be necessary on these so I should remove it. (maybe)
-		self.memory.read8(address) as u32
+		self.bus_read8(address) as u32
 	}
 
 	/// Reads a signed 8 bit value from memory and makes sure that
 	/// all of the correct data ends up on the correct data bus (basically byte)
 	pub fn mread8_signed_al(&self, address: u32) -> u32 {
 		// # TODO alignment shouldn't be necessary on these so I should remove it.(maybe)
-		((self.memory.read8(address) as i8) as i32) as u32
+		((self.bus_read8(address) as i8) as i32) as u32
 	}
 
 	/// Reads an unsigned 16 bit value from memory and makes sure that
 	/// all of the correct data ends up on the correct data bus (basically byte)
 	pub fn mread16_al(&self, address: u32) -> u32 {
 		// # TODO alignment shouldn't be necessary on these so I should remove it.(maybe)
-		let data = self.memory.read16(address & 0xFFFFFFFE); // make sure what we retrieve is word aligned.
+		let data = self.bus_read16(address & 0xFFFFFFFE); // make sure what we retrieve is word aligned.
 		let offset = (address & 0x1) * 8; // offset from the word boundary in bits.
 		((data << (16 - offset)) | (data >> offset)) as u32 // rotate right by offset.
 		// self.memory.read16(address) as u32
@@ -120,13 +123,13 @@ impl ArmCpu {
 	/// all of the correct data ends up on the correct data bus (basically byte)
 	pub fn mread16_signed_al(&self, address: u32) -> u32 {
 		// # TODO alignment shouldn't be necessary on these so I should remove it.(maybe)
-		((self.memory.read16(address) as i16) as i32) as u32
+		((self.bus_read16(address) as i16) as i32) as u32
 	}
 
 	/// Reads an unsigned 32 bit value from memory and makes sure that
 	/// all of the correct data ends up on the correct data bus (basically byte)
 	pub fn mread32_al(&self, address: u32) -> u32 {
-		let data = self.memory.read32(address & 0xFFFFFFFC); // make sure what we retrieve is word aligned.
+		let data = self.bus_read32(address & 0xFFFFFFFC); // make sure what we retrieve is word aligned.
 		let offset = (address & 0x3) * 8; // offset from the word boundary in bits.
 		(data << (32 - offset)) | (data >> offset) // rotate right by offset.
 	}
@@ -144,15 +147,67 @@ impl ArmCpu {
 	// }
 
 	pub fn mwrite8(&mut self, address: u32, value: u8) {
-		self.memory.write8(address, value);
+		self.bus_write8(address, value);
 	}
 
 	pub fn mwrite16(&mut self, address: u32, value: u16) {
-		self.memory.write16(address, value);
+		self.bus_write16(address, value);
 	}
 
 	pub fn mwrite32(&mut self, address: u32, value: u32) {
-		self.memory.write32(address, value);
+		self.bus_write32(address, value);
+	}
+
+	// Every data access made by an instruction goes through these.
+	// Test builds check for a mock bus first so that single instructions
+	// can be run without the GBA memory map.
+
+	#[cfg(not(test))] #[inline(always)]
+	fn bus_read8(&self, address: u32) -> u8 { self.memory.read8(address) }
+
+	#[cfg(not(test))] #[inline(always)]
+	fn bus_read16(&self, address: u32) -> u16 { self.memory.read16(address) }
+
+	#[cfg(not(test))] #[inline(always)]
+	fn bus_read32(&self, address: u32) -> u32 { self.memory.read32(address) }
+
+	#[cfg(not(test))] #[inline(always)]
+	fn bus_write8(&mut self, address: u32, value: u8) { self.memory.write8(address, value); }
+
+	#[cfg(not(test))] #[inline(always)]
+	fn bus_write16(&mut self, address: u32, value: u16) { self.memory.write16(address, value); }
+
+	#[cfg(not(test))] #[inline(always)]
+	fn bus_write32(&mut self, address: u32, value: u32) { self.memory.write32(address, value); }
+
+	#[cfg(test)]
+	fn bus_read8(&self, address: u32) -> u8 {
+		tests::bus::read8(address).unwrap_or_else(|| self.memory.read8(address))
+	}
+
+	#[cfg(test)]
+	fn bus_read16(&self, address: u32) -> u16 {
+		tests::bus::read16(address).unwrap_or_else(|| self.memory.read16(address))
+	}
+
+	#[cfg(test)]
+	fn bus_read32(&self, address: u32) -> u32 {
+		tests::bus::read32(address).unwrap_or_else(|| self.memory.read32(address))
+	}
+
+	#[cfg(test)]
+	fn bus_write8(&mut self, address: u32, value: u8) {
+		if !tests::bus::write8(address, value) { self.memory.write8(address, value); }
+	}
+
+	#[cfg(test)]
+	fn bus_write16(&mut self, address: u32, value: u16) {
+		if !tests::bus::write16(address, value) { self.memory.write16(address, value); }
+	}
+
+	#[cfg(test)]
+	fn bus_write32(&mut self, address: u32, value: u32) {
+		if !tests::bus::write32(address, value) { self.memory.write32(address, value); }
 	}
 
 	fn arm_tick(&mut self) {
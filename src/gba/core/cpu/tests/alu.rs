@@ -0,0 +1,194 @@
+use super::super::ArmCpu;
+use super::super::alu::*;
+use super::super::arm::functions::*;
+use super::*;
+
+const VECTORS_PATH: &'static str = "tests/vectors/alu.txt";
+
+fn check_failures(what: &str, total: usize, failures: Vec<String>) {
+	assert!(total > 0, "No {} vectors found in {}.", what, VECTORS_PATH);
+	assert!(failures.is_empty(), "{} of {} {} vectors failed:\n{}", failures.len(), total, what, failures.join("\n"));
+}
+
+/// Runs the shift without touching the flags.
+fn shift(cpu: &ArmCpu, op: &str, value: u32, amount: u32) -> u32 {
+	match op {
+		"lsl_imm" => arm_alu_lli(value, amount),
+		"lsr_imm" => arm_alu_lri(value, amount),
+		"asr_imm" => arm_alu_ari(value, amount),
+		"ror_imm" => arm_alu_rri(cpu, value, amount),
+		"lsl_reg" => arm_alu_llr(value, amount),
+		"lsr_reg" => arm_alu_lrr(value, amount),
+		"asr_reg" => arm_alu_arr(value, amount),
+		"ror_reg" => arm_alu_rrr(value, amount),
+		_ => panic!("Unknown shift `{}` in test vectors.", op)
+	}
+}
+
+/// Runs the shift and sets the carry to the shifter's carry out.
+fn shift_s(cpu: &mut ArmCpu, op: &str, value: u32, amount: u32) -> u32 {
+	match op {
+		"lsl_imm" => arm_alu_lli_s(cpu, value, amount),
+		"lsr_imm" => arm_alu_lri_s(cpu, value, amount),
+		"asr_imm" => arm_alu_ari_s(cpu, value, amount),
+		"ror_imm" => arm_alu_rri_s(cpu, value, amount),
+		"lsl_reg" => arm_alu_llr_s(cpu, value, amount),
+		"lsr_reg" => arm_alu_lrr_s(cpu, value, amount),
+		"asr_reg" => arm_alu_arr_s(cpu, value, amount),
+		"ror_reg" => arm_alu_rrr_s(cpu, value, amount),
+		_ => panic!("Unknown shift `{}` in test vectors.", op)
+	}
+}
+
+/// Runs a data processing instruction with the S bit set,
+/// returning None for the ones that only set flags.
+fn dataproc_s(cpu: &mut ArmCpu, op: &str, lhs: u32, rhs: u32) -> Option<u32> {
+	match op {
+		"and" => Some(arm_fn_and_s(cpu, lhs, rhs)),
+		"eor" => Some(arm_fn_eor_s(cpu, lhs, rhs)),
+		"sub" => Some(arm_fn_sub_s(cpu, lhs, rhs)),
+		"rsb" => Some(arm_fn_rsb_s(cpu, lhs, rhs)),
+		"add" => Some(arm_fn_add_s(cpu, lhs, rhs)),
+		"adc" => Some(arm_fn_adc_s(cpu, lhs, rhs)),
+		"sbc" => Some(arm_fn_sbc_s(cpu, lhs, rhs)),
+		"rsc" => Some(arm_fn_rsc_s(cpu, lhs, rhs)),
+		"tst" => { arm_fn_tst_s(cpu, lhs, rhs); None },
+		"teq" => { arm_fn_teq_s(cpu, lhs, rhs); None },
+		"cmp" => { arm_fn_cmp_s(cpu, lhs, rhs); None },
+		"cmn" => { arm_fn_cmn_s(cpu, lhs, rhs); None },
+		"orr" => Some(arm_fn_orr_s(cpu, lhs, rhs)),
+		"mov" => Some(arm_fn_mov_s(cpu, lhs, rhs)),
+		"bic" => Some(arm_fn_bic_s(cpu, lhs, rhs)),
+		"mvn" => Some(arm_fn_mvn_s(cpu, lhs, rhs)),
+		_ => panic!("Unknown data processing op `{}` in test vectors.", op)
+	}
+}
+
+/// Runs a data processing instruction without the S bit.
+fn dataproc(cpu: &ArmCpu, op: &str, lhs: u32, rhs: u32) -> Option<u32> {
+	match op {
+		"and" => Some(arm_fn_and(cpu, lhs, rhs)),
+		"eor" => Some(arm_fn_eor(cpu, lhs, rhs)),
+		"sub" => Some(arm_fn_sub(cpu, lhs, rhs)),
+		"rsb" => Some(arm_fn_rsb(cpu, lhs, rhs)),
+		"add" => Some(arm_fn_add(cpu, lhs, rhs)),
+		"adc" => Some(arm_fn_adc(cpu, lhs, rhs)),
+		"sbc" => Some(arm_fn_sbc(cpu, lhs, rhs)),
+		"rsc" => Some(arm_fn_rsc(cpu, lhs, rhs)),
+		"orr" => Some(arm_fn_orr(cpu, lhs, rhs)),
+		"mov" => Some(arm_fn_mov(cpu, lhs, rhs)),
+		"bic" => Some(arm_fn_bic(cpu, lhs, rhs)),
+		"mvn" => Some(arm_fn_mvn(cpu, lhs, rhs)),
+		_ => None
+	}
+}
+
+#[test]
+fn barrel_shifter() {
+	let source = load_vectors(VECTORS_PATH);
+	let vectors = vector_lines(&source, "shift");
+	let mut cpu = new_cpu();
+	let mut failures = Vec::new();
+
+	for v in vectors.iter() {
+		let (op, value, amount, c_in) = (v.fields[0], hex(v.fields[1]), dec(v.fields[2]), dec(v.fields[3]));
+		let (expected, expected_c) = (hex(v.fields[4]), dec(v.fields[5]));
+
+		cpu.registers.putfi_c(c_in);
+		let result = shift(&cpu, op, value, amount);
+		if result != expected {
+			failures.push(format!("line {}: {} {:08x} by {}: expected {:08x}, got {:08x}", v.line, op, value, amount, expected, result));
+		}
+		if cpu.registers.getfi_c() != c_in {
+			failures.push(format!("line {}: {} {:08x} by {}: the flagless shift changed C", v.line, op, value, amount));
+		}
+
+		cpu.registers.putfi_c(c_in);
+		let result = shift_s(&mut cpu, op, value, amount);
+		let c_out = cpu.registers.getfi_c();
+		if result != expected || c_out != expected_c {
+			failures.push(format!("line {}: {}_s {:08x} by {} (C={}): expected {:08x} C={}, got {:08x} C={}",
+				v.line, op, value, amount, c_in, expected, expected_c, result, c_out));
+		}
+	}
+
+	check_failures("shift", vectors.len(), failures);
+}
+
+#[test]
+fn rotated_immediates() {
+	let source = load_vectors(VECTORS_PATH);
+	let vectors = vector_lines(&source, "imm");
+	let mut cpu = new_cpu();
+	let mut failures = Vec::new();
+
+	for v in vectors.iter() {
+		let (field, c_in, expected, expected_c) = (hex(v.fields[0]), dec(v.fields[1]), hex(v.fields[2]), dec(v.fields[3]));
+
+		cpu.registers.putfi_c(c_in);
+		let result = arm_fn_op2_imm(&mut cpu, field);
+		if result != expected || cpu.registers.getfi_c() != c_in {
+			failures.push(format!("line {}: imm {:03x}: expected {:08x}, got {:08x}", v.line, field, expected, result));
+		}
+
+		cpu.registers.putfi_c(c_in);
+		let result = arm_fn_op2_imm_s(&mut cpu, field);
+		let c_out = cpu.registers.getfi_c();
+		if result != expected || c_out != expected_c {
+			failures.push(format!("line {}: imm_s {:03x} (C={}): expected {:08x} C={}, got {:08x} C={}",
+				v.line, field, c_in, expected, expected_c, result, c_out));
+		}
+	}
+
+	check_failures("rotated immediate", vectors.len(), failures);
+}
+
+#[test]
+fn data_processing() {
+	let source = load_vectors(VECTORS_PATH);
+	let vectors = vector_lines(&source, "dp");
+	let mut cpu = new_cpu();
+	let mut failures = Vec::new();
+
+	for v in vectors.iter() {
+		let (op, lhs, rhs, flags_in) = (v.fields[0], hex(v.fields[1]), hex(v.fields[2]), hex(v.fields[3]));
+		let expected = if v.fields[4] == "-" { None } else { Some(hex(v.fields[4])) };
+		let expected_flags = hex(v.fields[5]);
+
+		set_nzcv(&mut cpu, flags_in);
+		let result = dataproc(&cpu, op, lhs, rhs);
+		if expected.is_some() && result != expected {
+			failures.push(format!("line {}: {} {:08x}, {:08x} (NZCV={:x}): expected {:08x}, got {:?}",
+				v.line, op, lhs, rhs, flags_in, expected.unwrap(), result));
+		}
+
+		set_nzcv(&mut cpu, flags_in);
+		let result = dataproc_s(&mut cpu, op, lhs, rhs);
+		let flags = get_nzcv(&cpu);
+		if result != expected || flags != expected_flags {
+			failures.push(format!("line {}: {}s {:08x}, {:08x} (NZCV={:x}): expected {:?} NZCV={:x}, got {:?} NZCV={:x}",
+				v.line, op, lhs, rhs, flags_in, expected, expected_flags, result, flags));
+		}
+	}
+
+	check_failures("data processing", vectors.len(), failures);
+}
+
+#[test]
+fn condition_codes() {
+	let source = load_vectors(VECTORS_PATH);
+	let vectors = vector_lines(&source, "cond");
+	let mut cpu = new_cpu();
+	let mut failures = Vec::new();
+
+	for v in vectors.iter() {
+		let (cond, flags, expected) = (hex(v.fields[0]), hex(v.fields[1]), dec(v.fields[2]) != 0);
+		set_nzcv(&mut cpu, flags);
+		let passed = cpu.check_condition(cond);
+		if passed != expected {
+			failures.push(format!("line {}: condition {:x} with NZCV={:x}: expected {}, got {}", v.line, cond, flags, expected, passed));
+		}
+	}
+
+	check_failures("condition", vectors.len(), failures);
+}
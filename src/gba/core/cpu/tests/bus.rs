@@ -0,0 +1,129 @@
+//! A mock bus that stands in for the GBA memory map while a test runs.
+//! Reads are served from a sparse memory and every data access the CPU makes
+//! is recorded in order. The bus is installed per thread so that tests
+//! running in parallel don't see each other's accesses.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BusAccessKind {
+	Read,
+	Write
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BusAccess {
+	pub kind: BusAccessKind,
+
+	/// Size of the access in bytes.
+	pub size: u32,
+	pub address: u32,
+	pub data: u32
+}
+
+pub struct MockBus {
+	pub memory: HashMap<u32, u8>,
+	pub accesses: Vec<BusAccess>,
+
+	/// If this is set, bytes that were never written read as
+	/// a hash of their address instead of zero.
+	pub fill_seed: Option<u32>
+}
+
+impl MockBus {
+	pub fn new() -> MockBus {
+		MockBus {
+			memory: HashMap::new(),
+			accesses: Vec::new(),
+			fill_seed: None
+		}
+	}
+
+	pub fn with_fill(seed: u32) -> MockBus {
+		let mut bus = MockBus::new();
+		bus.fill_seed = Some(seed);
+		return bus;
+	}
+
+	fn unwritten_byte(&self, address: u32) -> u8 {
+		match self.fill_seed {
+			Some(seed) => {
+				let mut x = address ^ seed;
+				x = (x ^ (x >> 16)).wrapping_mul(0x45d9f3b);
+				x = (x ^ (x >> 16)).wrapping_mul(0x45d9f3b);
+				(x ^ (x >> 16)) as u8
+			},
+			None => 0
+		}
+	}
+
+	/// Writes `size` bytes of `value` in little endian order without recording an access.
+	pub fn poke(&mut self, address: u32, size: u32, value: u32) {
+		for i in 0..size {
+			self.memory.insert(address.wrapping_add(i), ((value >> (i * 8)) & 0xff) as u8);
+		}
+	}
+
+	/// Reads `size` bytes in little endian order without recording an access.
+	pub fn peek(&self, address: u32, size: u32) -> u32 {
+		let mut value = 0;
+		for i in 0..size {
+			let byte_address = address.wrapping_add(i);
+			let byte = self.memory.get(&byte_address).map(|b| *b).unwrap_or_else(|| self.unwritten_byte(byte_address));
+			value |= (byte as u32) << (i * 8);
+		}
+		return value;
+	}
+}
+
+thread_local!(static MOCK_BUS: RefCell<Option<MockBus>> = RefCell::new(None));
+
+/// Routes the CPU's data accesses on this thread to `bus`.
+pub fn install(bus: MockBus) {
+	MOCK_BUS.with(|cell| *cell.borrow_mut() = Some(bus));
+}
+
+/// Gives the CPU back its memory map and returns the bus with its recorded accesses.
+pub fn uninstall() -> Option<MockBus> {
+	MOCK_BUS.with(|cell| cell.borrow_mut().take())
+}
+
+fn read(address: u32, size: u32) -> Option<u32> {
+	MOCK_BUS.with(|cell| {
+		match *cell.borrow_mut() {
+			Some(ref mut bus) => {
+				let data = bus.peek(address, size);
+				bus.accesses.push(BusAccess { kind: BusAccessKind::Read, size: size, address: address, data: data });
+				Some(data)
+			},
+			None => None
+		}
+	})
+}
+
+fn write(address: u32, size: u32, value: u32) -> bool {
+	MOCK_BUS.with(|cell| {
+		match *cell.borrow_mut() {
+			Some(ref mut bus) => {
+				bus.poke(address, size, value);
+				bus.accesses.push(BusAccess { kind: BusAccessKind::Write, size: size, address: address, data: value });
+				true
+			},
+			None => false
+		}
+	})
+}
+
+pub fn read8(address: u32) -> Option<u8> { read(address, 1).map(|d| d as u8) }
+
+pub fn read16(address: u32) -> Option<u16> { read(address, 2).map(|d| d as u16) }
+
+pub fn read32(address: u32) -> Option<u32> { read(address, 4) }
+
+/// Returns false if there is no mock bus installed and the write should go to memory.
+pub fn write8(address: u32, value: u8) -> bool { write(address, 1, value as u32) }
+
+pub fn write16(address: u32, value: u16) -> bool { write(address, 2, value as u32) }
+
+pub fn write32(address: u32, value: u32) -> bool { write(address, 4, value) }
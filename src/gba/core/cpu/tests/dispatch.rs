@@ -0,0 +1,214 @@
+//! Differential tests between the two instruction dispatchers: the opcode tables
+//! used by default and the match statements used with the `instr-match` feature.
+//! Every dispatch entry is run through both from the same random CPU state and
+//! mock bus contents, with the table path taken as the expected result.
+
+use std::panic::{self, AssertUnwindSafe};
+use super::super::ArmCpu;
+use super::super::registers::*;
+use super::super::arm::table as arm_table;
+use super::super::arm::table_match as arm_match;
+use super::super::thumb::table as thumb_table;
+use super::super::thumb::table_match as thumb_match;
+use super::bus::{self, MockBus, BusAccess};
+use super::{new_cpu, register_slots, CpuState, XorShift};
+
+const SEED: u32 = 0x2545f491;
+
+/// ARM entries cover many encodings so each one gets a few random instructions.
+/// Thumb is small enough to run every opcode once.
+const ARM_SAMPLES_PER_ENTRY: usize = 8;
+const THUMB_DP_SAMPLES_PER_ENTRY: usize = 64;
+
+/// The number of divergences described in full, the rest are only counted.
+const MAX_REPORTS: usize = 32;
+
+const MODES: [u32; 7] = [MODE_USR, MODE_SYS, MODE_FIQ, MODE_IRQ, MODE_SVC, MODE_ABT, MODE_UND];
+
+/// Everything a single instruction can change.
+struct Outcome {
+	panicked: bool,
+	state: CpuState,
+	branched: bool,
+	cycles: u64,
+	accesses: Vec<BusAccess>
+}
+
+fn random_psr(rng: &mut XorShift, thumb: bool) -> u32 {
+	let mode = MODES[(rng.next() % (MODES.len() as u32)) as usize];
+	let t = if thumb { 0x20 } else { 0 };
+	(rng.next() & 0xf00000c0) | t | mode
+}
+
+fn random_state(rng: &mut XorShift, thumb: bool) -> CpuState {
+	let mut registers: Vec<u32> = register_slots().iter().map(|_| rng.next()).collect();
+	registers[15] &= if thumb { !1 } else { !3 };
+	CpuState {
+		registers: registers,
+		cpsr: random_psr(rng, thumb),
+		spsr: (0..5).map(|_| {
+			let thumb = (rng.next() & 1) != 0;
+			random_psr(rng, thumb)
+		}).collect()
+	}
+}
+
+fn run<F: FnOnce(&mut ArmCpu)>(cpu: &mut ArmCpu, state: &CpuState, bus_seed: u32, dispatch: F) -> Outcome {
+	state.load_into(cpu);
+	cpu.prefetch_wait = 0;
+	cpu.branched = false;
+	let start_cycles = cpu.clock.cycles;
+
+	bus::install(MockBus::with_fill(bus_seed));
+	// Undefined instructions panic, that's fine as long as both paths do it.
+	let panicked = panic::catch_unwind(AssertUnwindSafe(|| dispatch(&mut *cpu))).is_err();
+	let mock = bus::uninstall().unwrap_or(MockBus::new());
+
+	Outcome {
+		panicked: panicked,
+		state: CpuState::from_cpu(cpu),
+		branched: cpu.branched,
+		cycles: cpu.clock.cycles - start_cycles,
+		accesses: mock.accesses
+	}
+}
+
+fn compare(table: &Outcome, matched: &Outcome) -> Vec<String> {
+	if table.panicked || matched.panicked {
+		if table.panicked == matched.panicked { return Vec::new() }
+		let describe = |o: &Outcome| if o.panicked { "panicked" } else { "returned" };
+		return vec![format!("table {} but match {}", describe(table), describe(matched))];
+	}
+
+	let mut diffs = table.state.diff(&matched.state);
+	if table.branched != matched.branched {
+		diffs.push(format!("branched: expected {}, got {}", table.branched, matched.branched));
+	}
+	if table.cycles != matched.cycles {
+		diffs.push(format!("cycles: expected {}, got {}", table.cycles, matched.cycles));
+	}
+	if table.accesses.len() != matched.accesses.len() {
+		diffs.push(format!("expected {} bus accesses, got {}", table.accesses.len(), matched.accesses.len()));
+	}
+	for (idx, (a, b)) in table.accesses.iter().zip(matched.accesses.iter()).enumerate() {
+		if a != b {
+			diffs.push(format!("bus access {}: expected {:?}, got {:?}", idx, a, b));
+			break
+		}
+	}
+	return diffs;
+}
+
+struct Divergences {
+	total: usize,
+	reports: Vec<String>
+}
+
+impl Divergences {
+	fn new() -> Divergences {
+		Divergences { total: 0, reports: Vec::new() }
+	}
+
+	fn check(&mut self, what: String, table: &Outcome, matched: &Outcome) {
+		let diffs = compare(table, matched);
+		if diffs.is_empty() { return }
+		self.total += 1;
+		if self.reports.len() < MAX_REPORTS {
+			self.reports.push(format!("{}: {}", what, diffs.join("; ")));
+		}
+	}
+
+	fn assert_none(&self, runs: usize) {
+		assert!(self.total == 0, "{} of {} runs diverged between the dispatchers (seed {:08x}):\n{}",
+			self.total, runs, SEED, self.reports.join("\n"));
+	}
+}
+
+#[test]
+fn arm_dispatchers_agree() {
+	let mut rng = XorShift(SEED);
+	let mut table_cpu = new_cpu();
+	let mut match_cpu = new_cpu();
+	let mut divergences = Divergences::new();
+	let mut runs = 0;
+
+	for row in 0..256u32 {
+		for column in 0..16u32 {
+			for _ in 0..ARM_SAMPLES_PER_ENTRY {
+				// Always executed, with random bits everywhere but the dispatch bits.
+				let instr = 0xe0000000 | (row << 20) | (column << 4) | (rng.next() & 0x000fff0f);
+				let state = random_state(&mut rng, false);
+				let bus_seed = rng.next();
+
+				let table = run(&mut table_cpu, &state, bus_seed, |cpu| {
+					(arm_table::ARM_OPCODE_TABLE[row as usize][column as usize])(cpu, instr)
+				});
+				let matched = run(&mut match_cpu, &state, bus_seed, |cpu| {
+					arm_match::run_instr(cpu, arm_match::to_index(row, column), instr)
+				});
+				divergences.check(format!("arm [{:02x}][{:x}] {:08x}", row, column, instr), &table, &matched);
+				runs += 1;
+			}
+		}
+	}
+
+	divergences.assert_none(runs);
+}
+
+#[test]
+fn thumb_dispatchers_agree() {
+	let mut rng = XorShift(SEED);
+	let mut table_cpu = new_cpu();
+	let mut match_cpu = new_cpu();
+	let mut divergences = Divergences::new();
+	let mut runs = 0;
+
+	for instr in 0..0x10000u32 {
+		let (row, column) = ((instr >> 12) & 0xf, (instr >> 8) & 0xf);
+		let state = random_state(&mut rng, true);
+		let bus_seed = rng.next();
+
+		let table = run(&mut table_cpu, &state, bus_seed, |cpu| {
+			(thumb_table::THUMB_OPCODE_TABLE[row as usize][column as usize])(cpu, instr)
+		});
+		let matched = run(&mut match_cpu, &state, bus_seed, |cpu| {
+			thumb_match::run_instr(cpu, thumb_match::to_index(row, column), instr)
+		});
+		divergences.check(format!("thumb [{:x}][{:x}] {:04x}", row, column, instr), &table, &matched);
+		runs += 1;
+	}
+
+	divergences.assert_none(runs);
+}
+
+/// The data processing group has its own second level table which
+/// the test above only goes through with whichever dispatcher is compiled in.
+#[test]
+fn thumb_dp_dispatchers_agree() {
+	let mut rng = XorShift(SEED);
+	let mut table_cpu = new_cpu();
+	let mut match_cpu = new_cpu();
+	let mut divergences = Divergences::new();
+	let mut runs = 0;
+
+	for row in 0..4u32 {
+		for column in 0..4u32 {
+			for _ in 0..THUMB_DP_SAMPLES_PER_ENTRY {
+				let instr = 0x4000 | (row << 8) | (column << 6) | (rng.next() & 0x3f);
+				let state = random_state(&mut rng, true);
+				let bus_seed = rng.next();
+
+				let table = run(&mut table_cpu, &state, bus_seed, |cpu| {
+					(thumb_table::THUMB_DP_OPCODE_TABLE[row as usize][column as usize])(cpu, instr)
+				});
+				let matched = run(&mut match_cpu, &state, bus_seed, |cpu| {
+					thumb_match::run_instr_dp(cpu, thumb_match::to_index_dp(row, column), instr)
+				});
+				divergences.check(format!("thumb dp [{:x}][{:x}] {:04x}", row, column, instr), &table, &matched);
+				runs += 1;
+			}
+		}
+	}
+
+	divergences.assert_none(runs);
+}
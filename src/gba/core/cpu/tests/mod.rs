@@ -0,0 +1,138 @@
+//! CPU tests driven by the vector files in `tests/vectors`.
+
+pub mod bus;
+
+mod alu;
+mod single_step;
+mod dispatch;
+
+use super::ArmCpu;
+use super::registers::*;
+
+/// A vector line split into its fields along with its line number,
+/// so that failures can point back at the file.
+pub struct VectorLine<'a> {
+	pub line: usize,
+	pub fields: Vec<&'a str>
+}
+
+pub fn load_vectors(path: &str) -> String {
+	::util::io::read_file_into_string(path).expect("Failed to read test vectors.")
+}
+
+/// Returns every line in the vector source whose first field is `kind`
+/// with the kind itself stripped off.
+pub fn vector_lines<'a>(source: &'a str, kind: &str) -> Vec<VectorLine<'a>> {
+	let mut lines = Vec::new();
+	for (idx, line) in source.lines().enumerate() {
+		let line = line.trim();
+		if line.is_empty() || line.starts_with("#") { continue }
+		let mut fields: Vec<&str> = line.split_whitespace().collect();
+		if fields[0] != kind { continue }
+		fields.remove(0);
+		lines.push(VectorLine { line: idx + 1, fields: fields });
+	}
+	return lines;
+}
+
+pub fn hex(field: &str) -> u32 {
+	u32::from_str_radix(field, 16).expect("Invalid hex value in test vectors.")
+}
+
+pub fn dec(field: &str) -> u32 {
+	field.parse().expect("Invalid decimal value in test vectors.")
+}
+
+/// ArmCpu carries the whole memory map inline so it has to live on the heap.
+pub fn new_cpu() -> Box<ArmCpu> {
+	Box::new(ArmCpu::new())
+}
+
+/// Sets the NZCV flags from the low 4 bits of `nzcv`.
+pub fn set_nzcv(cpu: &mut ArmCpu, nzcv: u32) {
+	cpu.registers.set_cpsr_flags(nzcv << 28);
+}
+
+pub fn get_nzcv(cpu: &ArmCpu) -> u32 {
+	cpu.registers.get_cpsr() >> 28
+}
+
+pub const SPSR_MODES: [(&'static str, u32); 5] = [
+	("SPSR_fiq", MODE_FIQ), ("SPSR_svc", MODE_SVC), ("SPSR_abt", MODE_ABT), ("SPSR_irq", MODE_IRQ), ("SPSR_und", MODE_UND)
+];
+
+/// Every physical register as (name, mode, register): the user bank,
+/// then r8-r14 for FIQ, then r13-r14 for SVC, ABT, IRQ and UND.
+pub fn register_slots() -> Vec<(String, u32, u32)> {
+	let mut slots = Vec::new();
+	for r in 0..16 { slots.push((format!("r{}", r), MODE_USR, r)); }
+	for r in 8..15 { slots.push((format!("r{}_fiq", r), MODE_FIQ, r)); }
+	for &(suffix, mode) in [("svc", MODE_SVC), ("abt", MODE_ABT), ("irq", MODE_IRQ), ("und", MODE_UND)].iter() {
+		for r in 13..15 { slots.push((format!("r{}_{}", r, suffix), mode, r)); }
+	}
+	return slots;
+}
+
+/// A copy of everything in the register file, so that the same
+/// state can be loaded into more than one CPU.
+pub struct CpuState {
+	/// In the same order as `register_slots`.
+	pub registers: Vec<u32>,
+	pub cpsr: u32,
+	pub spsr: Vec<u32>
+}
+
+impl CpuState {
+	pub fn load_into(&self, cpu: &mut ArmCpu) {
+		for (slot, &value) in register_slots().iter().zip(self.registers.iter()) {
+			cpu.registers.set_with_mode(slot.1, slot.2, value);
+		}
+		// The SPSRs can only be written through the current mode.
+		for (&(_, mode), &value) in SPSR_MODES.iter().zip(self.spsr.iter()) {
+			cpu.registers.set_mode(mode);
+			cpu.registers.set_spsr(value);
+		}
+		cpu.registers.set_cpsr(self.cpsr);
+	}
+
+	pub fn from_cpu(cpu: &ArmCpu) -> CpuState {
+		CpuState {
+			registers: register_slots().iter().map(|slot| cpu.registers.get_with_mode(slot.1, slot.2)).collect(),
+			cpsr: cpu.registers.get_cpsr(),
+			spsr: SPSR_MODES.iter().map(|&(_, mode)| cpu.registers.get_spsr_for_mode(mode)).collect()
+		}
+	}
+
+	/// Describes every register in `other` that doesn't match this one.
+	pub fn diff(&self, other: &CpuState) -> Vec<String> {
+		let mut mismatches = Vec::new();
+		for (idx, slot) in register_slots().iter().enumerate() {
+			if self.registers[idx] != other.registers[idx] {
+				mismatches.push(format!("{}: expected {:08x}, got {:08x}", slot.0, self.registers[idx], other.registers[idx]));
+			}
+		}
+		if self.cpsr != other.cpsr {
+			mismatches.push(format!("CPSR: expected {:08x}, got {:08x}", self.cpsr, other.cpsr));
+		}
+		for (idx, &(name, _)) in SPSR_MODES.iter().enumerate() {
+			if self.spsr[idx] != other.spsr[idx] {
+				mismatches.push(format!("{}: expected {:08x}, got {:08x}", name, self.spsr[idx], other.spsr[idx]));
+			}
+		}
+		return mismatches;
+	}
+}
+
+/// Small xorshift generator so that randomized tests are repeatable.
+pub struct XorShift(pub u32);
+
+impl XorShift {
+	pub fn next(&mut self) -> u32 {
+		let mut x = self.0;
+		x ^= x << 13;
+		x ^= x >> 17;
+		x ^= x << 5;
+		self.0 = x;
+		return x;
+	}
+}
@@ -0,0 +1,307 @@
+//! Runs single instruction CPU tests from JSON files laid out like the community
+//! ARM7TDMI single step suites. Each file holds an array of tests (or a single test):
+//!
+//! ```text
+//! {
+//!   "initial": { "R": [16], "R_fiq": [7], "R_svc": [2], "R_abt": [2], "R_irq": [2], "R_und": [2],
+//!                "CPSR": n, "SPSR": [5] },
+//!   "final": { same as initial },
+//!   "transactions": [{ "kind": 0|1|2, "size": 1|2|4, "addr": n, "data": n, "cycle": n }],
+//!   "opcode": n,
+//!   "cycles": n
+//! }
+//! ```
+//!
+//! `R` is the user bank, the other register arrays are the banked registers of each mode
+//! (r8-r14 for FIQ, r13-r14 for the rest) and `SPSR` is ordered FIQ, SVC, ABT, IRQ, UND.
+//! Transaction kinds are instruction fetch, data read and data write. Fetches are ignored
+//! because the opcode is handed to the CPU directly. Data reads seed the mock bus, and then
+//! the data reads and writes are compared, in order, against the accesses the instruction
+//! made. `cycles` is optional, when it is missing the cycle of the last transaction is used
+//! instead, and if neither is there the cycle count isn't checked.
+//!
+//! Files are loaded from `tests/vectors/cpu` or from the directory in `PYRITE_CPU_TESTS`.
+
+use std::env;
+use std::fs;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use rustc_serialize::json::Json;
+use super::super::ArmCpu;
+use super::super::registers::REG_PC;
+use super::super::arm::execute_arm;
+use super::super::thumb::execute_thumb;
+use super::bus::{self, MockBus, BusAccessKind};
+use super::{new_cpu, CpuState};
+
+const DEFAULT_DIR: &'static str = "tests/vectors/cpu";
+
+/// Set this environment variable to run the tests in another directory.
+const DIR_VAR: &'static str = "PYRITE_CPU_TESTS";
+
+/// The number of failures printed in full for each opcode, the rest are only counted.
+const MAX_REPORTS_PER_OPCODE: usize = 8;
+
+const TRANSACTION_READ: u32 = 1;
+const TRANSACTION_WRITE: u32 = 2;
+
+struct Transaction {
+	kind: u32,
+	size: u32,
+	address: u32,
+	data: u32,
+	cycle: Option<u32>
+}
+
+struct SingleStepTest {
+	initial: CpuState,
+	expected: CpuState,
+	transactions: Vec<Transaction>,
+	opcode: u32,
+	cycles: Option<u32>
+}
+
+fn field<'a>(obj: &'a Json, name: &str) -> Result<&'a Json, String> {
+	obj.find(name).ok_or_else(|| format!("missing `{}`", name))
+}
+
+fn number(obj: &Json, name: &str) -> Result<u32, String> {
+	let value = try!(field(obj, name));
+	value.as_u64().map(|n| n as u32).ok_or_else(|| format!("`{}` is not a number", name))
+}
+
+fn numbers(obj: &Json, name: &str, count: usize) -> Result<Vec<u32>, String> {
+	let value = try!(field(obj, name));
+	let array = try!(value.as_array().ok_or_else(|| format!("`{}` is not an array", name)));
+	if array.len() != count {
+		return Err(format!("`{}` should have {} entries, found {}", name, count, array.len()));
+	}
+	let mut out = Vec::with_capacity(count);
+	for v in array.iter() {
+		out.push(try!(v.as_u64().map(|n| n as u32).ok_or_else(|| format!("`{}` contains a non-number", name))));
+	}
+	return Ok(out);
+}
+
+fn state_from_json(obj: &Json) -> Result<CpuState, String> {
+	let mut registers = try!(numbers(obj, "R", 16));
+	registers.extend(try!(numbers(obj, "R_fiq", 7)));
+	for name in ["R_svc", "R_abt", "R_irq", "R_und"].iter() {
+		registers.extend(try!(numbers(obj, name, 2)));
+	}
+	Ok(CpuState {
+		registers: registers,
+		cpsr: try!(number(obj, "CPSR")),
+		spsr: try!(numbers(obj, "SPSR", 5))
+	})
+}
+
+impl SingleStepTest {
+	fn from_json(obj: &Json) -> Result<SingleStepTest, String> {
+		let mut transactions = Vec::new();
+		if let Some(list) = obj.find("transactions").and_then(|t| t.as_array()) {
+			for t in list.iter() {
+				transactions.push(Transaction {
+					kind: try!(number(t, "kind")),
+					size: try!(number(t, "size")),
+					address: try!(number(t, "addr")),
+					data: try!(number(t, "data")),
+					cycle: t.find("cycle").and_then(|c| c.as_u64()).map(|c| c as u32)
+				});
+			}
+		}
+
+		let cycles = match obj.find("cycles") {
+			Some(_) => Some(try!(number(obj, "cycles"))),
+			None => transactions.last().and_then(|t| t.cycle)
+		};
+
+		Ok(SingleStepTest {
+			initial: try!(state_from_json(try!(field(obj, "initial")))),
+			expected: try!(state_from_json(try!(field(obj, "final")))),
+			transactions: transactions,
+			opcode: try!(number(obj, "opcode")),
+			cycles: cycles
+		})
+	}
+}
+
+/// Executes a single instruction and then moves the pipeline along
+/// the same way `ArmCpu::tick` does.
+fn step(cpu: &mut ArmCpu, opcode: u32) {
+	cpu.prefetch_wait = 0;
+	cpu.branched = false;
+
+	if cpu.thumb_mode() {
+		execute_thumb(cpu, opcode);
+	} else if cpu.check_condition((opcode >> 28) & 0xf) {
+		execute_arm(cpu, opcode);
+	} else {
+		cpu.clock_prefetch_arm();
+	}
+
+	if cpu.branched {
+		cpu.align_pc();
+		cpu.pipeline_flush();
+		cpu.branched = false;
+		cpu.fill_pipeline();
+	} else {
+		let pc = cpu.get_pc();
+		let size = if cpu.thumb_mode() { 2 } else { 4 };
+		cpu.registers.set(REG_PC, pc + size);
+	}
+}
+
+/// Groups opcodes by the dispatch table entry that runs them.
+fn opcode_key(thumb: bool, opcode: u32) -> String {
+	if thumb {
+		format!("thumb [{:x}][{:x}]", (opcode >> 12) & 0xf, (opcode >> 8) & 0xf)
+	} else {
+		format!("arm [{:02x}][{:x}]", (opcode >> 20) & 0xff, (opcode >> 4) & 0xf)
+	}
+}
+
+fn size_mask(size: u32) -> u32 {
+	if size >= 4 { 0xffffffff } else { (1 << (size * 8)) - 1 }
+}
+
+fn access_name(kind: BusAccessKind) -> &'static str {
+	match kind {
+		BusAccessKind::Read => "a read",
+		BusAccessKind::Write => "a write"
+	}
+}
+
+/// Runs the test and returns a description of everything that didn't match.
+fn run_test(test: &SingleStepTest) -> Vec<String> {
+	let mut cpu = new_cpu();
+	// The single step suites don't model wait states.
+	for timing in cpu.clock.memory_timings.iter_mut() {
+		*timing = ((1, 1, 1), (1, 1, 1));
+	}
+	test.initial.load_into(&mut cpu);
+
+	let mut mock = MockBus::new();
+	for t in test.transactions.iter().filter(|t| t.kind == TRANSACTION_READ) {
+		mock.poke(t.address & !(t.size - 1), t.size, t.data);
+	}
+	bus::install(mock);
+	let start_cycles = cpu.clock.cycles;
+	step(&mut cpu, test.opcode);
+	let cycles = cpu.clock.cycles - start_cycles;
+	let mock = bus::uninstall().expect("The mock bus was removed while an instruction was running.");
+
+	let mut mismatches = test.expected.diff(&CpuState::from_cpu(&cpu));
+
+	// Reads and writes are compared together so that their order is checked too.
+	let expected_accesses: Vec<&Transaction> = test.transactions.iter()
+		.filter(|t| t.kind == TRANSACTION_READ || t.kind == TRANSACTION_WRITE).collect();
+	if expected_accesses.len() != mock.accesses.len() {
+		mismatches.push(format!("expected {} data accesses, got {}", expected_accesses.len(), mock.accesses.len()));
+	}
+	for (idx, (expected, got)) in expected_accesses.iter().zip(mock.accesses.iter()).enumerate() {
+		let expected_kind = if expected.kind == TRANSACTION_READ { BusAccessKind::Read } else { BusAccessKind::Write };
+		// The bus only sees aligned addresses, the low bits are dropped by the memory.
+		let same = expected_kind == got.kind && expected.size == got.size &&
+			(expected.address & !(expected.size - 1)) == (got.address & !(got.size - 1)) &&
+			(expected.data & size_mask(expected.size)) == (got.data & size_mask(got.size));
+		if !same {
+			mismatches.push(format!("access {}: expected {} of {} bytes of {:08x} at {:08x}, got {} of {} bytes of {:08x} at {:08x}",
+				idx, access_name(expected_kind), expected.size, expected.data, expected.address,
+				access_name(got.kind), got.size, got.data, got.address));
+		}
+	}
+
+	if let Some(expected_cycles) = test.cycles {
+		if cycles != expected_cycles as u64 {
+			mismatches.push(format!("cycles: expected {}, got {}", expected_cycles, cycles));
+		}
+	}
+
+	return mismatches;
+}
+
+fn load_tests(path: &Path) -> Result<Vec<SingleStepTest>, String> {
+	let source = try!(::util::io::read_file_into_string(path.to_str().unwrap_or("")));
+	let json = try!(Json::from_str(&source).map_err(|e| format!("{}", e)));
+	let mut tests = Vec::new();
+	match json.as_array() {
+		Some(list) => for (idx, obj) in list.iter().enumerate() {
+			tests.push(try!(SingleStepTest::from_json(obj).map_err(|e| format!("test {}: {}", idx, e))));
+		},
+		None => tests.push(try!(SingleStepTest::from_json(&json)))
+	}
+	return Ok(tests);
+}
+
+fn test_files(dir: &str) -> Vec<PathBuf> {
+	let mut files = Vec::new();
+	if let Ok(entries) = fs::read_dir(dir) {
+		for entry in entries {
+			if let Ok(entry) = entry {
+				let path = entry.path();
+				if path.extension().map(|e| e == "json").unwrap_or(false) {
+					files.push(path);
+				}
+			}
+		}
+	}
+	files.sort();
+	return files;
+}
+
+#[derive(Default)]
+struct OpcodeResults {
+	passed: usize,
+	failed: usize,
+	reports: Vec<String>
+}
+
+#[test]
+fn single_step_vectors() {
+	let dir = env::var(DIR_VAR).unwrap_or(DEFAULT_DIR.to_string());
+	let files = test_files(&dir);
+	if files.is_empty() {
+		println!("Skipping single step CPU tests, no JSON files in {}.", dir);
+		return
+	}
+
+	let mut results: BTreeMap<String, OpcodeResults> = BTreeMap::new();
+	let mut load_errors = Vec::new();
+	let mut total = 0;
+
+	for path in files.iter() {
+		let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("?").to_string();
+		let tests = match load_tests(path) {
+			Ok(t) => t,
+			Err(e) => { load_errors.push(format!("{}: {}", name, e)); continue }
+		};
+
+		for (idx, test) in tests.iter().enumerate() {
+			total += 1;
+			let thumb = ((test.initial.cpsr >> 5) & 1) != 0;
+			let mismatches = run_test(test);
+			let entry = results.entry(opcode_key(thumb, test.opcode)).or_insert(Default::default());
+			if mismatches.is_empty() {
+				entry.passed += 1;
+			} else {
+				entry.failed += 1;
+				if entry.reports.len() < MAX_REPORTS_PER_OPCODE {
+					entry.reports.push(format!("  {} #{} ({:08x}): {}", name, idx, test.opcode, mismatches.join("; ")));
+				}
+			}
+		}
+	}
+
+	let mut report = Vec::new();
+	let mut failed = 0;
+	for (key, r) in results.iter() {
+		if r.failed == 0 { continue }
+		failed += r.failed;
+		report.push(format!("{}: {} of {} failed", key, r.failed, r.failed + r.passed));
+		report.extend(r.reports.iter().cloned());
+	}
+
+	assert!(load_errors.is_empty(), "Failed to load single step tests:\n{}", load_errors.join("\n"));
+	assert!(failed == 0, "{} of {} single step tests failed:\n{}", failed, total, report.join("\n"));
+}
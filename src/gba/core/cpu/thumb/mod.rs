@@ -2,10 +2,11 @@ pub mod functions;
 pub mod thumb_dp_impl;
 pub mod thumb_impl;
 
-#[cfg(not(feature = "instr-match"))]
+// Tests build both dispatchers so that they can be compared.
+#[cfg(any(test, not(feature = "instr-match")))]
 pub mod table;
 
-#[cfg(feature = "instr-match")]
+#[cfg(any(test, feature = "instr-match"))]
 pub mod table_match;
 
 use super::ArmCpu;
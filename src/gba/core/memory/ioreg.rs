@@ -1,4 +1,3 @@
-use super::compat::*;
 pub use super::*;
 
 #[derive(Copy, Clone)]
@@ -397,17 +396,14 @@ pub struct GbaChannelFIFO {
 	pub enable_right: bool,
 	pub enable_left: bool,
 
+	/// The sample that is currently being played.
 	pub sample: i8,
 
-	pub frequency: f32,
-	pub freq_acc: f32,
-	pub freq_inc: f32,
+	/// Sum and count of the samples played since the last output sample was taken.
+	played_sum: i32,
+	played_count: i32,
 
-	// #TODO do I really want to store this on save?
-	out_data: FifoOutArray,
-	out_write_cursor: usize,
-	out_read_cursor: usize,
-	out_size: usize,
+	pub frequency: f32,
 
 	data: [i8; 32],
 	write_cursor: usize,
@@ -418,45 +414,34 @@ pub struct GbaChannelFIFO {
 
 impl GbaChannelFIFO {
 	pub fn reset(&mut self) {
-		self.out_write_cursor = 0;
-		self.out_read_cursor = 0;
-		self.out_size = 0;
+		self.played_sum = 0;
+		self.played_count = 0;
 		self.write_cursor = 0;
 		self.read_cursor = 0;
 		self.size = 0;
-		self.freq_acc = 0.0;
 	}
 
-	pub fn out_push(&mut self, sample: i8) {
-		if self.out_write_cursor == self.out_read_cursor && self.out_remaining() > 0 {
-			self.out_read_cursor = (self.out_write_cursor + 1) & FIFO_OUT_BUFFER_MASK;
-		}
-		self.out_data.data[self.out_write_cursor] = sample;
-		self.out_write_cursor = (self.out_write_cursor + 1) & FIFO_OUT_BUFFER_MASK;
-		self.out_size = min!(self.out_size + 1, FIFO_OUT_BUFFER_SIZE);
+	/// Called when the timer for this FIFO overflows and a new sample starts playing.
+	pub fn play(&mut self, sample: i8) {
+		self.sample = sample;
+		self.played_sum += sample as i32;
+		self.played_count += 1;
 	}
 
-	pub fn out_pop(&mut self) -> i8 {
-		if self.out_remaining() > 0 {
-			let sample = self.out_data.data[self.out_read_cursor];
-			self.out_read_cursor = (self.out_read_cursor + 1) & FIFO_OUT_BUFFER_MASK;
-			self.out_size -= 1;
-			return sample;
+	/// Returns the output of the FIFO for a single output sample.
+	/// If the timer is running faster than the output sample rate
+	/// the samples played in between are averaged.
+	pub fn take_output(&mut self) -> i8 {
+		if self.played_count > 0 {
+			let avg = self.played_sum / self.played_count;
+			self.played_sum = 0;
+			self.played_count = 0;
+			return avg as i8;
 		} else {
-			return 0;
+			return self.sample;
 		}
 	}
 
-	pub fn next_sample(&mut self) -> i8 {
-		let sample = self.out_pop();
-		self.sample = sample;
-		return sample;
-	}
-
-	pub fn out_remaining(&self) -> usize {
-		return self.out_size
-	}
-
 	/// Pushes two signed 8 bit samples into the FIFO queue.
 	pub fn push16(&mut self, sample2: u16) {
 		self.push((sample2 & 0xff) as i8);
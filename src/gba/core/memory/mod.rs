@@ -1,4 +1,3 @@
-mod compat;
 pub mod ioreg;
 
 // #TODO reduce the calls to map.
@@ -9,6 +8,7 @@ use self::ioreg::IORegister8;
 use self::ioreg::IORegister16;
 use self::ioreg::IORegister32;
 use self::ioreg::InternalRegisters;
+use std::collections::BTreeMap;
 
 pub struct MemoryRegion {
 	pub start: u32,
@@ -39,7 +39,17 @@ pub const MEM_SRAM:		MemoryRegion = MemoryRegion { start: 0x0e000000, end: 0x0E0
 pub struct GbaMemory {
 	pub internal_data: [u8; INTERNAL_MEM_SIZE],
 	pub internal_regs: InternalRegisters,
-	pub rom: Vec<u8>
+	pub rom: Vec<u8>,
+
+	/// The original value of every ROM byte that has been written to,
+	/// so that save states can store and undo writes to the ROM.
+	rom_originals: BTreeMap<usize, u8>,
+
+	/// Bytes of internal memory that cheats have written this frame, the game can't change them.
+	cheat_frozen: BTreeMap<usize, u8>,
+
+	/// ROM bytes replaced by cheats, only reads see them.
+	cheat_rom: BTreeMap<usize, u8>
 	// #TODO add SRAM.
 }
 
@@ -48,7 +58,10 @@ impl GbaMemory {
 		GbaMemory {
 			internal_data: [0u8; INTERNAL_MEM_SIZE],
 			internal_regs: InternalRegisters::new(),
-			rom: vec![]
+			rom: vec![],
+			rom_originals: BTreeMap::new(),
+			cheat_frozen: BTreeMap::new(),
+			cheat_rom: BTreeMap::new()
 		}
 	}
 
@@ -232,7 +245,7 @@ impl GbaMemory {
 			0x0E000000 ... 0x0E00FFFF => {}, // #TODO SRAM and shit.
 			_ => {
 				let (local_addr, writeable) = self.map(address);
-				if writeable {
+				if writeable && !self.cheat_frozen.contains_key(&local_addr) {
 					match address {
 						0x4000202 | 0x4000203 => self.internal_data[local_addr] &= !value,
 						_ => self.internal_data[local_addr] = value
@@ -278,12 +291,69 @@ pub const TM3CNT_H: IORegister16 = IORegister16(0x000010e);
 	#[inline]
 	fn rom_write8(&mut self, address: u32, value: u8) {
 		let local_addr = (address - (address & 0x0f000000)) as usize;
-		if local_addr < self.rom.len() { self.rom[local_addr] = value }
+		self.patch_rom(local_addr, value);
+	}
+
+	fn patch_rom(&mut self, offset: usize, value: u8) {
+		if offset < self.rom.len() {
+			if !self.rom_originals.contains_key(&offset) {
+				self.rom_originals.insert(offset, self.rom[offset]);
+			}
+			self.rom[offset] = value;
+		}
+	}
+
+	/// Replaces the cartridge ROM.
+	pub fn set_rom(&mut self, rom: Vec<u8>) {
+		self.rom = rom;
+		self.rom_originals.clear();
+	}
+
+	/// Returns the offset and current value of every ROM byte that has been written to.
+	pub fn rom_writes(&self) -> Vec<(u32, u8)> {
+		self.rom_originals.keys().map(|&offset| (offset as u32, self.rom[offset])).collect()
+	}
+
+	/// Undoes every write to the ROM and then applies the given writes.
+	pub fn replace_rom_writes(&mut self, writes: &[(u32, u8)]) {
+		for (&offset, &original) in self.rom_originals.iter() {
+			self.rom[offset] = original;
+		}
+		self.rom_originals.clear();
+		for &(offset, value) in writes.iter() {
+			self.patch_rom(offset as usize, value);
+		}
+	}
+
+	/// Writes a byte for a cheat. RAM and ROM written this way keep the value until
+	/// `clear_cheats` is called, anything else is written like the game would.
+	pub fn cheat_write8(&mut self, address: u32, value: u8) {
+		match address {
+			0x02000000 ... 0x03FFFFFF | 0x05000000 ... 0x07FFFFFF => {
+				let (local_addr, _) = self.map(address);
+				self.internal_data[local_addr] = value;
+				self.cheat_frozen.insert(local_addr, value);
+			},
+			0x08000000 ... 0x0Dffffff => {
+				let local_addr = (address - (address & 0x0f000000)) as usize;
+				self.cheat_rom.insert(local_addr, value);
+			},
+			_ => self.write8(address, value)
+		}
+	}
+
+	/// Lets the game write to the memory that cheats froze and undoes their ROM patches.
+	pub fn clear_cheats(&mut self) {
+		self.cheat_frozen.clear();
+		self.cheat_rom.clear();
 	}
 
 	#[inline]
 	fn rom_read8(&self, address: u32) -> u8 {
 		let local_addr = (address - (address & 0x0f000000)) as usize;
+		if !self.cheat_rom.is_empty() {
+			if let Some(&value) = self.cheat_rom.get(&local_addr) { return value }
+		}
 		if local_addr >= self.rom.len() { 0 }
 		else { self.rom[local_addr] }
 	}
@@ -0,0 +1,182 @@
+pub mod portaudio_sink;
+pub mod null_sink;
+pub mod wav_sink;
+
+use std::thread;
+use std::sync::Arc;
+use ::util::async_ring_buffer::AsyncRingBuffer;
+// use std::f64::consts::PI;
+
+pub use self::portaudio_sink::PortAudioSink;
+pub use self::null_sink::NullSink;
+pub use self::wav_sink::WavSink;
+
+pub const CHANNELS: i32 = 2;
+pub const SAMPLE_RATE: f64 = 44_100.0;
+pub const FRAMES_PER_BUFFER: u32 = 256;
+pub const AUDIO_DATA_BUFFER_SIZE: usize = FRAMES_PER_BUFFER as usize;
+const AUDIO_DATA_BUFFER_COUNT: usize = 8;
+
+// Size of audio data buffer in bytes is around:
+// AUDIO_DATA_BUFFER_SIZE * AUDIO_DATA_BUFFER_COUNT * 8
+
+pub type AudioBufferType = [(i16, i16); AUDIO_DATA_BUFFER_SIZE as usize];
+
+pub type AudioRingBuffer = Arc<AsyncRingBuffer<AudioBufferType>>;
+
+/// Something that consumes the audio produced by the emulator.
+/// Sinks read chunks of frames from the ring buffer that is passed to `start`.
+pub trait AudioSink {
+	/// A short name for the sink used in log messages.
+	fn name(&self) -> &'static str;
+
+	/// Starts consuming audio from the ring buffer.
+	/// Returns an error if the sink could not be started, in which
+	/// case the audio device will fall back to a null sink.
+	fn start(&mut self, ring_buffer: AudioRingBuffer) -> Result<(), String>;
+
+	/// Stops consuming audio and releases any resources held by the sink.
+	fn stop(&mut self);
+
+	/// Called right before `stop` with the last frames, which didn't fill a whole chunk.
+	/// They come after everything in the ring buffer. Only lossless sinks need them.
+	fn write_remaining(&mut self, _frames: &[(i16, i16)]) {}
+
+	/// Returns true if the sink needs every chunk of audio.
+	/// The emulator will wait for the sink to catch up instead of
+	/// dropping chunks when the ring buffer is full.
+	fn lossless(&self) -> bool { false }
+}
+
+pub struct AudioDevice {
+	pub ring_buffer: AudioRingBuffer,
+	sink: Box<AudioSink>,
+	sink_lossless: bool,
+	pub sample_rate: u32,
+	pub sample_rate_f: f32,
+
+	/// Frames that have been generated but not yet sent to the ring buffer.
+	pending_frames: AudioBufferType,
+	pending_count: usize,
+
+	/// Elapsed CPU cycles multiplied by the sample rate.
+	/// A new sample is due every time this passes the GBA's clock frequency.
+	pub sample_clock: u64,
+
+	/// The CPU cycle that the last generated sample was timestamped with.
+	pub last_sample_cycle: u64,
+
+	/// While recording, every generated frame is also collected here
+	/// so that it can be written out with the video frame it belongs to.
+	pub capture: Option<Vec<(i16, i16)>>,
+
+	/// The number of times each generated frame is sent to the output, this keeps
+	/// the output fed when the emulator runs slower or faster than normal.
+	/// 1.0 is normal speed, 2.0 is half speed and 0.0 mutes the output.
+	/// Lossless sinks always get every frame exactly once.
+	pub stretch: f32,
+	stretch_acc: f32
+}
+
+impl AudioDevice {
+	pub fn new(sink: Box<AudioSink>) -> AudioDevice {
+		let generator_fn = || [(0, 0); AUDIO_DATA_BUFFER_SIZE as usize];
+		AudioDevice {
+			ring_buffer: Arc::new(AsyncRingBuffer::new(AUDIO_DATA_BUFFER_COUNT, generator_fn)),
+			sink: sink,
+			sink_lossless: false,
+			sample_rate: 44_100,
+			sample_rate_f: 44_100.0,
+			pending_frames: [(0, 0); AUDIO_DATA_BUFFER_SIZE as usize],
+			pending_count: 0,
+			sample_clock: 0,
+			last_sample_cycle: 0,
+			capture: None,
+			stretch: 1.0,
+			stretch_acc: 0.0
+		}
+	}
+
+	/// Queues a single frame that was generated at the given CPU cycle.
+	/// Frames are sent to the output in chunks of `FRAMES_PER_BUFFER`.
+	pub fn push_frame(&mut self, cycle: u64, frame: (i16, i16)) {
+		self.last_sample_cycle = cycle;
+		if let Some(ref mut capture) = self.capture {
+			capture.push(frame);
+		}
+
+		if self.sink_lossless {
+			self.queue_frame(frame);
+		} else {
+			self.stretch_acc += self.stretch;
+			while self.stretch_acc >= 1.0 {
+				self.queue_frame(frame);
+				self.stretch_acc -= 1.0;
+			}
+		}
+	}
+
+	fn queue_frame(&mut self, frame: (i16, i16)) {
+		self.pending_frames[self.pending_count] = frame;
+		self.pending_count += 1;
+		if self.pending_count >= AUDIO_DATA_BUFFER_SIZE {
+			let pending = &self.pending_frames;
+			loop {
+				let written = self.ring_buffer.try_write(|frames| {
+					*frames = *pending;
+					return true
+				});
+
+				// If the reader has fallen behind we just drop this chunk
+				// unless the sink can't afford to miss any audio.
+				if written || !self.sink_lossless { break }
+				thread::yield_now();
+			}
+			self.pending_count = 0;
+		}
+	}
+
+	#[inline(always)]
+	pub fn millis_to_frames(&self, milliseconds: u32, millisecond_tenths: u32) -> u32 {
+		// 1 / 44_100 s - sample
+		// 1 / 1000 s - milliseconds
+		return milliseconds * 44 + millisecond_tenths * 4;
+	}
+
+	pub fn start(&mut self) {
+		match self.sink.start(self.ring_buffer.clone()) {
+			Ok(_) => {
+				debug_info!("Started {} audio sink.", self.sink.name());
+			},
+			Err(e) => {
+				debug_warn!("Failed to start {} audio sink, audio will be disabled. Error: {}", self.sink.name(), e);
+				self.sink = Box::new(NullSink::new());
+				// The null sink can't fail to start.
+				self.sink.start(self.ring_buffer.clone()).expect("Failed to start null audio sink.");
+			}
+		}
+		self.sink_lossless = self.sink.lossless();
+	}
+
+	pub fn stop(&mut self) {
+		debug_trace!("Stopping {} audio sink...", self.sink.name());
+		if self.pending_count > 0 {
+			self.sink.write_remaining(&self.pending_frames[0..self.pending_count]);
+			self.pending_count = 0;
+		}
+		self.sink.stop();
+	}
+}
+
+/// Hearing is logarithmic or something or other,
+/// so just multiplying our signal by 1/10 won't translate
+/// exactly to 1/10 of perceived volume.
+pub fn volume_to_signal_multiplier(volume: f32) -> f32 {
+	let level_change = 10.0f32 * volume.log2();
+	let sound_pressure = (10.0f32).powf(level_change / 20.0);
+	return sound_pressure;
+}
+
+pub fn apply_volume(s: i16, v: f32) -> i16 {
+	(s as f32 * v) as i16
+}
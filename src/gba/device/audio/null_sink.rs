@@ -0,0 +1,23 @@
+use super::*;
+
+/// Discards all audio. Used when there is no sound device
+/// or when running headless.
+pub struct NullSink;
+
+impl NullSink {
+	pub fn new() -> NullSink {
+		NullSink
+	}
+}
+
+impl AudioSink for NullSink {
+	fn name(&self) -> &'static str { "Null" }
+
+	fn start(&mut self, _: AudioRingBuffer) -> Result<(), String> {
+		// Nothing reads from the ring buffer so the emulator
+		// will just drop chunks once it is full.
+		return Ok(())
+	}
+
+	fn stop(&mut self) {}
+}
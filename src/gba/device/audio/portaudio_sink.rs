@@ -1,54 +1,48 @@
 use portaudio;
 use std::thread;
-use std::sync::Arc;
-use ::util::async_ring_buffer::AsyncRingBuffer;
-// use std::f64::consts::PI;
-
-const CHANNELS: i32 = 2;
-const SAMPLE_RATE: f64 = 44_100.0;
-const FRAMES_PER_BUFFER: u32 = 256;
-const AUDIO_DATA_BUFFER_SIZE: usize = FRAMES_PER_BUFFER as usize;
-const AUDIO_DATA_BUFFER_COUNT: usize = 8;
-
-// Size of audio data buffer in bytes is around:
-// AUDIO_DATA_BUFFER_SIZE * AUDIO_DATA_BUFFER_COUNT * 8
-
-pub type AudioBufferType = [(i16, i16); AUDIO_DATA_BUFFER_SIZE as usize];
-
-pub struct AudioDevice {
-	pub ring_buffer: Arc<AsyncRingBuffer<AudioBufferType>>,
-	output_thread: Option<thread::JoinHandle<()>>,
-	pub sample_rate: u32,
-	pub sample_rate_f: f32
+use std::sync::mpsc;
+use super::*;
+
+/// Plays audio through the default output device using PortAudio.
+pub struct PortAudioSink {
+	output_thread: Option<thread::JoinHandle<()>>
 }
 
-impl AudioDevice {
-	pub fn new() -> AudioDevice {
-		let generator_fn = || [(0, 0); AUDIO_DATA_BUFFER_SIZE as usize];
-		AudioDevice {
-			ring_buffer: Arc::new(AsyncRingBuffer::new(AUDIO_DATA_BUFFER_COUNT, generator_fn)),
-			output_thread: None,
-			sample_rate: 44_100,
-			sample_rate_f: 44_100.0
+impl PortAudioSink {
+	pub fn new() -> PortAudioSink {
+		PortAudioSink {
+			output_thread: None
 		}
 	}
+}
 
-	#[inline(always)]
-	pub fn millis_to_frames(&self, milliseconds: u32, millisecond_tenths: u32) -> u32 {
-		// 1 / 44_100 s - sample
-		// 1 / 1000 s - milliseconds
-		return milliseconds * 44 + millisecond_tenths * 4;
-	}
-
-	pub fn start(&mut self) {
-		let ring_buffer = self.ring_buffer.clone();
-		let thread = thread::Builder::new().name("Audio".to_string()).spawn(move || {
-			start_port_audio(ring_buffer);
-		}).expect("Failed to start audio thread.");
-		self.output_thread = Some(thread);
+impl AudioSink for PortAudioSink {
+	fn name(&self) -> &'static str { "PortAudio" }
+
+	fn start(&mut self, ring_buffer: AudioRingBuffer) -> Result<(), String> {
+		let (tx, rx) = mpsc::channel();
+		let thread = match thread::Builder::new().name("Audio".to_string()).spawn(move || {
+			start_port_audio(ring_buffer, tx);
+		}) {
+			Ok(t) => t,
+			Err(e) => return Err(format!("Failed to start audio thread: {}", e))
+		};
+
+		// Wait for PortAudio to tell us whether or not it managed to start.
+		let result = match rx.recv() {
+			Ok(r) => r,
+			Err(_) => Err("Audio thread stopped before starting PortAudio.".to_string())
+		};
+
+		if result.is_ok() {
+			self.output_thread = Some(thread);
+		} else {
+			let _ = thread.join();
+		}
+		return result;
 	}
 
-	pub fn stop(&mut self) {
+	fn stop(&mut self) {
 		debug_trace!("Waiting for audio output thread to stop...");
 		match self.output_thread.take() {
 			Some(t) => {
@@ -69,24 +63,22 @@ impl AudioDevice {
 	}
 }
 
-/// Hearing is logarithmic or something or other,
-/// so just multiplying our signal by 1/10 won't translate
-/// exactly to 1/10 of perceived volume.
-pub fn volume_to_signal_multiplier(volume: f32) -> f32 {
-	let level_change = 10.0f32 * volume.log2();
-	let sound_pressure = (10.0f32).powf(level_change / 20.0);
-	return sound_pressure;
-}
-
-fn apply_volume(s: i16, v: f32) -> i16 {
-	(s as f32 * v) as i16
-}
-
-fn start_port_audio(ring_buffer: Arc<AsyncRingBuffer<AudioBufferType>>) {
+fn start_port_audio(ring_buffer: AudioRingBuffer, started: mpsc::Sender<Result<(), String>>) {
 	// SETUP:
-	let pa = portaudio::PortAudio::new().expect("Failed to initialize port audio.");
-	let settings = pa.default_output_stream_settings(CHANNELS, SAMPLE_RATE, FRAMES_PER_BUFFER)
-		.expect("Failed to get PortAudio default output stream settings.");
+	let pa = match portaudio::PortAudio::new() {
+		Ok(pa) => pa,
+		Err(e) => {
+			let _ = started.send(Err(format!("Failed to initialize PortAudio: {}", e)));
+			return
+		}
+	};
+	let settings = match pa.default_output_stream_settings(CHANNELS, SAMPLE_RATE, FRAMES_PER_BUFFER) {
+		Ok(settings) => settings,
+		Err(e) => {
+			let _ = started.send(Err(format!("Failed to get PortAudio default output stream settings: {}", e)));
+			return
+		}
+	};
 	let mut remaining_audio_data_index = 0;
 
 	let mut last_left = 0;
@@ -146,16 +138,22 @@ fn start_port_audio(ring_buffer: Arc<AsyncRingBuffer<AudioBufferType>>) {
 		return portaudio::Continue;
 	};
 
-	let mut stream = pa.open_non_blocking_stream(settings, callback)
-		.expect("Failed to create PortAudio output stream.");
+	let mut stream = match pa.open_non_blocking_stream(settings, callback) {
+		Ok(stream) => stream,
+		Err(e) => {
+			let _ = started.send(Err(format!("Failed to create PortAudio output stream: {}", e)));
+			return
+		}
+	};
 	debug_info!("Opened PortAudio stream.");
 
 	match stream.start() {
 		Ok(_) => {
 			debug_info!("Started PortAudio output stream");
+			let _ = started.send(Ok(()));
 		},
 		Err(e) => {
-			debug_error!("Failed to start PortAudio output stream. Error: {}", e);
+			let _ = started.send(Err(format!("Failed to start PortAudio output stream: {}", e)));
 			return
 		}
 	}
@@ -0,0 +1,185 @@
+use std::thread;
+use std::time::Duration;
+use std::io::prelude::*;
+use std::io::{SeekFrom, BufWriter};
+use std::fs::File;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use super::*;
+
+/// Size of the RIFF + fmt + data chunk headers.
+pub const WAV_HEADER_SIZE: u32 = 44;
+
+/// The RIFF size field is 32 bits and also counts the header after it, so the
+/// audio can't be any longer than this.
+pub const MAX_WAV_DATA_SIZE: u32 = ::std::u32::MAX - (WAV_HEADER_SIZE - 8);
+
+/// Writes all of the audio produced by the emulator
+/// to a 16bit stereo PCM WAV file.
+pub struct WavSink {
+	path: String,
+	running: Arc<AtomicBool>,
+
+	/// The frames that didn't fill a chunk, written after the ring buffer is empty.
+	remaining: Arc<Mutex<Vec<(i16, i16)>>>,
+	writer_thread: Option<thread::JoinHandle<()>>
+}
+
+impl WavSink {
+	pub fn new(path: &str) -> WavSink {
+		WavSink {
+			path: path.to_string(),
+			running: Arc::new(AtomicBool::new(false)),
+			remaining: Arc::new(Mutex::new(Vec::new())),
+			writer_thread: None
+		}
+	}
+}
+
+impl AudioSink for WavSink {
+	fn name(&self) -> &'static str { "WAV" }
+
+	fn start(&mut self, ring_buffer: AudioRingBuffer) -> Result<(), String> {
+		let mut file = match File::create(&self.path) {
+			Ok(f) => BufWriter::new(f),
+			Err(e) => return Err(format!("Failed to create {}: {}", self.path, e))
+		};
+
+		// The sizes are filled in once we know how much data was written.
+		if let Err(e) = write_wav_header(&mut file, 0) {
+			return Err(format!("Failed to write WAV header to {}: {}", self.path, e));
+		}
+
+		self.running.store(true, Ordering::Release);
+		let running = self.running.clone();
+		let remaining = self.remaining.clone();
+		let path = self.path.clone();
+		let thread = thread::Builder::new().name("Audio".to_string()).spawn(move || {
+			let mut data_size = 0;
+			if write_wav_data(&mut file, &ring_buffer, &running, &mut data_size) {
+				append_frames(&mut file, &remaining.lock().unwrap(), &mut data_size);
+			}
+			let finished = file.seek(SeekFrom::Start(0))
+				.and_then(|_| write_wav_header(&mut file, data_size))
+				.and_then(|_| file.flush());
+			match finished {
+				Ok(_) => debug_info!("Wrote {} bytes of audio to {}", data_size, path),
+				Err(e) => debug_error!("Failed to finish writing WAV file {}. Error: {}", path, e)
+			}
+		});
+
+		match thread {
+			Ok(t) => {
+				self.writer_thread = Some(t);
+				return Ok(())
+			},
+			Err(e) => {
+				self.running.store(false, Ordering::Release);
+				return Err(format!("Failed to start WAV writer thread: {}", e))
+			}
+		}
+	}
+
+	fn write_remaining(&mut self, frames: &[(i16, i16)]) {
+		self.remaining.lock().unwrap().extend_from_slice(frames);
+	}
+
+	fn stop(&mut self) {
+		self.running.store(false, Ordering::Release);
+		if let Some(t) = self.writer_thread.take() {
+			if t.join().is_err() {
+				debug_error!("Error while waiting for WAV writer thread.");
+			}
+		}
+	}
+
+	fn lossless(&self) -> bool { true }
+}
+
+/// Reads chunks from the ring buffer until the sink is stopped and there is nothing left to read.
+/// Returns false if it stopped writing them before that.
+fn write_wav_data<W: Write>(w: &mut W, ring_buffer: &AudioRingBuffer, running: &AtomicBool, data_size: &mut u32) -> bool {
+	let mut writing = true;
+	loop {
+		let read = ring_buffer.try_read(|frames| {
+			if writing {
+				writing = append_frames(w, &frames[..], data_size);
+			}
+			return true
+		});
+
+		if !read {
+			if !running.load(Ordering::Acquire) { break }
+			thread::sleep(Duration::from_millis(1));
+		}
+	}
+	return writing;
+}
+
+/// Writes frames and adds them to `data_size` unless they would take the file past the size limit.
+/// Returns false if they weren't written.
+fn append_frames<W: Write>(w: &mut W, frames: &[(i16, i16)], data_size: &mut u32) -> bool {
+	let new_size = match data_size.checked_add((frames.len() * 4) as u32) {
+		Some(size) if size <= MAX_WAV_DATA_SIZE => size,
+		_ => {
+			debug_error!("The WAV file reached its 4 GiB size limit, the rest of the audio isn't written.");
+			return false
+		}
+	};
+	match write_frames(w, frames) {
+		Ok(_) => {
+			*data_size = new_size;
+			return true
+		},
+		Err(e) => {
+			debug_error!("Failed to write audio data. Error: {}", e);
+			return false
+		}
+	}
+}
+
+/// Writes frames as 16bit little endian PCM.
+fn write_frames<W: Write>(w: &mut W, frames: &[(i16, i16)]) -> ::std::io::Result<()> {
+	let mut bytes = Vec::with_capacity(frames.len() * 4);
+	for &(left, right) in frames.iter() {
+		bytes.push(left as u8);
+		bytes.push((left >> 8) as u8);
+		bytes.push(right as u8);
+		bytes.push((right >> 8) as u8);
+	}
+	w.write_all(&bytes)
+}
+
+/// Writes the RIFF, fmt and data chunk headers for `data_size` bytes of 16bit stereo PCM.
+pub fn write_wav_header<W: Write>(w: &mut W, data_size: u32) -> ::std::io::Result<()> {
+	let sample_rate = SAMPLE_RATE as u32;
+	let channels = CHANNELS as u16;
+	let bits_per_sample = 16u16;
+	let block_align = channels * (bits_per_sample / 8);
+	let byte_rate = sample_rate * (block_align as u32);
+
+	try!(w.write_all(b"RIFF"));
+	try!(write_u32_le(w, WAV_HEADER_SIZE - 8 + data_size));
+	try!(w.write_all(b"WAVE"));
+
+	try!(w.write_all(b"fmt "));
+	try!(write_u32_le(w, 16));
+	try!(write_u16_le(w, 1)); // PCM
+	try!(write_u16_le(w, channels));
+	try!(write_u32_le(w, sample_rate));
+	try!(write_u32_le(w, byte_rate));
+	try!(write_u16_le(w, block_align));
+	try!(write_u16_le(w, bits_per_sample));
+
+	try!(w.write_all(b"data"));
+	try!(write_u32_le(w, data_size));
+	return Ok(())
+}
+
+fn write_u16_le<W: Write>(w: &mut W, v: u16) -> ::std::io::Result<()> {
+	w.write_all(&[v as u8, (v >> 8) as u8])
+}
+
+fn write_u32_le<W: Write>(w: &mut W, v: u32) -> ::std::io::Result<()> {
+	w.write_all(&[v as u8, (v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8])
+}
@@ -1,70 +1,194 @@
 use glutin::{Event, ElementState, VirtualKeyCode};
 use super::super::Gba;
 use super::super::GbaEventPoll;
-use super::super::INT_KEYPAD;
-use super::imgui_support;
+use super::super::input::InputKey;
 use rust_imgui as imgui;
+use ::debug::debugger;
+use ::pyrite::bindings::{Action, actions_for_key, bind, gamepad_actions, bind_gamepad};
 
 impl GbaEventPoll for Gba {
+	/// The buttons that are pressed here reach the GBA at the start of the next frame.
 	fn poll_device_events(&mut self) {
-		for event in self.device.video.display.poll_events() {
+		self.poll_window_events();
+	}
+}
+
+impl Gba {
+	fn poll_window_events(&mut self) {
+		// Handled after all of the events so that they see the same state.
+		let mut actions = Vec::new();
+
+		for event in self.device.video.poll_events() {
 			match event {
 				Event::Closed => self.request_exit = true,
-				Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Escape)) => {
-					self.request_exit = true
+				// Tracked even while ImGui has the keyboard so that a release isn't missed.
+				Event::KeyboardInput(state, _, Some(VirtualKeyCode::LShift)) |
+				Event::KeyboardInput(state, _, Some(VirtualKeyCode::RShift)) => {
+					self.extras.shift_held = state == ElementState::Pressed;
+				},
+				Event::KeyboardInput(ElementState::Released, _, Some(keycode)) => {
+					for action in actions_for_key(keycode) {
+						actions.push((action, false, keyboard_key(keycode)));
+					}
 				},
 				_ => {}
 			}
 
-			imgui_support::imgui_check_event(&mut self.device.video.im_support, &event);
+			// The settings window is waiting for a key to bind, escape cancels it.
+			// Shift changes what the other keys do so it can't be bound itself.
+			if let Event::KeyboardInput(ElementState::Pressed, _, Some(keycode)) = event {
+				let is_shift = keycode == VirtualKeyCode::LShift || keycode == VirtualKeyCode::RShift;
+				if !is_shift {
+					if let Some(name) = debugger::get_debugger().rebinding.take() {
+						if keycode != VirtualKeyCode::Escape {
+							bind(name, keycode);
+						}
+						continue
+					}
+				}
+			}
+
+			self.device.video.imgui_event(&event);
 			let io = imgui::get_io();
 			let imgui_not_capturing = (io.want_text_input | io.want_capture_keyboard) == 0;
 
-			if imgui_not_capturing {
-				match event {
-				// GENERAL DEBUGGING STUFF:
-					Event::KeyboardInput(state, _, Some(VirtualKeyCode::D)) => {
-						match state {
-							ElementState::Pressed => set_pyrite_dyn_debug!(true),
-							_ => set_pyrite_dyn_debug!(false)
-						}
-					},
-					Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::R)) => {
-						self.cpu.reg_dump_pretty();
-					},
+			// Escape always gets out, even while typing into ImGui.
+			if let Event::KeyboardInput(ElementState::Pressed, _, Some(keycode)) = event {
+				for action in actions_for_key(keycode) {
+					if imgui_not_capturing || (action == Action::Exit && keycode == VirtualKeyCode::Escape) {
+						actions.push((action, true, keyboard_key(keycode)));
+					}
+				}
+			}
+		}
+
+		// Gamepads don't type into ImGui so they're never held back by it.
+		let threshold = (min!(psetting!(gamepad_axis_threshold), 100) * 32767 / 100) as i16;
+		for (gamepad, input, pressed) in self.device.gamepads.poll(threshold) {
+			if pressed {
+				if let Some(name) = debugger::get_debugger().rebinding.take() {
+					bind_gamepad(name, input);
+					continue
+				}
+			}
+			// Bindings are the same for every controller, but each one holds its own buttons.
+			let key = InputKey::Gamepad(gamepad, input);
+			for action in gamepad_actions(input) {
+				match action {
+					Action::Button(mask) if pressed => self.input.gamepad.press(key, mask),
+					Action::Button(_) => self.input.gamepad.release(key),
+					_ => actions.push((action, pressed, key))
+				}
+			}
+		}
+
+		for (action, pressed, key) in actions.into_iter() {
+			if pressed {
+				self.action_pressed(action, key);
+			} else {
+				self.action_released(action, key);
+			}
+		}
+	}
+
+	/// `key` is what pressed it, the buttons it holds are let go when it's released.
+	fn action_pressed(&mut self, action: Action, key: InputKey) {
+		match action {
+			Action::Button(mask) => self.input.keyboard.press(key, mask),
+			Action::Turbo(mask) => self.input.turbo.press(key, mask),
 
-					Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::P)) => {
-						self.extras.request_pause = !self.extras.paused;
-					},
+			// Recording is stopped by any macro key.
+			Action::Macro(slot) => {
+				if self.extras.shift_held || self.input.recording_macro().is_some() {
+					self.toggle_macro_recording(slot);
+				} else {
+					self.play_macro(slot);
+				}
+			},
+			Action::Exit => self.request_exit = true,
+			Action::Pause => self.extras.request_pause = !self.extras.paused,
 
-				// DEBUGGING LAYERS IN GRAPHICS:
-					Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Key1)) => {debug_toggle_layer!(0);},
-					Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Key2)) => {debug_toggle_layer!(1);},
-					Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Key3)) => {debug_toggle_layer!(2);},
-					Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Key4)) => {debug_toggle_layer!(3);},
-					Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Key5)) => {debug_toggle_layer!(4);},
-					Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::L)) => {debug_turn_off_all_layers!();},
-					Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::K)) => {debug_turn_on_all_layers!();},
+			// Rewinds for as long as it's held.
+			Action::Rewind => {
+				if self.movie_active() {
+					console_warn!("Can't rewind while a movie is recording or playing.");
+				} else {
+					self.extras.rewinding = true;
+				}
+			},
 
-				// ACTUAL GBA SHIT:
-					Event::KeyboardInput(ElementState::Pressed, _, Some(keycode)) => {
-						self.joypad.key_pressed(keycode);
-					},
-					Event::KeyboardInput(ElementState::Released, _, Some(keycode)) => {
-						self.joypad.key_released(keycode);
-					},
-					_ => {}
+			// Fast-forwards while held, shift toggles it instead.
+			Action::FastForward => {
+				if self.extras.shift_held {
+					self.extras.fast_forward_toggled = !self.extras.fast_forward_toggled;
+				} else {
+					self.extras.fast_forward_held = true;
 				}
-			}
+			},
+
+			Action::SlowMotion => self.extras.slow_motion = !self.extras.slow_motion,
+			Action::Screenshot => self.extras.request_screenshot = true,
+
+			Action::Slot(slot) => {
+				if self.extras.shift_held {
+					self.extras.request_save_slot = Some(slot);
+				} else {
+					self.extras.request_load_slot = Some(slot);
+				}
+			},
+
+		// GENERAL DEBUGGING STUFF:
+			Action::DynamicDebug => set_pyrite_dyn_debug!(true),
+			Action::RegisterDump => self.cpu.reg_dump_pretty(),
+
+		// DEBUGGING LAYERS IN GRAPHICS:
+			Action::ToggleLayer(layer) => {debug_toggle_layer!(layer);},
+			Action::AllLayersOff => {debug_turn_off_all_layers!();},
+			Action::AllLayersOn => {debug_turn_on_all_layers!();},
 		}
+	}
 
-		if self.joypad.tick(&mut self.cpu) {
-			self.hardware_interrupt(INT_KEYPAD);
+	fn action_released(&mut self, action: Action, key: InputKey) {
+		match action {
+			Action::Button(_) => self.input.keyboard.release(key),
+			Action::Turbo(_) => self.input.turbo.release(key),
+			Action::Rewind => self.extras.rewinding = false,
+			Action::FastForward => self.extras.fast_forward_held = false,
+			Action::DynamicDebug => set_pyrite_dyn_debug!(false),
+			_ => {}
+		}
+	}
+
+	/// Starts recording into a macro slot, or saves what was recorded if a recording is running.
+	fn toggle_macro_recording(&mut self, slot: usize) {
+		match self.input.finish_macro_recording() {
+			Some((recorded_slot, frames)) => {
+				console_log!("Recorded {} frames into Macro {}.", frames.len(), recorded_slot);
+				::pyrite::get_settings().input_macros.insert(format!("Macro {}", recorded_slot), frames);
+				::pyrite::settings_changed();
+			},
+			None => {
+				console_log!("Recording Macro {}, press any macro key to stop.", slot);
+				self.input.start_macro_recording(slot);
+			}
 		}
+	}
 
+	fn play_macro(&mut self, slot: usize) {
+		match ::pyrite::get_settings().input_macros.get(&format!("Macro {}", slot)) {
+			Some(frames) if !frames.is_empty() => self.input.play_macro(frames.clone()),
+			_ => console_warn!("Macro {} is empty, hold shift and press its key to record it.", slot)
+		}
 	}
 }
 
+fn keyboard_key(keycode: VirtualKeyCode) -> InputKey {
+	InputKey::Keyboard(vkc_to_idx(keycode) as u32)
+}
+
+/// The number of keys that `idx_to_vkc` knows about.
+pub const KEY_COUNT: usize = 149;
+
 pub fn idx_to_vkc(idx: usize) -> VirtualKeyCode {
 	match idx {
 		0 => VirtualKeyCode::Key1,
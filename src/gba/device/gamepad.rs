@@ -0,0 +1,197 @@
+//! Game controllers, read through the Linux joystick API (/dev/input/js*) since glutin doesn't
+//! have them. Every controller gets a thread that blocks on its device file and sends what happens
+//! to the emulator, and new controllers are looked for every second so they can be plugged in
+//! at any time. Other platforms don't have controllers yet.
+//!
+//! Buttons and the two directions of every axis are inputs that are bound to the same actions as
+//! keys (see `pyrite::bindings`), an axis presses its input once it's past the threshold in the settings.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::mpsc::{channel, Sender, Receiver};
+use time;
+
+/// The most /dev/input/js* devices that are looked for.
+const MAX_GAMEPADS: usize = 8;
+
+/// How often new controllers are looked for.
+const SCAN_INTERVAL_NS: u64 = 1000000000;
+
+const JS_EVENT_BUTTON: u8 = 0x01;
+const JS_EVENT_AXIS: u8 = 0x02;
+
+/// Set on the events that the driver sends when a device is opened to give the current state.
+const JS_EVENT_INIT: u8 = 0x80;
+
+/// Inputs from 0 to 255 are buttons, axes come after.
+const AXIS_INPUT_START: u32 = 0x100;
+
+pub fn button_input(button: u8) -> u32 {
+	button as u32
+}
+
+pub fn axis_input(axis: u8, positive: bool) -> u32 {
+	AXIS_INPUT_START + ((axis as u32) << 1) + (positive as u32)
+}
+
+pub fn input_name(input: u32) -> String {
+	if input < AXIS_INPUT_START {
+		format!("Button {}", input)
+	} else {
+		let axis = (input - AXIS_INPUT_START) >> 1;
+		format!("Axis {}{}", axis, if (input & 1) != 0 { "+" } else { "-" })
+	}
+}
+
+enum GamepadEvent {
+	Connected(usize),
+	Disconnected(usize),
+	Button { gamepad: usize, button: u8, pressed: bool },
+	Axis { gamepad: usize, axis: u8, value: i16 }
+}
+
+pub struct Gamepads {
+	enabled: bool,
+	sender: Sender<GamepadEvent>,
+	receiver: Receiver<GamepadEvent>,
+
+	/// Devices that have a thread reading them.
+	open: BTreeSet<usize>,
+	pub connected: BTreeSet<usize>,
+	next_scan: u64,
+
+	/// Buttons that are held down.
+	buttons: BTreeSet<(usize, u8)>,
+
+	/// Which way each axis is pushed, -1, 0 or 1.
+	axes: BTreeMap<(usize, u8), i8>
+}
+
+impl Gamepads {
+	pub fn new() -> Gamepads {
+		let (sender, receiver) = channel();
+		Gamepads {
+			enabled: true,
+			sender: sender,
+			receiver: receiver,
+			open: BTreeSet::new(),
+			connected: BTreeSet::new(),
+			next_scan: 0,
+			buttons: BTreeSet::new(),
+			axes: BTreeMap::new()
+		}
+	}
+
+	/// Gamepads that never look for any controllers.
+	pub fn disabled() -> Gamepads {
+		let mut gamepads = Gamepads::new();
+		gamepads.enabled = false;
+		return gamepads;
+	}
+
+	/// Returns the controller and input of everything that was pressed (true) or released (false)
+	/// since the last poll, in order. `threshold` is how far an axis has to be pushed, from 0 to 32767.
+	pub fn poll(&mut self, threshold: i16) -> Vec<(usize, u32, bool)> {
+		let mut inputs = Vec::new();
+		if !self.enabled { return inputs }
+
+		let now = time::precise_time_ns();
+		if now >= self.next_scan {
+			self.next_scan = now + SCAN_INTERVAL_NS;
+			self.scan();
+		}
+
+		while let Ok(event) = self.receiver.try_recv() {
+			match event {
+				GamepadEvent::Connected(gamepad) => {
+					console_log!("Gamepad {} connected.", gamepad);
+					self.connected.insert(gamepad);
+				},
+				GamepadEvent::Disconnected(gamepad) => {
+					// It's also sent for devices that couldn't be opened, those are tried again on the next scan.
+					if self.connected.remove(&gamepad) {
+						console_log!("Gamepad {} disconnected.", gamepad);
+					}
+					self.open.remove(&gamepad);
+					self.release_all(gamepad, &mut inputs);
+				},
+				GamepadEvent::Button { gamepad, button, pressed } => {
+					if pressed { self.buttons.insert((gamepad, button)); }
+					else { self.buttons.remove(&(gamepad, button)); }
+					inputs.push((gamepad, button_input(button), pressed));
+				},
+				GamepadEvent::Axis { gamepad, axis, value } => {
+					let direction = if value > threshold { 1 } else if value < -threshold { -1 } else { 0 };
+					let previous = self.axes.insert((gamepad, axis), direction).unwrap_or(0);
+					if previous != direction {
+						if previous != 0 { inputs.push((gamepad, axis_input(axis, previous > 0), false)); }
+						if direction != 0 { inputs.push((gamepad, axis_input(axis, direction > 0), true)); }
+					}
+				}
+			}
+		}
+		return inputs;
+	}
+
+	/// Lets go of everything that a controller was holding.
+	fn release_all(&mut self, gamepad: usize, inputs: &mut Vec<(usize, u32, bool)>) {
+		let buttons: Vec<_> = self.buttons.iter().filter(|&&(g, _)| g == gamepad).cloned().collect();
+		for (g, button) in buttons {
+			self.buttons.remove(&(g, button));
+			inputs.push((gamepad, button_input(button), false));
+		}
+
+		let axes: Vec<_> = self.axes.iter().filter(|&(&(g, _), _)| g == gamepad).map(|(&k, &v)| (k, v)).collect();
+		for (key, direction) in axes {
+			self.axes.remove(&key);
+			if direction != 0 { inputs.push((gamepad, axis_input(key.1, direction > 0), false)); }
+		}
+	}
+
+	#[cfg(target_os = "linux")]
+	fn scan(&mut self) {
+		use std::path::Path;
+		for gamepad in 0..MAX_GAMEPADS {
+			if self.open.contains(&gamepad) { continue }
+			let path = format!("/dev/input/js{}", gamepad);
+			if !Path::new(&path).exists() { continue }
+			self.open.insert(gamepad);
+			let sender = self.sender.clone();
+			::std::thread::spawn(move || read_gamepad(gamepad, path, sender));
+		}
+	}
+
+	#[cfg(not(target_os = "linux"))]
+	fn scan(&mut self) {}
+}
+
+/// Reads a device until it's unplugged. Each event is 8 bytes:
+/// a u32 timestamp, an i16 value, a u8 type and a u8 button or axis number.
+#[cfg(target_os = "linux")]
+fn read_gamepad(gamepad: usize, path: String, sender: Sender<GamepadEvent>) {
+	use std::fs::File;
+	use std::io::Read;
+
+	let mut file = match File::open(&path) {
+		Ok(f) => f,
+		Err(_) => {
+			// Probably not allowed to read it, it's looked at again with the next scan.
+			let _ = sender.send(GamepadEvent::Disconnected(gamepad));
+			return
+		}
+	};
+	if sender.send(GamepadEvent::Connected(gamepad)).is_err() { return }
+
+	let mut event = [0u8; 8];
+	while file.read_exact(&mut event).is_ok() {
+		let value = (event[4] as u16 | ((event[5] as u16) << 8)) as i16;
+		let number = event[7];
+		let sent = match event[6] & !JS_EVENT_INIT {
+			JS_EVENT_BUTTON => sender.send(GamepadEvent::Button { gamepad: gamepad, button: number, pressed: value != 0 }),
+			JS_EVENT_AXIS => sender.send(GamepadEvent::Axis { gamepad: gamepad, axis: number, value: value }),
+			_ => Ok(())
+		};
+		// The emulator is gone.
+		if sent.is_err() { return }
+	}
+	let _ = sender.send(GamepadEvent::Disconnected(gamepad));
+}
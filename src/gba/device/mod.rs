@@ -5,27 +5,96 @@ pub mod video;
 pub mod audio;
 pub mod events;
 pub mod imgui_support;
+pub mod recorder;
+pub mod gamepad;
 
 use self::video::*;
 use self::audio::*;
+use self::recorder::AvRecorder;
+use self::gamepad::Gamepads;
+use super::hw::lcd::GbaLcdScreenBuffer;
 pub use self::events::*;
 
 pub struct GbaDevice {
-	pub video: VideoDevice,
-	pub audio: AudioDevice
+	pub video: Box<VideoSink>,
+	pub audio: AudioDevice,
+	pub recorder: Option<AvRecorder>,
+	pub gamepads: Gamepads
 }
 
 impl GbaDevice {
+	/// Creates a device with a window, audio output and gamepads.
 	pub fn new() -> GbaDevice {
+		let mut device = GbaDevice::with_sinks(Box::new(GlVideoSink::new()), Box::new(PortAudioSink::new()));
+		device.gamepads = Gamepads::new();
+		return device;
+	}
+
+	/// Creates a device that doesn't need a window, OpenGL or a sound device.
+	pub fn headless() -> GbaDevice {
+		GbaDevice::with_sinks(Box::new(HeadlessVideoSink::new()), Box::new(NullSink::new()))
+	}
+
+	/// Creates a device that sends its video and audio to the given sinks.
+	/// If the audio sink fails to start, audio falls back to a null sink. Gamepads aren't looked for.
+	pub fn with_sinks(video_sink: Box<VideoSink>, audio_sink: Box<AudioSink>) -> GbaDevice {
 		let mut ret = GbaDevice {
-			video: VideoDevice::new(),
-			audio: AudioDevice::new()
+			video: video_sink,
+			audio: AudioDevice::new(audio_sink),
+			recorder: None,
+			gamepads: Gamepads::disabled()
 		};
 		ret.audio.start();
 		return ret;
 	}
 
 	pub fn close(&mut self) {
+		self.stop_recording();
 		self.audio.stop();
 	}
+
+	pub fn recording(&self) -> bool {
+		self.recorder.is_some()
+	}
+
+	/// Starts recording video to `path` as Y4M and audio next to it as WAV.
+	/// Any recording that is already running is finished first.
+	pub fn start_recording(&mut self, path: &str) -> Result<(), String> {
+		self.stop_recording();
+		let recorder = try!(AvRecorder::create(path));
+		console_log!("Recording to {}", recorder.video_path());
+		self.recorder = Some(recorder);
+		self.audio.capture = Some(Vec::with_capacity(1024));
+		return Ok(())
+	}
+
+	pub fn stop_recording(&mut self) {
+		self.audio.capture = None;
+		if let Some(recorder) = self.recorder.take() {
+			let frames = recorder.frames;
+			let path = recorder.video_path().to_string();
+			match recorder.finish() {
+				Ok(_) => console_log!("Recorded {} frames to {}", frames, path),
+				Err(e) => console_error!("{}", e)
+			}
+		}
+	}
+
+	/// Writes a finished frame and the audio generated since the last one to the recording.
+	/// If writing fails the recording is stopped.
+	pub fn record_frame(&mut self, buffer: &GbaLcdScreenBuffer) {
+		let result = match (self.recorder.as_mut(), self.audio.capture.as_mut()) {
+			(Some(recorder), Some(capture)) => {
+				let result = recorder.write_frame(buffer, capture);
+				capture.clear();
+				result
+			},
+			_ => return
+		};
+
+		if let Err(e) = result {
+			console_error!("{}", e);
+			self.stop_recording();
+		}
+	}
 }
\ No newline at end of file
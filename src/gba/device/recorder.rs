@@ -0,0 +1,146 @@
+//! Records every emulated frame and the audio generated during it.
+//! Video is written as an uncompressed Y4M (4:4:4, full range) and audio
+//! as a 16bit stereo WAV next to it. Both are written from the emulation
+//! thread at the end of each frame, so the two streams never drift apart,
+//! no matter how fast or slow the emulator is running.
+
+use std::io;
+use std::io::prelude::*;
+use std::io::{SeekFrom, BufWriter};
+use std::fs::File;
+use std::path::Path;
+use super::super::hw::lcd::GbaLcdScreenBuffer;
+use super::video::{GBA_SCREEN_WIDTH, GBA_SCREEN_HEIGHT};
+use super::audio::wav_sink::{write_wav_header, MAX_WAV_DATA_SIZE};
+
+/// Where recordings started from the debugger go.
+pub const RECORDING_DIR: &'static str = "recordings";
+
+/// The GBA's refresh rate as a fraction: 2^24 cycles per second / 280896 cycles per frame.
+const FRAME_RATE_NUM: u32 = 262144;
+const FRAME_RATE_DEN: u32 = 4389;
+
+const PLANE_SIZE: usize = (GBA_SCREEN_WIDTH * GBA_SCREEN_HEIGHT) as usize;
+
+pub struct AvRecorder {
+	video_path: String,
+	audio_path: String,
+	video: BufWriter<File>,
+	audio: BufWriter<File>,
+
+	/// Y, U and V planes of the frame that is being written.
+	planes: Vec<u8>,
+
+	pub frames: u64,
+	pub audio_bytes: u32
+}
+
+impl AvRecorder {
+	/// Creates the video file at `path` with a `.y4m` extension and the audio
+	/// file next to it with a `.wav` extension.
+	pub fn create(path: &str) -> Result<AvRecorder, String> {
+		let video_path = Path::new(path).with_extension("y4m").to_string_lossy().into_owned();
+		let audio_path = Path::new(path).with_extension("wav").to_string_lossy().into_owned();
+
+		let mut video = match File::create(&video_path) {
+			Ok(f) => BufWriter::new(f),
+			Err(e) => return Err(format!("Failed to create {}: {}", video_path, e))
+		};
+		let mut audio = match File::create(&audio_path) {
+			Ok(f) => BufWriter::new(f),
+			Err(e) => return Err(format!("Failed to create {}: {}", audio_path, e))
+		};
+
+		let header = format!("YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C444 XCOLORRANGE=FULL\n",
+			GBA_SCREEN_WIDTH, GBA_SCREEN_HEIGHT, FRAME_RATE_NUM, FRAME_RATE_DEN);
+		if let Err(e) = video.write_all(header.as_bytes()) {
+			return Err(format!("Failed to write Y4M header to {}: {}", video_path, e));
+		}
+		// The sizes are filled in when the recording is finished.
+		if let Err(e) = write_wav_header(&mut audio, 0) {
+			return Err(format!("Failed to write WAV header to {}: {}", audio_path, e));
+		}
+
+		Ok(AvRecorder {
+			video_path: video_path,
+			audio_path: audio_path,
+			video: video,
+			audio: audio,
+			planes: vec![0; PLANE_SIZE * 3],
+			frames: 0,
+			audio_bytes: 0
+		})
+	}
+
+	/// The path of the video file, the audio file has the same name with a `.wav` extension.
+	pub fn video_path(&self) -> &str {
+		&self.video_path
+	}
+
+	/// Writes a finished frame along with every audio frame that was generated during it.
+	/// Fails without writing anything once the WAV file would go past its 4 GiB limit.
+	pub fn write_frame(&mut self, buffer: &GbaLcdScreenBuffer, audio: &[(i16, i16)]) -> Result<(), String> {
+		let audio_bytes = match self.audio_bytes.checked_add((audio.len() * 4) as u32) {
+			Some(bytes) if bytes <= MAX_WAV_DATA_SIZE => bytes,
+			_ => return Err(format!("{} reached the 4 GiB size limit of WAV files.", self.audio_path))
+		};
+		try!(self.write_video(buffer).map_err(|e| format!("Failed to write video to {}: {}", self.video_path, e)));
+		try!(self.write_audio(audio).map_err(|e| format!("Failed to write audio to {}: {}", self.audio_path, e)));
+		self.audio_bytes = audio_bytes;
+		self.frames += 1;
+		return Ok(())
+	}
+
+	fn write_video(&mut self, buffer: &GbaLcdScreenBuffer) -> io::Result<()> {
+		{
+			let (y_plane, uv_planes) = self.planes.split_at_mut(PLANE_SIZE);
+			let (u_plane, v_plane) = uv_planes.split_at_mut(PLANE_SIZE);
+			let mut idx = 0;
+			for line in buffer.iter() {
+				for &(r, g, b) in line.iter() {
+					let (y, u, v) = rgb_to_yuv(r, g, b);
+					y_plane[idx] = y;
+					u_plane[idx] = u;
+					v_plane[idx] = v;
+					idx += 1;
+				}
+			}
+		}
+		try!(self.video.write_all(b"FRAME\n"));
+		return self.video.write_all(&self.planes);
+	}
+
+	fn write_audio(&mut self, audio: &[(i16, i16)]) -> io::Result<()> {
+		let mut bytes = Vec::with_capacity(audio.len() * 4);
+		for &(left, right) in audio.iter() {
+			bytes.push(left as u8);
+			bytes.push((left >> 8) as u8);
+			bytes.push(right as u8);
+			bytes.push((right >> 8) as u8);
+		}
+		return self.audio.write_all(&bytes);
+	}
+
+	/// Fills in the WAV header and flushes both files.
+	pub fn finish(mut self) -> Result<(), String> {
+		try!(self.video.flush().map_err(|e| format!("Failed to finish writing {}: {}", self.video_path, e)));
+		let audio_bytes = self.audio_bytes;
+		let finished = self.audio.seek(SeekFrom::Start(0))
+			.and_then(|_| write_wav_header(&mut self.audio, audio_bytes))
+			.and_then(|_| self.audio.flush());
+		return finished.map_err(|e| format!("Failed to finish writing {}: {}", self.audio_path, e));
+	}
+}
+
+/// Full range BT.601, the same conversion JPEG uses.
+fn rgb_to_yuv(r: u8, g: u8, b: u8) -> (u8, u8, u8) {
+	let (r, g, b) = (r as i32, g as i32, b as i32);
+	let y = (19595 * r + 38470 * g + 7471 * b + 32768) >> 16;
+	let u = ((-11059 * r - 21709 * g + 32768 * b + 32768) >> 16) + 128;
+	let v = ((32768 * r - 27439 * g - 5329 * b + 32768) >> 16) + 128;
+	(clamp_u8(y), clamp_u8(u), clamp_u8(v))
+}
+
+fn clamp_u8(v: i32) -> u8 {
+	if v < 0 { 0 } else if v > 255 { 255 } else { v as u8 }
+}
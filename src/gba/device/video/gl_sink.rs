@@ -2,14 +2,12 @@ use glutin;
 use gl;
 use std::mem;
 use std::ptr;
-use super::imgui_support;
-use super::imgui_support::ImGuiSupport;
-use super::super::hw::lcd::GbaLcdScreenBuffer;
+use super::super::imgui_support;
+use super::super::imgui_support::ImGuiSupport;
+use super::super::super::hw::lcd::GbaLcdScreenBuffer;
+use super::*;
 // use ::util::frame_counter::FrameCounter;
 
-const ENABLE_VSYNC: bool = true;
-const GBA_SCREEN_WIDTH: u32 = 240;
-const GBA_SCREEN_HEIGHT: u32 = 160;
 
 macro_rules! gl_check_error {
 	() => ({
@@ -17,7 +15,8 @@ macro_rules! gl_check_error {
 	})
 }
 
-pub struct VideoDevice {
+/// Displays the GBA screen and the debugger in a glutin window using OpenGL.
+pub struct GlVideoSink {
 	pub display: glutin::Window,
 	pub im_support: ImGuiSupport,
 	pub gl_vbo_handle: u32,
@@ -30,12 +29,14 @@ pub struct VideoDevice {
 	pub gl_attrib_location_tex: u32,
 }
 
-impl VideoDevice {
-	pub fn new() -> VideoDevice {
+impl GlVideoSink {
+	pub fn new() -> GlVideoSink {
 		let mut builder = glutin::WindowBuilder::new()
 			.with_dimensions(GBA_SCREEN_WIDTH * 4, GBA_SCREEN_HEIGHT * 4);
 
-		if ENABLE_VSYNC {
+		// The frame limiter keeps the speed right, vsync on its own would run
+		// games at the monitor's refresh rate.
+		if psetting!(vsync) {
 			builder = builder.with_vsync();
 		}
 
@@ -46,7 +47,7 @@ impl VideoDevice {
 			window.make_current().expect("Failed to make pyrite window current context.");
 		}
 
-		let mut ret = VideoDevice {
+		let mut ret = GlVideoSink {
 			display: window,
 			im_support: ImGuiSupport::new(),
 			gl_vbo_handle: 0,
@@ -171,14 +172,20 @@ impl VideoDevice {
 		debug_info!("Successfully destroyed video device.");
 	}
 
-	pub fn prepare_imgui(&mut self) {
+}
+
+impl VideoSink for GlVideoSink {
+	fn name(&self) -> &'static str { "OpenGL" }
+
+	fn prepare_imgui(&mut self) -> bool {
 		let window_size = self.display.get_inner_size().expect("Unable to retrieve window dimensions.");
 		let hidpi_factor = self.display.hidpi_factor();
 		imgui_support::imgui_new_frame(&mut self.im_support, window_size, hidpi_factor);
+		return true
 	}
 
 	/// Renders the screen texture.
-	pub fn render(&mut self, buffer: &GbaLcdScreenBuffer) {
+	fn render(&mut self, buffer: &GbaLcdScreenBuffer) {
 		profiler_begin!("Render GBA Frame");
 		unsafe {
 			gl::ClearColor(1.0, 0.0, 1.0, 1.0);
@@ -217,9 +224,19 @@ impl VideoDevice {
 		self.display.swap_buffers().expect("Swapping glutin window buffers.");
 		profiler_end!();
 	}
+
+	fn has_window(&self) -> bool { true }
+
+	fn poll_events(&mut self) -> Vec<glutin::Event> {
+		self.display.poll_events().collect()
+	}
+
+	fn imgui_event(&mut self, event: &glutin::Event) {
+		imgui_support::imgui_check_event(&mut self.im_support, event);
+	}
 }
 
-impl Drop for VideoDevice {
+impl Drop for GlVideoSink {
 	fn drop(&mut self) {
 		self.uninit();
 	}
@@ -0,0 +1,28 @@
+use super::super::super::hw::lcd::GbaLcdScreenBuffer;
+use super::*;
+
+/// A video sink that doesn't display anything.
+/// Callers that want the picture read it straight from `GbaLcd::screen_buffer`
+/// (or `Gba::screen_buffer`) after a frame.
+pub struct HeadlessVideoSink {
+	/// The number of frames that have been rendered.
+	pub frames: u64
+}
+
+impl HeadlessVideoSink {
+	pub fn new() -> HeadlessVideoSink {
+		HeadlessVideoSink {
+			frames: 0
+		}
+	}
+}
+
+impl VideoSink for HeadlessVideoSink {
+	fn name(&self) -> &'static str { "Headless" }
+
+	fn prepare_imgui(&mut self) -> bool { false }
+
+	fn render(&mut self, _: &GbaLcdScreenBuffer) {
+		self.frames += 1;
+	}
+}
@@ -0,0 +1,34 @@
+pub mod gl_sink;
+pub mod headless_sink;
+
+use glutin::Event;
+use super::super::hw::lcd::GbaLcdScreenBuffer;
+
+pub use self::gl_sink::GlVideoSink;
+pub use self::headless_sink::HeadlessVideoSink;
+
+pub const GBA_SCREEN_WIDTH: u32 = 240;
+pub const GBA_SCREEN_HEIGHT: u32 = 160;
+
+/// Something that displays the frames produced by the emulator.
+pub trait VideoSink {
+	/// A short name for the sink used in log messages.
+	fn name(&self) -> &'static str;
+
+	/// Starts a new ImGui frame.
+	/// Returns false if this sink has no GUI, in which case
+	/// the debugger should not be rendered.
+	fn prepare_imgui(&mut self) -> bool;
+
+	/// Called once at the end of every frame with the finished screen.
+	fn render(&mut self, buffer: &GbaLcdScreenBuffer);
+
+	/// Returns true if this sink shows a window that someone is playing in.
+	fn has_window(&self) -> bool { false }
+
+	/// Takes the input events that the window got since the last call.
+	fn poll_events(&mut self) -> Vec<Event> { Vec::new() }
+
+	/// Passes an input event on to the sink's ImGui.
+	fn imgui_event(&mut self, _event: &Event) {}
+}
@@ -25,81 +25,101 @@ const PSG_VOLUME_MULS: [f32; 8] = [
 	0.5714285714285714, 0.7142857142857143, 0.8571428571428571, 1.0
 ];
 
-pub fn tick(cpu: &mut ArmCpu, device: &AudioDevice) {
-	measure_start(MEASURE_AUDIO_TICK_TIME);
-	measure_iteration(MEASURE_AUDIO_TICK_TIME);
+/// The GBA's system clock frequency in Hz (2^24).
+pub const GBA_CLOCK_FREQ: u64 = 16_777_216;
 
-	// console_warn!("{} OUT REMINAING", cpu.memory.internal_regs.audio_fifo_a.out_remaining());
+/// Generates every sample that has become due since the last call.
+/// This should be called after every CPU or DMA step so that samples are
+/// produced in step with timer overflows and sound register writes.
+/// Each sample is timestamped with the CPU cycle that it falls on.
+pub fn tick(cpu: &mut ArmCpu, device: &mut AudioDevice) {
+	let elapsed = cpu.clock.audio_clock;
+	if elapsed == 0 { return }
+	cpu.clock.audio_clock = 0;
 
-	device.ring_buffer.try_write(|frames| {
-		let soundcnt_l = cpu.memory.get_reg(ioreg::SOUNDCNT_L);
-		let soundcnt_h = cpu.memory.get_reg(ioreg::SOUNDCNT_H);
-		let soundcnt_x = cpu.memory.get_reg(ioreg::SOUNDCNT_X);
+	device.sample_clock += (elapsed as u64) * (device.sample_rate as u64);
+	if device.sample_clock < GBA_CLOCK_FREQ { return }
 
-		let mut mixer = GbaAudioMixer::new(soundcnt_l, soundcnt_h, soundcnt_x);
-		mixer.init();
+	measure_start(MEASURE_AUDIO_TICK_TIME);
 
-		channel1::init(cpu, device);
-		channel2::init(cpu, device);
-		channel3::init(cpu, device);
-		channel4::init(cpu, device);
-		channel_ab::init(cpu, device);
+	while device.sample_clock >= GBA_CLOCK_FREQ {
+		measure_iteration(MEASURE_AUDIO_TICK_TIME);
+		device.sample_clock -= GBA_CLOCK_FREQ;
 
-		for idx in 0..frames.len() {
-			mixer.psg_count = 0;
+		// Whatever is left in the sample clock is how far past the sample we've run.
+		let overshoot = device.sample_clock / (device.sample_rate as u64);
+		let sample_cycle = cpu.clock.cycles - min!(overshoot, cpu.clock.cycles);
 
-			// Sound 1:
-			if cpu.memory.internal_regs.audio_channel1.playing {
-				mixer.c1 = AMPLITUDE_OUTPUTS[channel1::tick(cpu, device)];
-				mixer.psg_count += 1;
-			} else {
-				mixer.c1 = 0;
-			}
+		let frame = generate_sample(cpu, device);
+		device.push_frame(sample_cycle, frame);
+	}
 
-			// Sound 2:
-			if cpu.memory.internal_regs.audio_channel2.playing {
-				mixer.c2 = AMPLITUDE_OUTPUTS[channel2::tick(cpu, device)];
-				mixer.psg_count += 1;
-			} else {
-				mixer.c2 = 0;
-			}
+	measure_end(MEASURE_AUDIO_TICK_TIME);
+}
 
-			// Sound 3:
-			if cpu.memory.internal_regs.audio_channel3.playing {
-				mixer.c3 = channel3::tick(cpu, device);
-				mixer.psg_count += 1;
-			} else {
-				mixer.c3 = 0;
-			}
+fn generate_sample(cpu: &mut ArmCpu, device: &AudioDevice) -> (i16, i16) {
+	// The registers are read for every sample so that a write
+	// is heard starting from the very next sample.
+	let soundcnt_l = cpu.memory.get_reg(ioreg::SOUNDCNT_L);
+	let soundcnt_h = cpu.memory.get_reg(ioreg::SOUNDCNT_H);
+	let soundcnt_x = cpu.memory.get_reg(ioreg::SOUNDCNT_X);
+
+	let mut mixer = GbaAudioMixer::new(soundcnt_l, soundcnt_h, soundcnt_x);
+	mixer.init();
+
+	channel1::init(cpu, device);
+	channel2::init(cpu, device);
+	channel3::init(cpu, device);
+	channel4::init(cpu, device);
+
+	mixer.psg_count = 0;
+
+	// Sound 1:
+	if cpu.memory.internal_regs.audio_channel1.playing {
+		mixer.c1 = AMPLITUDE_OUTPUTS[channel1::tick(cpu, device)];
+		mixer.psg_count += 1;
+	} else {
+		mixer.c1 = 0;
+	}
 
-			// Sound 4:
-			if cpu.memory.internal_regs.audio_channel4.playing {
-				mixer.c4 = AMPLITUDE_OUTPUTS[channel4::tick(cpu, device)];
-				mixer.psg_count += 1;
-			} else {
-				mixer.c4 = 0;
-			}
+	// Sound 2:
+	if cpu.memory.internal_regs.audio_channel2.playing {
+		mixer.c2 = AMPLITUDE_OUTPUTS[channel2::tick(cpu, device)];
+		mixer.psg_count += 1;
+	} else {
+		mixer.c2 = 0;
+	}
 
-			// DMA Sound:
-			if (soundcnt_x & 0x80) != 0 {
-				mixer.ca = channel_ab::tick_a(cpu);
-				mixer.cb = channel_ab::tick_b(cpu);
-			} else {
-				mixer.ca = 0;
-				mixer.cb = 0;
-			}
+	// Sound 3:
+	if cpu.memory.internal_regs.audio_channel3.playing {
+		mixer.c3 = channel3::tick(cpu, device);
+		mixer.psg_count += 1;
+	} else {
+		mixer.c3 = 0;
+	}
 
-			if psetting!(sound_enabled) {
-				frames[idx] = mixer.mix();
-			} else {
-				frames[idx] = (0, 0);
-			}
-		}
+	// Sound 4:
+	if cpu.memory.internal_regs.audio_channel4.playing {
+		mixer.c4 = AMPLITUDE_OUTPUTS[channel4::tick(cpu, device)];
+		mixer.psg_count += 1;
+	} else {
+		mixer.c4 = 0;
+	}
 
-		return true
-	});
+	// DMA Sound:
+	if (soundcnt_x & 0x80) != 0 {
+		mixer.ca = channel_ab::tick_a(cpu);
+		mixer.cb = channel_ab::tick_b(cpu);
+	} else {
+		mixer.ca = 0;
+		mixer.cb = 0;
+	}
 
-	measure_end(MEASURE_AUDIO_TICK_TIME);
+	if psetting!(sound_enabled) {
+		return mixer.mix();
+	} else {
+		return (0, 0);
+	}
 }
 
 #[derive(Default)]
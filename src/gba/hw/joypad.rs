@@ -1,6 +1,5 @@
 use super::super::core::cpu::ArmCpu;
 use super::super::core::memory::*;
-use glutin::VirtualKeyCode;
 
 #[derive(RustcEncodable, RustcDecodable)]
 pub struct GbaJoypad {
@@ -41,16 +40,16 @@ pub struct GbaJoypad {
 // In logical OR mode, an interrupt is requested when at least one of the selected buttons is pressed.
 // In logical AND mode, an interrupt is requested when ALL of the selected buttons are pressed.
 
-const GBA_BTN_A: u16 = 0x1;
-const GBA_BTN_B: u16 = 0x2;
-const GBA_BTN_SELECT: u16 = 0x4;
-const GBA_BTN_START: u16 = 0x8;
-const GBA_BTN_RIGHT: u16 = 0x10;
-const GBA_BTN_LEFT: u16 = 0x20; 
-const GBA_BTN_UP: u16 = 0x40;
-const GBA_BTN_DOWN: u16 = 0x80;
-const GBA_BTN_R: u16 = 0x100;
-const GBA_BTN_L: u16 = 0x200;
+pub const GBA_BTN_A: u16 = 0x1;
+pub const GBA_BTN_B: u16 = 0x2;
+pub const GBA_BTN_SELECT: u16 = 0x4;
+pub const GBA_BTN_START: u16 = 0x8;
+pub const GBA_BTN_RIGHT: u16 = 0x10;
+pub const GBA_BTN_LEFT: u16 = 0x20; 
+pub const GBA_BTN_UP: u16 = 0x40;
+pub const GBA_BTN_DOWN: u16 = 0x80;
+pub const GBA_BTN_R: u16 = 0x100;
+pub const GBA_BTN_L: u16 = 0x200;
 
 const KEY_MASK: u16 = 0x3ff; // Mask that takes only the keys.
 
@@ -69,12 +68,13 @@ impl GbaJoypad {
 			let keycnt = cpu.memory.get_reg(ioreg::KEYCNT);
 			if (keycnt & 0x4000) != 0 { // IRQ is enabled
 				let key_cnt_masked = keycnt & KEY_MASK;
+				let pressed = self.pressed_buttons();
 				if (keycnt & 0x8000) != 0 { // Logical AND Mode
-					if (key_cnt_masked & (self.key_input & KEY_MASK)) == key_cnt_masked {
+					if (key_cnt_masked & pressed) == key_cnt_masked {
 						return true
 					}
 				} else { // Logical OR Mode
-					if (key_cnt_masked & (self.key_input & KEY_MASK)) != 0 {
+					if (key_cnt_masked & pressed) != 0 {
 						return true
 					}
 				}
@@ -83,41 +83,35 @@ impl GbaJoypad {
 		return false
 	}
 
-	pub fn key_pressed(&mut self, keycode: VirtualKeyCode) {
-		if let Some(mask) = self.map_keycode_to_mask(keycode) {
-			self.set_button_pressed(mask);
+	/// Sets the state of every button at once, see `gba::input` for where they come from.
+	/// A set bit in `pressed` means that the button is being held down.
+	pub fn set_buttons(&mut self, pressed: u16) {
+		let key_input = !pressed & KEY_MASK;
+		if key_input != self.key_input {
+			self.key_input = key_input;
+			self.dirty = true;
 		}
 	}
 
-	pub fn key_released(&mut self, keycode: VirtualKeyCode) {
-		if let Some(mask) = self.map_keycode_to_mask(keycode) {
-			self.set_button_released(mask);
-		}
-	}
-
-	pub fn map_keycode_to_mask(&self, keycode: VirtualKeyCode) -> Option<u16> {
-		match keycode {
-			VirtualKeyCode::Z => Some(GBA_BTN_A),
-			VirtualKeyCode::X => Some(GBA_BTN_B),
-			VirtualKeyCode::Back => Some(GBA_BTN_SELECT),
-			VirtualKeyCode::Return => Some(GBA_BTN_START),
-			VirtualKeyCode::Right => Some(GBA_BTN_RIGHT),
-			VirtualKeyCode::Left => Some(GBA_BTN_LEFT),
-			VirtualKeyCode::Up => Some(GBA_BTN_UP),
-			VirtualKeyCode::Down => Some(GBA_BTN_DOWN),
-			VirtualKeyCode::A => Some(GBA_BTN_L),
-			VirtualKeyCode::S => Some(GBA_BTN_R),
-			_ => None
-		}
-	}
-
-	fn set_button_pressed(&mut self, mask: u16) {
-		self.key_input &= !mask; // 0 = button pressed
-		self.dirty = true;
+	/// Returns a mask of the buttons that are currently held down.
+	pub fn pressed_buttons(&self) -> u16 {
+		!self.key_input & KEY_MASK
 	}
+}
 
-	fn set_button_released(&mut self, mask: u16) {
-		self.key_input |= mask; // 1 = button released
-		self.dirty = true;
+/// Returns the mask for a button given its name (e.g. "A", "start", "Left").
+pub fn button_from_name(name: &str) -> Option<u16> {
+	match name.to_lowercase().as_ref() {
+		"a" => Some(GBA_BTN_A),
+		"b" => Some(GBA_BTN_B),
+		"select" => Some(GBA_BTN_SELECT),
+		"start" => Some(GBA_BTN_START),
+		"right" => Some(GBA_BTN_RIGHT),
+		"left" => Some(GBA_BTN_LEFT),
+		"up" => Some(GBA_BTN_UP),
+		"down" => Some(GBA_BTN_DOWN),
+		"r" => Some(GBA_BTN_R),
+		"l" => Some(GBA_BTN_L),
+		_ => None
 	}
-}
\ No newline at end of file
+}
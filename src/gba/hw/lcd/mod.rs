@@ -136,6 +136,17 @@ impl GbaLcd {
 		self.blend_line(line, memory);
 	}
 
+	/// Used instead of `render_line` for frames that are skipped.
+	/// Nothing is drawn but the affine backgrounds still move along.
+	pub fn skip_line(&mut self, memory: &mut GbaMemory) {
+		let dispcnt = memory.get_reg(ioreg::DISPCNT);
+		match dispcnt & 0x7 {
+			1 => mode1::skip_mode_1(dispcnt, memory),
+			2 => mode2::skip_mode_2(dispcnt, memory),
+			_ => {}
+		}
+	}
+
 	fn clear_obj_line(&mut self) {
 		for i in 0..240 {
 			self.lines.obj[i] = 0;
@@ -28,15 +28,7 @@ pub fn render_mode_1(dispcnt: u16, memory: &mut GbaMemory, line: u16, lines: &mu
 	try_render_text_bg(memory, 0x9, ioreg::BG1CNT, ioreg::BG1HOFS, ioreg::BG1VOFS, &mut lines.bg1);
 
 	if ((dispcnt >> 10) & 1) != 0 {
-		let params = BGRotScaleParams {
-			ref_x_reg: ioreg::BG2X,
-			ref_y_reg: ioreg::BG2Y,
-			dx_reg: ioreg::BG2PA,
-			dy_reg: ioreg::BG2PC,
-			dmx_reg: ioreg::BG2PB,
-			dmy_reg: ioreg::BG2PD
-		};
-		draw_tiles_rs_mode(memory.get_reg(ioreg::BG2CNT), params, memory, &mut lines.bg2);
+		draw_tiles_rs_mode(memory.get_reg(ioreg::BG2CNT), BG2_RS_PARAMS, memory, &mut lines.bg2);
 	}
 	
 	draw_objs(
@@ -46,4 +38,10 @@ pub fn render_mode_1(dispcnt: u16, memory: &mut GbaMemory, line: u16, lines: &mu
 		memory, 
 		line, 
 		lines);
-}
\ No newline at end of file
+}
+/// Moves BG2's reference point along for a line that isn't drawn.
+pub fn skip_mode_1(dispcnt: u16, memory: &mut GbaMemory) {
+	if ((dispcnt >> 10) & 1) != 0 {
+		advance_rs_reference(&BG2_RS_PARAMS, memory);
+	}
+}
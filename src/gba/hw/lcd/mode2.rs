@@ -10,27 +10,11 @@ pub fn render_mode_2(dispcnt: u16, memory: &mut GbaMemory, line: u16, lines: &mu
 	lines.bg3_enable = true;
 
 	if ((dispcnt >> 10) & 1) != 0 {
-		let params = BGRotScaleParams {
-			ref_x_reg: ioreg::BG2X,
-			ref_y_reg: ioreg::BG2Y,
-			dx_reg: ioreg::BG2PA,
-			dy_reg: ioreg::BG2PC,
-			dmx_reg: ioreg::BG2PB,
-			dmy_reg: ioreg::BG2PD
-		};
-		draw_tiles_rs_mode(memory.get_reg(ioreg::BG2CNT), params, memory, &mut lines.bg2);
+		draw_tiles_rs_mode(memory.get_reg(ioreg::BG2CNT), BG2_RS_PARAMS, memory, &mut lines.bg2);
 	}
 
 	if ((dispcnt >> 11) & 1) != 0 {
-		let params = BGRotScaleParams {
-			ref_x_reg: ioreg::BG3X,
-			ref_y_reg: ioreg::BG3Y,
-			dx_reg: ioreg::BG3PA,
-			dy_reg: ioreg::BG3PC,
-			dmx_reg: ioreg::BG3PB,
-			dmy_reg: ioreg::BG3PD
-		};
-		draw_tiles_rs_mode(memory.get_reg(ioreg::BG3CNT), params, memory, &mut lines.bg3);
+		draw_tiles_rs_mode(memory.get_reg(ioreg::BG3CNT), BG3_RS_PARAMS, memory, &mut lines.bg3);
 	}
 	
 	draw_objs(
@@ -40,4 +24,14 @@ pub fn render_mode_2(dispcnt: u16, memory: &mut GbaMemory, line: u16, lines: &mu
 		memory, 
 		line, 
 		lines);
-}
\ No newline at end of file
+}
+/// Moves the reference points along for a line that isn't drawn.
+pub fn skip_mode_2(dispcnt: u16, memory: &mut GbaMemory) {
+	if ((dispcnt >> 10) & 1) != 0 {
+		advance_rs_reference(&BG2_RS_PARAMS, memory);
+	}
+
+	if ((dispcnt >> 11) & 1) != 0 {
+		advance_rs_reference(&BG3_RS_PARAMS, memory);
+	}
+}
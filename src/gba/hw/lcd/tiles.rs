@@ -165,6 +165,24 @@ pub struct BGRotScaleParams {
 	pub dmy_reg: IORegister16 // pd
 }
 
+pub const BG2_RS_PARAMS: BGRotScaleParams = BGRotScaleParams {
+	ref_x_reg: ioreg::BG2X,
+	ref_y_reg: ioreg::BG2Y,
+	dx_reg: ioreg::BG2PA,
+	dy_reg: ioreg::BG2PC,
+	dmx_reg: ioreg::BG2PB,
+	dmy_reg: ioreg::BG2PD
+};
+
+pub const BG3_RS_PARAMS: BGRotScaleParams = BGRotScaleParams {
+	ref_x_reg: ioreg::BG3X,
+	ref_y_reg: ioreg::BG3Y,
+	dx_reg: ioreg::BG3PA,
+	dy_reg: ioreg::BG3PC,
+	dmx_reg: ioreg::BG3PB,
+	dmy_reg: ioreg::BG3PD
+};
+
 pub fn draw_tiles_rs_mode(bgcnt: u16, params: BGRotScaleParams, memory: &mut GbaMemory, bg_line: &mut GbaBGLine) {
 	// I work with signed types in this function instead of the normal u32
 	// for reasons that I immediately forgot after writing it. (◕‿◕✿)
@@ -173,9 +191,7 @@ pub fn draw_tiles_rs_mode(bgcnt: u16, params: BGRotScaleParams, memory: &mut Gba
 	let mut x = sx;
 	let mut y = sy;
 	let dx = (memory.get_reg(params.dx_reg) as i16) as i32; // sign extension from 16bits to 32 bits
-	let dmx = (memory.get_reg(params.dmx_reg) as i16) as i32; // sign extension from 16bits to 32 bits
 	let dy = (memory.get_reg(params.dy_reg) as i16) as i32; // sign extension from 16bits to 32 bits
-	let dmy = (memory.get_reg(params.dmy_reg) as i16) as i32; // sign extension from 16bits to 32 bits
 
 	{
 		let vram_tile_data = memory.get_slice(0x06000000, 0x0600FFFF);
@@ -252,6 +268,14 @@ pub fn draw_tiles_rs_mode(bgcnt: u16, params: BGRotScaleParams, memory: &mut Gba
 		}
 	}
 
+	advance_rs_reference(&params, memory);
+}
+
+/// Moves the reference point along to the next line. This also has to happen
+/// for lines that aren't drawn (see `GbaLcd::skip_line`).
+pub fn advance_rs_reference(params: &BGRotScaleParams, memory: &mut GbaMemory) {
+	let dmx = (memory.get_reg(params.dmx_reg) as i16) as i32; // sign extension from 16bits to 32 bits
+	let dmy = (memory.get_reg(params.dmy_reg) as i16) as i32; // sign extension from 16bits to 32 bits
 	memory.internal_regs.bg2x += dmx as u32;
 	memory.internal_regs.bg2y += dmy as u32;
 }
@@ -0,0 +1,176 @@
+//! Where the GBA's buttons come from.
+//!
+//! Every source of input gives the buttons it's holding down as a mask of
+//! `GBA_BTN_*` once per frame, and the masks from all of them are combined.
+//! The joypad only sees the result, which is latched at the start of each frame
+//! so that a frame always runs with the same buttons no matter where they came from.
+//!
+//! Turbo buttons and macros are done here too. They only depend on the frames that are
+//! emulated, so a movie that is recording gets exactly what the game saw.
+
+use std::collections::BTreeMap;
+use super::hw::joypad::*;
+
+#[cfg(test)]
+mod tests;
+
+pub trait InputSource {
+	/// Called once at the start of every frame that is emulated,
+	/// returns the buttons that are held down during it.
+	fn next_frame(&mut self) -> u16;
+}
+
+/// A key or gamepad input that can hold buttons down.
+/// The keyboard's keys and the gamepads' inputs are numbered separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum InputKey {
+	Keyboard(u32),
+
+	/// The controller and its input, the same input on two controllers is two keys.
+	Gamepad(usize, u32)
+}
+
+/// Buttons held down by a device that sends presses and releases, like a keyboard.
+/// The buttons are kept for each key that is down, so a key that repeats doesn't
+/// hold its buttons any longer and a button bound to two keys stays down until both are let go.
+pub struct HeldButtons {
+	held: BTreeMap<InputKey, u16>
+}
+
+impl HeldButtons {
+	pub fn new() -> HeldButtons {
+		HeldButtons { held: BTreeMap::new() }
+	}
+
+	pub fn press(&mut self, key: InputKey, mask: u16) {
+		*self.held.entry(key).or_insert(0) |= mask;
+	}
+
+	/// Lets go of every button that the key was holding.
+	pub fn release(&mut self, key: InputKey) {
+		self.held.remove(&key);
+	}
+
+	pub fn release_all(&mut self) {
+		self.held.clear();
+	}
+
+	pub fn buttons(&self) -> u16 {
+		self.held.values().fold(0, |buttons, &mask| buttons | mask)
+	}
+}
+
+impl InputSource for HeldButtons {
+	fn next_frame(&mut self) -> u16 {
+		self.buttons()
+	}
+}
+
+/// A recorded sequence of buttons, one mask per frame.
+struct MacroPlayback {
+	frames: Vec<u16>,
+	position: usize
+}
+
+impl InputSource for MacroPlayback {
+	fn next_frame(&mut self) -> u16 {
+		let buttons = self.frames.get(self.position).cloned().unwrap_or(0);
+		self.position += 1;
+		return buttons;
+	}
+}
+
+/// All of the sources of input. The keyboard and gamepads are always there,
+/// anything else (scripts, remote control) can be added.
+pub struct GbaInput {
+	pub keyboard: HeldButtons,
+	pub gamepad: HeldButtons,
+
+	/// Buttons that are pressed and released over and over while they're held.
+	pub turbo: HeldButtons,
+
+	/// Frames that turbo buttons stay pressed and then released for.
+	pub turbo_rate: u32,
+
+	sources: Vec<Box<InputSource>>,
+
+	/// The macro slot being recorded into and the frames recorded so far.
+	macro_recording: Option<(usize, Vec<u16>)>,
+	macro_playback: Option<MacroPlayback>,
+
+	/// Frames that have been emulated, turbo buttons are timed from this.
+	frame: u64
+}
+
+impl GbaInput {
+	pub fn new() -> GbaInput {
+		GbaInput {
+			keyboard: HeldButtons::new(),
+			gamepad: HeldButtons::new(),
+			turbo: HeldButtons::new(),
+			turbo_rate: 2,
+			sources: Vec::new(),
+			macro_recording: None,
+			macro_playback: None,
+			frame: 0
+		}
+	}
+
+	pub fn recording_macro(&self) -> Option<usize> {
+		self.macro_recording.as_ref().map(|&(slot, _)| slot)
+	}
+
+	/// Records the buttons of every frame from now on, anything that was being recorded is dropped.
+	pub fn start_macro_recording(&mut self, slot: usize) {
+		self.macro_recording = Some((slot, Vec::new()));
+	}
+
+	/// Returns the slot that was being recorded into and what was recorded.
+	pub fn finish_macro_recording(&mut self) -> Option<(usize, Vec<u16>)> {
+		self.macro_recording.take()
+	}
+
+	/// Plays the frames of a macro on top of the other input, starting with the next frame.
+	pub fn play_macro(&mut self, frames: Vec<u16>) {
+		self.macro_playback = Some(MacroPlayback { frames: frames, position: 0 });
+	}
+
+	pub fn add_source(&mut self, source: Box<InputSource>) {
+		self.sources.push(source);
+	}
+
+	pub fn clear_sources(&mut self) {
+		self.sources.clear();
+	}
+
+	/// The buttons that any source is holding down for the next frame.
+	pub fn next_frame(&mut self) -> u16 {
+		let mut buttons = self.keyboard.next_frame() | self.gamepad.next_frame();
+		for source in self.sources.iter_mut() {
+			buttons |= source.next_frame();
+		}
+
+		let rate = max!(self.turbo_rate, 1) as u64;
+		if (self.frame / rate) % 2 == 0 {
+			buttons |= self.turbo.next_frame();
+		}
+		self.frame += 1;
+
+		buttons &= GBA_BTN_A | GBA_BTN_B | GBA_BTN_SELECT | GBA_BTN_START | GBA_BTN_RIGHT |
+			GBA_BTN_LEFT | GBA_BTN_UP | GBA_BTN_DOWN | GBA_BTN_R | GBA_BTN_L;
+
+		// A macro that is playing isn't recorded again.
+		if let Some((_, ref mut frames)) = self.macro_recording {
+			frames.push(buttons);
+		}
+
+		let mut finished = false;
+		if let Some(ref mut playback) = self.macro_playback {
+			buttons |= playback.next_frame();
+			finished = playback.position >= playback.frames.len();
+		}
+		if finished { self.macro_playback = None; }
+
+		return buttons;
+	}
+}
@@ -0,0 +1,114 @@
+use super::super::core::cpu::ArmCpu;
+use super::super::core::memory::*;
+use super::super::hw::joypad::*;
+use super::{GbaInput, InputKey};
+
+#[test]
+fn devices_dont_release_each_others_buttons() {
+	let mut input = GbaInput::new();
+	input.keyboard.press(InputKey::Keyboard(1), GBA_BTN_A);
+	input.gamepad.press(InputKey::Gamepad(0, 1), GBA_BTN_A | GBA_BTN_B);
+	input.gamepad.release(InputKey::Gamepad(0, 1));
+	assert_eq!(input.next_frame(), GBA_BTN_A);
+
+	// Two keys bound to the same button.
+	input.keyboard.press(InputKey::Keyboard(2), GBA_BTN_A);
+	input.keyboard.release(InputKey::Keyboard(2));
+	assert_eq!(input.next_frame(), GBA_BTN_A);
+	input.keyboard.release(InputKey::Keyboard(1));
+	assert_eq!(input.next_frame(), 0);
+}
+
+#[test]
+fn gamepads_hold_their_own_buttons() {
+	let mut input = GbaInput::new();
+	input.gamepad.press(InputKey::Gamepad(0, 0), GBA_BTN_A);
+	input.gamepad.press(InputKey::Gamepad(1, 0), GBA_BTN_A);
+	input.gamepad.release(InputKey::Gamepad(1, 0));
+	assert_eq!(input.next_frame(), GBA_BTN_A);
+	input.gamepad.release(InputKey::Gamepad(0, 0));
+	assert_eq!(input.next_frame(), 0);
+}
+
+#[test]
+fn repeated_presses_dont_stick() {
+	let mut input = GbaInput::new();
+	for _ in 0..3 {
+		input.keyboard.press(InputKey::Keyboard(1), GBA_BTN_UP);
+	}
+	input.keyboard.press(InputKey::Keyboard(1), GBA_BTN_R);
+	assert_eq!(input.next_frame(), GBA_BTN_UP | GBA_BTN_R);
+	input.keyboard.release(InputKey::Keyboard(1));
+	assert_eq!(input.next_frame(), 0);
+
+	// Releasing a key that isn't down doesn't change anything.
+	input.keyboard.release(InputKey::Keyboard(1));
+	input.keyboard.press(InputKey::Keyboard(2), GBA_BTN_DOWN);
+	input.keyboard.release(InputKey::Keyboard(3));
+	assert_eq!(input.next_frame(), GBA_BTN_DOWN);
+}
+
+#[test]
+fn turbo_buttons_follow_the_rate() {
+	let mut input = GbaInput::new();
+	input.turbo_rate = 2;
+	input.turbo.press(InputKey::Keyboard(1), GBA_BTN_B);
+	let frames: Vec<u16> = (0..6).map(|_| input.next_frame()).collect();
+	assert_eq!(frames, vec![GBA_BTN_B, GBA_BTN_B, 0, 0, GBA_BTN_B, GBA_BTN_B]);
+}
+
+#[test]
+fn macros_play_back_what_was_recorded() {
+	let mut input = GbaInput::new();
+	input.start_macro_recording(1);
+	input.keyboard.press(InputKey::Keyboard(1), GBA_BTN_RIGHT);
+	input.next_frame();
+	input.keyboard.press(InputKey::Keyboard(2), GBA_BTN_A);
+	input.next_frame();
+	input.keyboard.release(InputKey::Keyboard(1));
+	input.keyboard.release(InputKey::Keyboard(2));
+	input.next_frame();
+
+	let (slot, frames) = input.finish_macro_recording().unwrap();
+	assert_eq!(slot, 1);
+	assert_eq!(frames, vec![GBA_BTN_RIGHT, GBA_BTN_RIGHT | GBA_BTN_A, 0]);
+	assert!(input.recording_macro().is_none());
+
+	// It's combined with whatever else is held.
+	input.play_macro(frames);
+	input.keyboard.press(InputKey::Keyboard(3), GBA_BTN_L);
+	let played: Vec<u16> = (0..4).map(|_| input.next_frame()).collect();
+	assert_eq!(played, vec![GBA_BTN_RIGHT | GBA_BTN_L, GBA_BTN_RIGHT | GBA_BTN_A | GBA_BTN_L, GBA_BTN_L, GBA_BTN_L]);
+}
+
+#[test]
+fn keypad_irq_or_mode_needs_any_selected_button() {
+	let mut cpu = ArmCpu::new();
+	let mut joypad = GbaJoypad::new();
+	cpu.memory.set_reg(ioreg::KEYCNT, 0x4000 | GBA_BTN_A | GBA_BTN_B);
+	assert!(!joypad.tick(&mut cpu), "Nothing is pressed.");
+
+	joypad.set_buttons(GBA_BTN_B);
+	assert!(joypad.tick(&mut cpu));
+	joypad.set_buttons(GBA_BTN_UP);
+	assert!(!joypad.tick(&mut cpu), "Only buttons that aren't selected are pressed.");
+
+	cpu.memory.set_reg(ioreg::KEYCNT, GBA_BTN_A | GBA_BTN_B);
+	joypad.set_buttons(GBA_BTN_A);
+	assert!(!joypad.tick(&mut cpu), "The IRQ is disabled.");
+}
+
+#[test]
+fn keypad_irq_and_mode_needs_every_selected_button() {
+	let mut cpu = ArmCpu::new();
+	let mut joypad = GbaJoypad::new();
+	cpu.memory.set_reg(ioreg::KEYCNT, 0xC000 | GBA_BTN_A | GBA_BTN_B);
+	assert!(!joypad.tick(&mut cpu), "Nothing is pressed.");
+
+	joypad.set_buttons(GBA_BTN_A);
+	assert!(!joypad.tick(&mut cpu));
+	joypad.set_buttons(GBA_BTN_A | GBA_BTN_B | GBA_BTN_UP);
+	assert!(joypad.tick(&mut cpu));
+	joypad.set_buttons(GBA_BTN_B);
+	assert!(!joypad.tick(&mut cpu));
+}
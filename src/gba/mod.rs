@@ -2,16 +2,27 @@ pub mod core;
 pub mod hw;
 pub mod device;
 pub mod serialization;
+pub mod movie;
+pub mod speed;
+pub mod cheats;
+pub mod input;
 use time;
 
-use std::thread;
-use std::time::Duration;
+use std::fs;
 use ::util::measure::*;
 use self::core::memory::*;
 use self::core::cpu::registers;
 use self::core::cpu::ArmCpu;
 use self::device::GbaDevice;
+use self::device::recorder::RECORDING_DIR;
+use self::serialization::BinarySerialization;
+use self::serialization::rewind::RewindBuffer;
+use self::movie::{Movie, MOVIE_DIR};
+use self::speed::{Speed, FrameLimiter, FrameSkip};
+use self::cheats::CheatEngine;
+use self::input::GbaInput;
 use self::hw::lcd::GbaLcd;
+use self::hw::lcd::GbaLcdScreenBuffer;
 use self::hw::joypad::GbaJoypad;
 use self::hw::dma;
 use self::hw::audio;
@@ -20,22 +31,14 @@ use self::hw::timers;
 use super::debug::debugger;
 // use super::debug::debugger::GbaDebugger;
 
-// #TODO remove this debug code.
-// I'm using vsync now and because my monitor's refresh rate is 60Hz
-// I don't limit the FPS, this is bad to leave false though :P
-const LIMIT_FPS: bool = false;
+/// The GBA's CPU runs at 2^24Hz.
+pub const CYCLES_PER_SECOND: u64 = 16777216;
 
-/// The number of CPU ticks per audio tick.
-/// The higher this value is, the better the audio will sound for some games
-/// that frequenty change the value in the sound registers (e.g. Fire Emblem).
-const AUDIO_TICK_RATE: u32 = 6144;
-
-/// delay for a 60fps frame in nanoseconds.
-const FPS_60_DELTA_NS: u64 = 16000000; // 16666667
-
-/// #TODO remove this debug code.
-/// true if the starting address should be 0 in SVC mode.
-const STARTUP_BIOS: bool = true;
+/// true if the starting address should be 0 in SVC mode, false to start at the ROM
+/// as if the BIOS had already run. Read from the settings when the GBA starts.
+pub fn startup_bios() -> bool {
+	!psetting!(skip_bios)
+}
 
 /// LCD V-Blank Interrupt
 pub const INT_VBLANK: u16 = 0x01;
@@ -72,6 +75,27 @@ pub const INT_GAMEPAK: u16 = 0x2000;
 pub struct GbaExtras {
 	pub paused: bool,
 	pub request_pause: bool,
+
+	/// Set to save a screenshot of the next frame that is rendered.
+	pub request_screenshot: bool,
+
+	/// Set to save or load a save state slot once the current frame is done.
+	pub request_save_slot: Option<usize>,
+	pub request_load_slot: Option<usize>,
+
+	/// Either shift key is held, used to tell the save slot hotkeys apart from the load ones.
+	pub shift_held: bool,
+
+	/// True while the rewind key is held.
+	pub rewinding: bool,
+
+	/// Fast-forwards while the key is held or until it's toggled off.
+	pub fast_forward_held: bool,
+	pub fast_forward_toggled: bool,
+	pub slow_motion: bool,
+
+	/// The file name of the loaded ROM without its extension.
+	pub rom_name: String,
 }
 
 impl GbaExtras {
@@ -79,6 +103,15 @@ impl GbaExtras {
 		GbaExtras {
 			paused: false,
 			request_pause: false,
+			request_screenshot: false,
+			request_save_slot: None,
+			request_load_slot: None,
+			shift_held: false,
+			rewinding: false,
+			fast_forward_held: false,
+			fast_forward_toggled: false,
+			slow_motion: false,
+			rom_name: String::new(),
 		}
 	}
 }
@@ -88,30 +121,92 @@ pub struct Gba {
 	pub lcd: GbaLcd,
 	pub device: GbaDevice,
 	pub joypad: GbaJoypad,
+	pub input: GbaInput,
 	pub request_exit: bool,
-	pub extras: GbaExtras
+	pub extras: GbaExtras,
+	pub rewind: RewindBuffer,
+	pub movie: Option<Movie>,
+	pub frame_skip: FrameSkip,
+	pub cheats: CheatEngine,
+
+	/// Set for frames that are emulated without being drawn.
+	skip_render: bool
 }
 
 impl Gba {
+	/// Creates a GBA with a window and audio output.
 	pub fn new() -> Gba {
-		Gba {
+		Gba::with_device(GbaDevice::new())
+	}
+
+	/// Creates a GBA that runs without a window, OpenGL or a sound device.
+	pub fn headless() -> Gba {
+		Gba::with_device(GbaDevice::headless())
+	}
+
+	pub fn with_device(mut device: GbaDevice) -> Gba {
+		// Rewinding and frame skipping are only for someone playing in a window.
+		let interactive = device.video.has_window();
+		let mut gba = Gba {
 			cpu: ArmCpu::new(),
 			lcd: GbaLcd::new(),
-			device: GbaDevice::new(),
+			device: device,
 			joypad: GbaJoypad::new(),
+			input: GbaInput::new(),
 			request_exit: false,
 			extras: GbaExtras::new(),
-		}
+			rewind: RewindBuffer::new(),
+			movie: None,
+			frame_skip: FrameSkip::new(),
+			cheats: CheatEngine::new(),
+			skip_render: false,
+		};
+		gba.rewind.available = interactive;
+		gba.frame_skip.available = interactive;
+		return gba;
 	}
 
 	pub fn load_cartridge(&mut self, data: Vec<u8>) {
-		self.cpu.memory.rom = data;
+		self.cpu.memory.set_rom(data);
+	}
+
+	/// The 4 character game code from the cartridge header, e.g. AGBJ.
+	/// Empty if there is no cartridge loaded.
+	pub fn game_code(&self) -> String {
+		self.cartridge_header_string(0xAC, 0xB0)
+	}
+
+	/// The up to 12 character game title from the cartridge header.
+	pub fn game_title(&self) -> String {
+		self.cartridge_header_string(0xA0, 0xAC)
+	}
+
+	fn cartridge_header_string(&self, start: usize, end: usize) -> String {
+		let rom = &self.cpu.memory.rom;
+		if rom.len() < end { return String::new() }
+		let bytes: Vec<u8> = rom[start..end].iter().cloned().take_while(|&b| b != 0).collect();
+		return String::from_utf8_lossy(&bytes).into_owned();
+	}
+
+	/// FNV-1a over the entire ROM, used to tell different dumps and revisions apart.
+	pub fn rom_hash(&self) -> u64 {
+		let mut hash: u64 = 0xcbf29ce484222325;
+		for &b in self.cpu.memory.rom.iter() {
+			hash ^= b as u64;
+			hash = hash.wrapping_mul(0x100000001b3);
+		}
+		return hash;
+	}
+
+	/// The last frame that was drawn by the LCD.
+	pub fn screen_buffer(&self) -> &GbaLcdScreenBuffer {
+		&self.lcd.screen_buffer
 	}
 
 	pub fn init(&mut self) {
 		self.cpu.registers.setf_f(); // The FIQ flag should always be high.
 
-		if STARTUP_BIOS {
+		if startup_bios() {
 			self.cpu.registers.setf_i(); // Disables IRQ interrupts.
 			self.cpu.registers.setf_f(); // Disables FIQ interrupts. (They are impossible on the GBA, but this is high by default.)
 			self.cpu.set_pc(0x00000000);
@@ -132,19 +227,16 @@ impl Gba {
 
 	pub fn run(&mut self) {
 		self.init();
+		let mut limiter = FrameLimiter::new();
 		'running: loop {
-			if LIMIT_FPS {
-				let start_time = time::precise_time_ns();
-				self.tick();
-				let delta = time::precise_time_ns() - start_time;
-				let sleep_time_millis = if delta > FPS_60_DELTA_NS { 0 } else { FPS_60_DELTA_NS - delta } / 1000000;
-				thread::sleep(Duration::from_millis(sleep_time_millis));
-			} else { // #TODO remove debug code.
-				self.tick();
-			}
+			let speed = Speed::current(&self.extras);
+			self.device.audio.stretch = speed.audio_stretch();
+			self.tick();
 			if self.request_exit { break 'running; }
+			limiter.wait(speed.frame_duration());
 		}
 		self.request_exit = false; // in case we don't actually close here.
+		self.stop_movie();
 
 		// It's important that we do this so that we don't leak things
 		// beyond our comprehension.
@@ -156,24 +248,48 @@ impl Gba {
 	pub fn tick(&mut self) {
 		profiler_begin!("Pyrite Frame");
 		let frame_start_time = time::precise_time_ns();
+
+		// Recordings need every frame, and so does whoever is recording a movie.
+		let frame_duration = Speed::current(&self.extras).frame_duration();
+		self.skip_render = !self.frame_skip.should_render(debugger::get_debugger().full_frame_time, frame_duration)
+			&& !self.device.recording() && !self.recording_movie();
+
 		if !self.extras.paused {
-			self.frame();
+			if self.extras.rewinding && !self.movie_active() {
+				self.rewind_frame();
+			} else {
+				self.frame();
+			}
 		} else {
 			self.poll_device_events();
 		}
 
 		// IMGUI:
 		profiler_begin!("ImGui");
-		self.device.video.prepare_imgui();
+		let has_gui = self.device.video.prepare_imgui();
 
 		let render_start_time = time::precise_time_ns();
-		debugger::render_debugger(self);
+		if has_gui {
+			debugger::render_debugger(self);
+		}
 		profiler_end!();
 		profiler_begin!("Render Call");
 		self.device.video.render(&self.lcd.screen_buffer);
 		profiler_end!();
 		let render_end_time = time::precise_time_ns();
 
+		if self.extras.request_screenshot {
+			self.extras.request_screenshot = false;
+			self.take_screenshot();
+		}
+
+		if let Some(slot) = self.extras.request_save_slot.take() {
+			self.save_slot(slot);
+		}
+		if let Some(slot) = self.extras.request_load_slot.take() {
+			self.load_slot(slot);
+		}
+
 		let mut debugger = debugger::get_debugger();
 		debugger.frame_build_time = (render_start_time - frame_start_time) as f64 / 1000000.0;
 		debugger.frame_render_time = (render_end_time - render_start_time) as f64 / 1000000.0;
@@ -189,8 +305,118 @@ impl Gba {
 		profiler_clear!();
 	}
 
+	/// Saves the last frame into the screenshot directory.
+	pub fn take_screenshot(&mut self) {
+		match ::pyrite::screenshot::save_screenshot(&self.lcd.screen_buffer, &self.extras.rom_name) {
+			Ok(path) => console_log!("Saved screenshot to {}", path),
+			Err(e) => console_error!("{}", e)
+		}
+	}
+
+	/// Saves a state into one of the numbered slots for the loaded ROM.
+	pub fn save_slot(&mut self, slot: usize) {
+		let path = ::pyrite::save_slots::slot_path(&self.extras.rom_name, slot);
+		match self.save_to_file(&path.to_string_lossy()) {
+			Ok(_) => console_log!("Saved state to slot {}.", slot),
+			Err(e) => console_error!("Failed to save state to slot {}: {}", slot, e)
+		}
+		debugger::get_debugger().save_slots_window.dirty = true;
+	}
+
+	/// Loads the cheats saved for the current game.
+	pub fn load_cheats(&mut self) {
+		let game_code = self.game_code();
+		if let Err(e) = self.cheats.load(&game_code, &self.extras.rom_name) {
+			console_error!("{}", e);
+		}
+	}
+
+	/// Loads the state in one of the numbered slots for the loaded ROM.
+	pub fn load_slot(&mut self, slot: usize) {
+		if self.movie_active() {
+			console_warn!("Can't load a state while a movie is recording or playing.");
+			return
+		}
+		let path = ::pyrite::save_slots::slot_path(&self.extras.rom_name, slot);
+		if !path.exists() {
+			console_warn!("Slot {} is empty.", slot);
+			return
+		}
+		match self.load_from_file(&path.to_string_lossy()) {
+			Ok(_) => console_log!("Loaded state from slot {}.", slot),
+			Err(e) => console_error!("Failed to load state from slot {}: {}", slot, e)
+		}
+	}
+
+	/// Starts recording a movie from the current state into the movie directory.
+	pub fn start_movie(&mut self) {
+		if let Err(e) = fs::create_dir_all(MOVIE_DIR) {
+			console_error!("Failed to create the movie directory {}: {}", MOVIE_DIR, e);
+			return
+		}
+		let path = ::pyrite::output_path(MOVIE_DIR, &self.extras.rom_name, "pmv");
+		if let Err(e) = self.start_movie_recording(&path.to_string_lossy(), false) {
+			console_error!("{}", e);
+		}
+	}
+
+	/// Starts recording into the recording directory.
+	pub fn start_recording(&mut self) {
+		if let Err(e) = fs::create_dir_all(RECORDING_DIR) {
+			console_error!("Failed to create the recording directory {}: {}", RECORDING_DIR, e);
+			return
+		}
+		let path = ::pyrite::output_path(RECORDING_DIR, &self.extras.rom_name, "y4m");
+		if let Err(e) = self.device.start_recording(&path.to_string_lossy()) {
+			console_error!("{}", e);
+		}
+	}
+
+	/// Goes back to the previous rewind snapshot when it's time to. No frame is emulated
+	/// so no audio is generated and the sound stays muted while rewinding.
+	fn rewind_frame(&mut self) {
+		self.poll_device_events();
+		if !self.rewind.should_step_back() { return }
+		let snapshot = match self.rewind.pop() {
+			Some(s) => s.to_vec(),
+			None => return
+		};
+		if let Err(e) = self.load_rewind_snapshot(&snapshot) {
+			console_error!("Failed to rewind: {}", e);
+			self.rewind.clear();
+		}
+	}
+
+	/// Takes a rewind snapshot every few frames, as configured in the settings.
+	fn record_rewind_snapshot(&mut self) {
+		if !self.rewind.available || !psetting!(rewind_enabled) {
+			if self.rewind.memory_used() > 0 { self.rewind.clear(); }
+			return
+		}
+		self.rewind.configure(psetting!(rewind_buffer_size) as usize * 1024 * 1024, psetting!(rewind_interval));
+		if !self.rewind.should_snapshot() { return }
+		match self.rewind_snapshot() {
+			Ok(snapshot) => self.rewind.push(snapshot),
+			Err(e) => console_error!("Failed to take a rewind snapshot: {}", e)
+		}
+	}
+
 	fn frame(&mut self) {
 		profiler_begin!("GBA Frame");
+		// The buttons only change at the start of a frame, a movie that is playing replaces them.
+		self.input.turbo_rate = psetting!(turbo_rate);
+		let buttons = self.input.next_frame();
+		self.joypad.set_buttons(buttons);
+		if self.movie_active() {
+			self.movie_frame();
+			self.cpu.memory.clear_cheats();
+		} else {
+			self.cheats.apply(&mut self.cpu.memory);
+		}
+		if self.joypad.tick(&mut self.cpu) {
+			self.hardware_interrupt(INT_KEYPAD);
+		}
+
 		// Clears the VBlank flag.
 		{
 			let mut dispstat = self.cpu.memory.get_reg(ioreg::DISPSTAT);
@@ -242,6 +468,12 @@ impl Gba {
 			&self.cpu.memory.internal_data[MEM_IOREG.local_addr..(MEM_IOREG.local_addr+MEM_IOREG.size)]
 		);
 
+		if self.device.recording() {
+			self.device.record_frame(&self.lcd.screen_buffer);
+		}
+
+		self.record_rewind_snapshot();
+
 		// pyrite_debugging!({
 		// 	use std::sync::atomic::Ordering;
 		// 	let _write_misses = self.device.audio.ring_buffer._stat_write_misses.load(Ordering::Relaxed);
@@ -335,9 +567,13 @@ impl Gba {
 		self.do_hdraw();
 		profiler_end_id!("running-cpu");
 
-		profiler_begin_id!("rendering-line");
-		self.lcd.render_line(&mut self.cpu.memory, line);
-		profiler_end_id!("rendering-line");
+		if !self.skip_render {
+			profiler_begin_id!("rendering-line");
+			self.lcd.render_line(&mut self.cpu.memory, line);
+			profiler_end_id!("rendering-line");
+		} else {
+			self.lcd.skip_line(&mut self.cpu.memory);
+		}
 
 		profiler_begin_id!("running-cpu");
 		self.do_hblank();
@@ -401,16 +637,16 @@ Display status and Interrupt control. The H-Blank conditions are generated once
 					measure_iteration(MEASURE_CPU_TICKS_TIME);
 					self.cpu.tick();
 					self.increment_timers();
-					if self.cpu.clock.audio_clock > AUDIO_TICK_RATE {
-						audio::tick(&mut self.cpu, &mut self.device.audio);
-						self.cpu.clock.audio_clock = 0;
-					}
 					self.check_dmas(dma::DMA_TIMING_IMMEDIATE);
 				} else {
 					self.cpu.reg_dump_pretty();
 					panic!("Attempting to execute at unexecutable address 0x{:08x}!", self.cpu.get_exec_address());
 				}
 			}
+
+			// Samples are generated as soon as they are due so that
+			// sound register writes and FIFO refills land on the right sample.
+			audio::tick(&mut self.cpu, &mut self.device.audio);
 		}
 
 		measure_end(MEASURE_CPU_TICKS_TIME);
@@ -0,0 +1,325 @@
+//! Input movies, the buttons held during every frame so that a run can be played back exactly.
+//!
+//! A movie starts either from power-on or from a save state that is embedded in it.
+//! It is stored like a save state: a deflate stream with a header, a format version and
+//! a list of chunks (see `serialization/chunk.rs`) holding the game the movie was made with,
+//! the settings that change how the game runs, the embedded state and the inputs.
+//!
+//! Playback is only exact if the emulator is deterministic. As of now:
+//! - Buttons are always latched into KEYINPUT at the start of a frame. While a movie is playing
+//!   its buttons replace the ones from the keyboard and gamepads.
+//! - There is no RTC emulation so nothing reads the host's clock.
+//! - The audio thread only ever reads the ring buffer, it never changes the emulation.
+//!   The sound settings only change what is mixed into the output.
+//! - Loading a state or rewinding would break the recording, so they are refused while a movie is active.
+//! - The BIOS and whether the machine boots through it change the whole run, they are stored as
+//!   the emulation settings.
+
+#[cfg(test)]
+mod tests;
+
+use std::io::prelude::*;
+use std::fs::{File, create_dir_all};
+use std::path::Path;
+use bincode::rustc_serialize::{encode, decode};
+use bincode::SizeLimit;
+use rustc_serialize::{Encodable, Decodable};
+use flate2::Compression;
+use flate2::write::DeflateEncoder;
+use flate2::read::DeflateDecoder;
+use super::Gba;
+use super::startup_bios;
+use super::core::memory::MEM_BIOS;
+use super::serialization::{BinarySerialization, StateInfo};
+use super::serialization::chunk::{self, Chunk, ChunkKind};
+
+/// Where movies recorded from the debugger go.
+pub const MOVIE_DIR: &'static str = "movies";
+
+/// It just says pyritemv.
+const MOVIE_HEADER: [u8; 8] = [0x70, 0x79, 0x72, 0x69, 0x74, 0x65, 0x6d, 0x76];
+const MOVIE_VERSION: u8 = 1;
+
+const INFO: ChunkKind = ChunkKind { tag: [b'I', b'N', b'F', b'O'], version: 1, required: true };
+const SETTINGS: ChunkKind = ChunkKind { tag: [b'E', b'M', b'U', b'S'], version: 1, required: true };
+const START_STATE: ChunkKind = ChunkKind { tag: [b'S', b'T', b'A', b'T'], version: 1, required: false };
+const INPUT: ChunkKind = ChunkKind { tag: [b'I', b'N', b'P', b'T'], version: 1, required: true };
+
+/// Everything outside of the game that changes how it runs.
+#[derive(RustcEncodable, RustcDecodable, Debug, Clone, PartialEq)]
+pub struct EmulationSettings {
+	/// True if the machine starts in the BIOS instead of jumping straight to the ROM.
+	pub startup_bios: bool,
+
+	/// FNV-1a of the BIOS.
+	pub bios_hash: u64
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MovieMode {
+	Recording,
+	Playing
+}
+
+pub struct Movie {
+	pub mode: MovieMode,
+
+	/// Where a recording is written when it's stopped.
+	pub path: String,
+
+	info: StateInfo,
+	settings: EmulationSettings,
+
+	/// A save state to load before the first frame, the movie starts from power-on without one.
+	start_state: Option<Vec<u8>>,
+	started: bool,
+
+	/// The buttons held during each frame, a set bit is a pressed button.
+	inputs: Vec<u16>,
+
+	/// The next frame to record or play.
+	pub frame: usize
+}
+
+impl Movie {
+	/// Starts recording a movie of the given machine. If `from_power_on` is false,
+	/// the machine's current state is embedded so the movie can start from there.
+	pub fn record(gba: &Gba, path: &str, from_power_on: bool) -> Result<Movie, String> {
+		let start_state = if from_power_on {
+			if gba.cpu.clock.cycles != 0 {
+				return Err("A movie can only be recorded from power-on before the first frame.".to_string());
+			}
+			None
+		} else {
+			let mut state = Vec::new();
+			try!(gba.serialize(&mut state).map_err(|e| format!("Failed to save the movie's start state: {}", e)));
+			Some(state)
+		};
+
+		Ok(Movie {
+			mode: MovieMode::Recording,
+			path: path.to_string(),
+			info: gba.state_info(),
+			settings: gba.emulation_settings(),
+			start_state: start_state,
+			started: false,
+			inputs: Vec::new(),
+			frame: 0
+		})
+	}
+
+	pub fn load(path: &str) -> Result<Movie, String> {
+		let mut f = try!(File::open(path).map_err(|e| format!("Failed to open movie {}: {}", path, e)));
+		let mut movie = try!(Movie::read_from(&mut f).map_err(|e| format!("Failed to read movie {}: {}", path, e)));
+		movie.path = path.to_string();
+		return Ok(movie);
+	}
+
+	pub fn read_from(r_unwrapped: &mut Read) -> Result<Movie, String> {
+		let mut r = DeflateDecoder::new(r_unwrapped);
+		let mut header = [0u8; 8];
+		if r.read_exact(&mut header).is_err() || header != MOVIE_HEADER {
+			return Err("Not a Pyrite movie.".to_string());
+		}
+		let mut version = [0u8; 1];
+		try!(r.read_exact(&mut version).map_err(|e| format!("{}", e)));
+		if version[0] != MOVIE_VERSION {
+			return Err(format!("Movie format version {} is not supported (current version is {}).", version[0], MOVIE_VERSION));
+		}
+		let chunks = try!(chunk::read_chunks(&mut r).map_err(|e| format!("{}", e)));
+
+		for kind in [&INFO, &SETTINGS, &START_STATE, &INPUT].iter() {
+			match chunks.iter().find(|c| c.tag == kind.tag) {
+				Some(c) if c.version > kind.version => {
+					return Err(format!("The {} section is version {} and can't be loaded (current version is {}).",
+						c.name(), c.version, kind.version));
+				},
+				None if kind.required => return Err(format!("The movie has no {} section.", chunk::tag_name(&kind.tag))),
+				_ => {}
+			}
+		}
+
+		let mut info: Option<StateInfo> = None;
+		let mut settings: Option<EmulationSettings> = None;
+		let mut start_state: Option<Vec<u8>> = None;
+		let mut inputs: Vec<u16> = Vec::new();
+		for c in chunks.into_iter() {
+			if c.tag == INFO.tag { info = Some(try!(decode_chunk(&c))) }
+			else if c.tag == SETTINGS.tag { settings = Some(try!(decode_chunk(&c))) }
+			else if c.tag == START_STATE.tag { start_state = Some(c.data) }
+			else if c.tag == INPUT.tag {
+				inputs = c.data.chunks(2).filter(|b| b.len() == 2).map(|b| (b[0] as u16) | ((b[1] as u16) << 8)).collect();
+			}
+		}
+
+		Ok(Movie {
+			mode: MovieMode::Playing,
+			path: String::new(),
+			info: info.unwrap(),
+			settings: settings.unwrap(),
+			start_state: start_state,
+			started: false,
+			inputs: inputs,
+			frame: 0
+		})
+	}
+
+	pub fn save(&self) -> Result<(), String> {
+		if let Some(parent) = Path::new(&self.path).parent() {
+			if parent != Path::new("") {
+				try!(create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e)));
+			}
+		}
+		let mut f = try!(File::create(&self.path).map_err(|e| format!("Failed to create movie {}: {}", self.path, e)));
+		return self.write_to(&mut f).map_err(|e| format!("Failed to write movie {}: {}", self.path, e));
+	}
+
+	pub fn write_to(&self, w_unwrapped: &mut Write) -> Result<(), String> {
+		let mut chunks = vec![
+			Chunk::new(&INFO, try!(encode_chunk(&self.info))),
+			Chunk::new(&SETTINGS, try!(encode_chunk(&self.settings)))
+		];
+		if let Some(ref state) = self.start_state {
+			chunks.push(Chunk::new(&START_STATE, state.clone()));
+		}
+		let mut input = Vec::with_capacity(self.inputs.len() * 2);
+		for &buttons in self.inputs.iter() {
+			input.push(buttons as u8);
+			input.push((buttons >> 8) as u8);
+		}
+		chunks.push(Chunk::new(&INPUT, input));
+
+		let mut w = DeflateEncoder::new(w_unwrapped, Compression::Default);
+		try!(w.write_all(&MOVIE_HEADER).map_err(|e| format!("{}", e)));
+		try!(w.write_all(&[MOVIE_VERSION]).map_err(|e| format!("{}", e)));
+		try!(chunk::write_chunks(&mut w, &chunks).map_err(|e| format!("{}", e)));
+		try!(w.finish().map_err(|e| format!("{}", e)));
+		return Ok(())
+	}
+
+	/// The number of frames recorded so far or in the movie that is playing.
+	pub fn len(&self) -> usize {
+		self.inputs.len()
+	}
+
+	pub fn finished(&self) -> bool {
+		self.mode == MovieMode::Playing && self.frame >= self.inputs.len()
+	}
+
+	/// Checks that the movie can be played back exactly on the given machine.
+	fn check(&self, gba: &Gba) -> Result<(), String> {
+		if self.info.rom_hash != gba.rom_hash() {
+			return Err(format!("The movie was made with {} ({}) and another ROM is loaded.",
+				self.info.game_title, self.info.game_code));
+		}
+		let settings = gba.emulation_settings();
+		if self.settings.startup_bios != settings.startup_bios {
+			return Err("The movie was made with a different startup mode.".to_string());
+		}
+		if self.settings.bios_hash != settings.bios_hash {
+			console_warn!("The movie was made with a different BIOS, it might not play back correctly.");
+		}
+		if self.start_state.is_none() && gba.cpu.clock.cycles != 0 {
+			return Err("This movie starts from power-on, play it by passing --movie when starting Pyrite.".to_string());
+		}
+		return Ok(())
+	}
+}
+
+impl Gba {
+	pub fn emulation_settings(&self) -> EmulationSettings {
+		let mut hash: u64 = 0xcbf29ce484222325;
+		for &b in self.cpu.memory.internal_data[0..MEM_BIOS.size].iter() {
+			hash ^= b as u64;
+			hash = hash.wrapping_mul(0x100000001b3);
+		}
+		EmulationSettings {
+			startup_bios: startup_bios(),
+			bios_hash: hash
+		}
+	}
+
+	pub fn movie_active(&self) -> bool {
+		self.movie.is_some()
+	}
+
+	pub fn recording_movie(&self) -> bool {
+		self.movie.as_ref().map_or(false, |m| m.mode == MovieMode::Recording)
+	}
+
+	/// Starts recording a movie to `path`, see `Movie::record`.
+	pub fn start_movie_recording(&mut self, path: &str, from_power_on: bool) -> Result<(), String> {
+		self.stop_movie();
+		let movie = try!(Movie::record(self, path, from_power_on));
+		console_log!("Recording movie to {}", path);
+		self.movie = Some(movie);
+		return Ok(())
+	}
+
+	pub fn play_movie(&mut self, movie: Movie) -> Result<(), String> {
+		self.stop_movie();
+		try!(movie.check(self));
+		console_log!("Playing movie {} ({} frames)", movie.path, movie.len());
+		self.movie = Some(movie);
+		return Ok(())
+	}
+
+	/// Stops the movie, a recording is written to its file.
+	pub fn stop_movie(&mut self) {
+		if let Some(movie) = self.movie.take() {
+			match movie.mode {
+				MovieMode::Recording => match movie.save() {
+					Ok(_) => console_log!("Recorded {} frames to {}", movie.len(), movie.path),
+					Err(e) => console_error!("{}", e)
+				},
+				MovieMode::Playing => console_log!("Stopped movie at frame {} of {}", movie.frame, movie.len())
+			}
+		}
+	}
+
+	/// Called at the start of every frame. Loads the movie's start state on its first frame,
+	/// then records the buttons that are held or replaces them with the movie's.
+	pub fn movie_frame(&mut self) {
+		let mut movie = match self.movie.take() {
+			Some(m) => m,
+			None => return
+		};
+
+		if !movie.started {
+			movie.started = true;
+			let loaded = match movie.start_state {
+				Some(ref state) => self.deserialize(&mut &state[..]),
+				None => Ok(())
+			};
+			if let Err(e) = loaded {
+				console_error!("Failed to load the movie's start state: {}", e);
+				self.movie = Some(movie);
+				self.stop_movie();
+				return
+			}
+		}
+
+		match movie.mode {
+			MovieMode::Recording => {
+				movie.inputs.push(self.joypad.pressed_buttons());
+			},
+			MovieMode::Playing => {
+				if movie.finished() {
+					console_log!("Movie {} finished after {} frames.", movie.path, movie.len());
+					return
+				}
+				self.joypad.set_buttons(movie.inputs[movie.frame]);
+			}
+		}
+		movie.frame += 1;
+		self.movie = Some(movie);
+	}
+}
+
+fn encode_chunk<T: Encodable>(value: &T) -> Result<Vec<u8>, String> {
+	encode(value, SizeLimit::Infinite).map_err(|e| format!("{}", e))
+}
+
+fn decode_chunk<T: Decodable>(c: &Chunk) -> Result<T, String> {
+	decode(&c.data).map_err(|e| format!("The {} section is corrupted: {}", c.name(), e))
+}
@@ -0,0 +1,106 @@
+use super::super::Gba;
+use super::super::core::memory::ioreg;
+use super::super::input::InputSource;
+use super::{Movie, MovieMode};
+
+fn new_gba() -> Box<Gba> {
+	let mut rom = vec![0u8; 0x200];
+	for (idx, b) in "PYRE".bytes().enumerate() { rom[0xAC + idx] = b; }
+	let mut gba = Box::new(Gba::headless());
+	gba.load_cartridge(rom);
+	gba.init();
+	return gba;
+}
+
+/// Different buttons for every frame.
+fn buttons_for_frame(frame: usize) -> u16 {
+	((frame as u16) * 37) & 0x3ff
+}
+
+/// Presses `buttons_for_frame` starting from frame 0.
+struct ChangingButtons(usize);
+
+impl InputSource for ChangingButtons {
+	fn next_frame(&mut self) -> u16 {
+		self.0 += 1;
+		buttons_for_frame(self.0 - 1)
+	}
+}
+
+fn assert_same_machine(a: &Gba, b: &Gba) {
+	for r in 0..16 {
+		assert_eq!(a.cpu.registers.get(r), b.cpu.registers.get(r));
+	}
+	assert_eq!(a.cpu.clock.cycles, b.cpu.clock.cycles);
+	assert_eq!(a.cpu.memory.get_reg(ioreg::KEYINPUT), b.cpu.memory.get_reg(ioreg::KEYINPUT));
+	assert!(&a.cpu.memory.internal_data[..] == &b.cpu.memory.internal_data[..], "Memory differs");
+}
+
+#[test]
+fn plays_back_a_recording_exactly() {
+	let mut recorded = new_gba();
+	for _ in 0..2 { recorded.tick(); }
+	recorded.start_movie_recording("unused.pmv", false).unwrap();
+	recorded.input.add_source(Box::new(ChangingButtons(0)));
+	for frame in 0..5 {
+		recorded.tick();
+		assert_eq!(recorded.cpu.memory.get_reg(ioreg::KEYINPUT), !buttons_for_frame(frame) & 0x3ff);
+	}
+	let movie = recorded.movie.take().unwrap();
+	assert_eq!(movie.len(), 5);
+
+	let mut data = Vec::new();
+	movie.write_to(&mut data).unwrap();
+	let loaded = Movie::read_from(&mut &data[..]).unwrap();
+	assert_eq!(loaded.mode, MovieMode::Playing);
+	assert_eq!(loaded.len(), 5);
+
+	// Starts somewhere else entirely, the embedded state is loaded on the first frame.
+	let mut played = new_gba();
+	played.play_movie(loaded).unwrap();
+	for _ in 0..5 { played.tick(); }
+	assert!(played.movie.as_ref().unwrap().finished());
+	assert_same_machine(&recorded, &played);
+
+	// The movie ends after its last frame.
+	played.tick();
+	assert!(!played.movie_active());
+}
+
+#[test]
+fn power_on_movies_need_a_fresh_machine() {
+	let mut gba = new_gba();
+	gba.tick();
+	assert!(gba.start_movie_recording("unused.pmv", true).is_err());
+
+	let fresh = new_gba();
+	let movie = Movie::record(&fresh, "unused.pmv", true).unwrap();
+	assert!(gba.play_movie(movie).is_err());
+}
+
+#[test]
+fn rejects_other_roms() {
+	let gba = new_gba();
+	let movie = Movie::record(&gba, "unused.pmv", true).unwrap();
+
+	let mut other = Box::new(Gba::headless());
+	other.load_cartridge(vec![1u8; 0x200]);
+	assert!(other.play_movie(movie).is_err());
+	assert!(!other.movie_active());
+}
+
+#[test]
+fn bad_start_states_stop_the_movie() {
+	let mut recorded = new_gba();
+	recorded.start_movie_recording("unused.pmv", false).unwrap();
+	for _ in 0..3 { recorded.tick(); }
+	let mut data = Vec::new();
+	recorded.movie.take().unwrap().write_to(&mut data).unwrap();
+	let mut movie = Movie::read_from(&mut &data[..]).unwrap();
+	movie.start_state = Some(b"not a save state".to_vec());
+
+	let mut played = new_gba();
+	played.play_movie(movie).unwrap();
+	played.tick();
+	assert!(!played.movie_active());
+}
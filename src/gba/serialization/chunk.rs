@@ -0,0 +1,101 @@
+//! Save states are a list of tagged chunks so that sections can be
+//! versioned, migrated, added and skipped independently of each other.
+//!
+//! ```text
+//! tag      [u8; 4]
+//! version  u16 (little endian)
+//! length   u32 (little endian)
+//! data     [u8; length]
+//! ```
+//!
+//! The list ends with an `END ` chunk that has no data.
+
+use std::io;
+use std::io::prelude::*;
+use super::SaveStateError;
+
+pub type ChunkTag = [u8; 4];
+
+/// A section of the machine state and the version of its layout.
+pub struct ChunkKind {
+	pub tag: ChunkTag,
+
+	/// The version that is written by this build.
+	pub version: u16,
+
+	/// If this is false, states without this chunk can still be loaded
+	/// and whatever it holds is left as it was.
+	pub required: bool
+}
+
+pub const CHUNK_END: ChunkTag = [b'E', b'N', b'D', b' '];
+
+/// The largest chunk that will be read, anything bigger is treated as a corrupted length.
+const MAX_CHUNK_SIZE: u32 = 16 * 1024 * 1024;
+
+pub struct Chunk {
+	pub tag: ChunkTag,
+	pub version: u16,
+	pub data: Vec<u8>
+}
+
+impl Chunk {
+	pub fn new(kind: &ChunkKind, data: Vec<u8>) -> Chunk {
+		Chunk {
+			tag: kind.tag,
+			version: kind.version,
+			data: data
+		}
+	}
+
+	pub fn name(&self) -> String {
+		tag_name(&self.tag)
+	}
+}
+
+pub fn tag_name(tag: &ChunkTag) -> String {
+	String::from_utf8_lossy(tag).trim_right().to_string()
+}
+
+pub fn write_chunks(w: &mut Write, chunks: &[Chunk]) -> io::Result<()> {
+	for chunk in chunks.iter() {
+		try!(write_chunk_header(w, &chunk.tag, chunk.version, chunk.data.len() as u32));
+		try!(w.write_all(&chunk.data));
+	}
+	return write_chunk_header(w, &CHUNK_END, 0, 0);
+}
+
+fn write_chunk_header(w: &mut Write, tag: &ChunkTag, version: u16, length: u32) -> io::Result<()> {
+	let header = [
+		tag[0], tag[1], tag[2], tag[3],
+		version as u8, (version >> 8) as u8,
+		length as u8, (length >> 8) as u8, (length >> 16) as u8, (length >> 24) as u8
+	];
+	w.write_all(&header)
+}
+
+/// Reads chunks until the end chunk.
+pub fn read_chunks(r: &mut Read) -> Result<Vec<Chunk>, SaveStateError> {
+	let mut chunks = Vec::new();
+	loop {
+		let mut header = [0u8; 10];
+		try!(r.read_exact(&mut header));
+		let tag = [header[0], header[1], header[2], header[3]];
+		let version = (header[4] as u16) | ((header[5] as u16) << 8);
+		let length = (header[6] as u32) | ((header[7] as u32) << 8) | ((header[8] as u32) << 16) | ((header[9] as u32) << 24);
+
+		if tag == CHUNK_END { break }
+
+		if length > MAX_CHUNK_SIZE {
+			return Err(SaveStateError::MalformedChunk {
+				chunk: tag_name(&tag),
+				reason: format!("{} bytes is too large for a chunk", length)
+			});
+		}
+
+		let mut data = vec![0u8; length as usize];
+		try!(r.read_exact(&mut data));
+		chunks.push(Chunk { tag: tag, version: version, data: data });
+	}
+	return Ok(chunks);
+}
@@ -0,0 +1,154 @@
+//! Brings chunks from older save states up to the versions written by this build.
+//!
+//! When the layout of a chunk changes, bump its version in `mod.rs` and add a
+//! migration here that rewrites the data of the previous version into the new one,
+//! usually by decoding a copy of the old struct and filling in the new fields:
+//!
+//! ```
+//! Migration { tag: CPU_CLOCK.tag, from: 1, migrate: clock_v1_to_v2 },
+//! ```
+//!
+//! Migrations are chained, so a version 1 chunk goes through 1 -> 2 -> 3 and so on.
+
+use std::io::prelude::*;
+use bincode::rustc_serialize::{encode, decode_from};
+use bincode::SizeLimit;
+use rustc_serialize::{Encodable, Decodable};
+use super::SaveStateError;
+use super::chunk::{Chunk, ChunkKind, ChunkTag, tag_name};
+use super::{CPU, CPU_CLOCK, JOYPAD, INTERNAL_REGS, RAM, RAM_SIZE};
+use super::super::core::cpu::registers::ArmRegisters;
+use super::super::core::cpu::clock::ArmCpuClock;
+use super::super::core::memory::ioreg::{DMAInternalReg, TimerInternalReg};
+use super::super::core::memory::ioreg::{GbaChannel1, GbaChannel2, GbaChannel3, GbaChannel4};
+use super::super::hw::joypad::GbaJoypad;
+
+pub struct Migration {
+	pub tag: ChunkTag,
+
+	/// The version that this migration upgrades from, the result is `from + 1`.
+	pub from: u16,
+
+	pub migrate: fn(&[u8]) -> Result<Vec<u8>, String>
+}
+
+const MIGRATIONS: &'static [Migration] = &[];
+
+/// Upgrades the chunk to the current version of its kind.
+pub fn upgrade(chunk: &mut Chunk, kind: &ChunkKind) -> Result<(), SaveStateError> {
+	if chunk.version > kind.version {
+		return Err(SaveStateError::UnsupportedChunkVersion {
+			chunk: chunk.name(),
+			version: chunk.version,
+			supported: kind.version
+		});
+	}
+
+	while chunk.version < kind.version {
+		let migration = match MIGRATIONS.iter().find(|m| m.tag == chunk.tag && m.from == chunk.version) {
+			Some(m) => m,
+			None => return Err(SaveStateError::UnsupportedChunkVersion {
+				chunk: chunk.name(),
+				version: chunk.version,
+				supported: kind.version
+			})
+		};
+
+		let data = try!((migration.migrate)(&chunk.data).map_err(|reason| SaveStateError::MalformedChunk {
+			chunk: chunk.name(),
+			reason: format!("migration from version {} failed: {}", chunk.version, reason)
+		}));
+		chunk.data = data;
+		chunk.version += 1;
+	}
+	return Ok(())
+}
+
+/// Format version 2 wrote every section back to back without tags or lengths.
+/// Its sections have the same layout as version 1 of the matching chunks, except for the
+/// DMA sound FIFOs in the internal IO registers (see `FifoV2`), so they are split into
+/// version 1 chunks and migrated like any other state.
+/// If one of these structs changes, decode it here with a copy of its old layout.
+pub fn chunks_from_v2(r: &mut Read) -> Result<Vec<Chunk>, SaveStateError> {
+	let mut cpu_data = [0u8; 2];
+	try!(r.read_exact(&mut cpu_data));
+	let branched = cpu_data[0] != 0;
+	let prefetch_wait = cpu_data[1];
+
+	let registers: ArmRegisters = try!(decode_v2(r, &CPU));
+	let clock: ArmCpuClock = try!(decode_v2(r, &CPU_CLOCK));
+	let joypad: GbaJoypad = try!(decode_v2(r, &JOYPAD));
+	let internal_regs = try!(internal_regs_from_v2(r));
+
+	let mut ram = vec![0u8; RAM_SIZE];
+	try!(r.read_exact(&mut ram));
+
+	Ok(vec![
+		Chunk { tag: CPU.tag, version: 1, data: try!(encode_v1(&(branched, prefetch_wait, registers))) },
+		Chunk { tag: CPU_CLOCK.tag, version: 1, data: try!(encode_v1(&clock)) },
+		Chunk { tag: JOYPAD.tag, version: 1, data: try!(encode_v1(&joypad)) },
+		Chunk { tag: INTERNAL_REGS.tag, version: 1, data: internal_regs },
+		Chunk { tag: RAM.tag, version: 1, data: ram }
+	])
+}
+
+/// A DMA sound FIFO as version 2 saved it. It buffered the samples it played until the
+/// audio thread took them, the buffer itself was never saved but its cursors were.
+#[derive(RustcEncodable, RustcDecodable, Default)]
+pub struct FifoV2 {
+	pub timer: u16,
+	pub enable_right: bool,
+	pub enable_left: bool,
+	pub sample: i8,
+	pub frequency: f32,
+	pub freq_acc: f32,
+	pub freq_inc: f32,
+	pub out_data: (),
+	pub out_write_cursor: usize,
+	pub out_read_cursor: usize,
+	pub out_size: usize,
+	pub data: [i8; 32],
+	pub write_cursor: usize,
+	pub read_cursor: usize,
+	pub size: usize
+}
+
+impl FifoV2 {
+	/// Encodes the FIFO the way `GbaChannelFIFO` is encoded now. The samples that were
+	/// waiting for the audio thread are dropped, nothing has been played since the last one.
+	fn encode_v1(&self) -> Result<Vec<u8>, SaveStateError> {
+		let (played_sum, played_count) = (0i32, 0i32);
+		encode_v1(&(self.timer, self.enable_right, self.enable_left, self.sample, played_sum, played_count,
+			self.frequency, self.data, self.write_cursor, self.read_cursor, self.size))
+	}
+}
+
+/// Rewrites the internal IO registers from version 2 into version 1 of `INTERNAL_REGS`.
+/// Every field before the FIFOs is unchanged, so those are copied over as they are.
+fn internal_regs_from_v2(r: &mut Read) -> Result<Vec<u8>, SaveStateError> {
+	let affine_and_state: (u32, u32, u32, u32, bool, bool, bool) = try!(decode_v2(r, &INTERNAL_REGS));
+	let dma_registers: [DMAInternalReg; 4] = try!(decode_v2(r, &INTERNAL_REGS));
+	let timers: [TimerInternalReg; 4] = try!(decode_v2(r, &INTERNAL_REGS));
+	let channels: (GbaChannel1, GbaChannel2, GbaChannel3, GbaChannel4) = try!(decode_v2(r, &INTERNAL_REGS));
+	let fifo_a: FifoV2 = try!(decode_v2(r, &INTERNAL_REGS));
+	let fifo_b: FifoV2 = try!(decode_v2(r, &INTERNAL_REGS));
+
+	let mut data = try!(encode_v1(&affine_and_state));
+	data.extend(try!(encode_v1(&dma_registers)));
+	data.extend(try!(encode_v1(&timers)));
+	data.extend(try!(encode_v1(&channels)));
+	data.extend(try!(fifo_a.encode_v1()));
+	data.extend(try!(fifo_b.encode_v1()));
+	return Ok(data);
+}
+
+fn decode_v2<T: Decodable>(mut r: &mut Read, kind: &ChunkKind) -> Result<T, SaveStateError> {
+	decode_from(&mut r, SizeLimit::Infinite).map_err(|e| SaveStateError::MalformedChunk {
+		chunk: tag_name(&kind.tag),
+		reason: format!("{}", e)
+	})
+}
+
+fn encode_v1<T: Encodable>(value: &T) -> Result<Vec<u8>, SaveStateError> {
+	encode(value, SizeLimit::Infinite).map_err(|e| SaveStateError::Encode(format!("{}", e)))
+}
@@ -0,0 +1,401 @@
+//! Save states.
+//!
+//! A state is a deflate stream that starts with the Pyrite header and the format
+//! version, followed by a list of tagged chunks (see `chunk.rs`). Every chunk has
+//! its own version so sections can change without breaking older states, those
+//! are brought up to date by the migrations in `migrations.rs`.
+//!
+//! A state holds the CPU, all of the internal memory except the BIOS, the internal
+//! IO registers (DMA, timers, sound channels and the DMA sound FIFOs), the joypad,
+//! the last frame drawn by the LCD, the audio sample clock and any writes to the ROM.
+//! A `META` chunk describes the state for the save slot browser and isn't loaded.
+//! `GbaExtras` isn't saved because it only holds frontend state like pausing, and there
+//! is no cartridge backup (SRAM, flash, EEPROM) or RTC emulation yet to save.
+
+pub mod chunk;
+pub mod migrations;
+pub mod rewind;
+
+#[cfg(test)]
+mod tests;
+
+use super::{Gba, CYCLES_PER_SECOND};
+use super::core::cpu::registers::ArmRegisters;
+use super::core::cpu::clock::ArmCpuClock;
+use super::core::memory::ioreg::InternalRegisters;
+use super::core::memory::{MEM_BIOS, MEM_OAM};
+use super::hw::joypad::GbaJoypad;
+use self::chunk::{Chunk, ChunkKind};
+
+use bincode::rustc_serialize::{encode, decode};
+use bincode::SizeLimit;
+use rustc_serialize::{Encodable, Decodable};
+
+use time;
+use std::fmt;
+use std::io;
+use std::io::prelude::*;
+use std::fs::File;
+use std::path::Path;
+use std::fs::create_dir_all;
+
+use flate2::Compression;
+use flate2::write::DeflateEncoder;
+use flate2::read::DeflateDecoder;
+
+/// Header for pyrite. It just says pyrite96
+const PYRITE_HEADER: [u8; 8] = [0x70, 0x79, 0x72, 0x69, 0x74, 0x65, 0x39, 0x36];
+
+/// The version of the current format.
+const VERSION: u8 = 3;
+
+/// The last version that wrote sections back to back instead of in chunks.
+const LEGACY_VERSION: u8 = 2;
+
+/// Everything in the internal memory after the BIOS.
+pub const RAM_SIZE: usize = MEM_OAM.local_addr + MEM_OAM.size - MEM_BIOS.size;
+
+pub const INFO: ChunkKind = ChunkKind { tag: [b'I', b'N', b'F', b'O'], version: 1, required: false };
+pub const META: ChunkKind = ChunkKind { tag: [b'M', b'E', b'T', b'A'], version: 1, required: false };
+pub const CPU: ChunkKind = ChunkKind { tag: [b'C', b'P', b'U', b' '], version: 1, required: true };
+pub const CPU_CLOCK: ChunkKind = ChunkKind { tag: [b'C', b'L', b'C', b'K'], version: 1, required: true };
+pub const JOYPAD: ChunkKind = ChunkKind { tag: [b'J', b'O', b'Y', b'P'], version: 1, required: true };
+pub const INTERNAL_REGS: ChunkKind = ChunkKind { tag: [b'I', b'R', b'E', b'G'], version: 1, required: true };
+pub const RAM: ChunkKind = ChunkKind { tag: [b'R', b'A', b'M', b' '], version: 1, required: true };
+pub const LCD: ChunkKind = ChunkKind { tag: [b'L', b'C', b'D', b' '], version: 1, required: false };
+pub const AUDIO: ChunkKind = ChunkKind { tag: [b'A', b'U', b'D', b'O'], version: 1, required: false };
+pub const ROM_WRITES: ChunkKind = ChunkKind { tag: [b'R', b'O', b'M', b'W'], version: 1, required: false };
+
+/// Every kind of chunk that this build reads and writes.
+pub const CHUNK_KINDS: [&'static ChunkKind; 10] = [
+	&INFO, &META, &CPU, &CPU_CLOCK, &JOYPAD, &INTERNAL_REGS, &RAM, &LCD, &AUDIO, &ROM_WRITES
+];
+
+/// The LCD's screen buffer as packed RGB.
+const SCREEN_SIZE: usize = 240 * 160 * 3;
+
+/// The size of the thumbnail stored in `StateMeta`, half the size of the screen.
+pub const THUMBNAIL_WIDTH: usize = 120;
+pub const THUMBNAIL_HEIGHT: usize = 80;
+
+/// Describes the game that a state was made with.
+#[derive(RustcEncodable, RustcDecodable, Debug, Clone)]
+pub struct StateInfo {
+	pub game_code: String,
+	pub game_title: String,
+	pub rom_hash: u64
+}
+
+/// Shown in the save slot browser without loading the state.
+#[derive(RustcEncodable, RustcDecodable, Debug, Clone)]
+pub struct StateMeta {
+	/// When the state was saved, in seconds since the Unix epoch.
+	pub timestamp: i64,
+
+	/// Seconds of emulated time since the game was started.
+	pub play_time: u64,
+
+	/// The last frame drawn by the LCD as packed RGB, `THUMBNAIL_WIDTH` by `THUMBNAIL_HEIGHT`.
+	pub thumbnail: Vec<u8>
+}
+
+#[derive(Debug)]
+pub enum SaveStateError {
+	Io(io::Error),
+
+	/// The file doesn't start with the Pyrite header.
+	NotASaveState,
+
+	/// The format version is newer than this build or too old to load.
+	UnsupportedFormat(u8),
+
+	/// A chunk is newer than this build or there's no migration from its version.
+	UnsupportedChunkVersion { chunk: String, version: u16, supported: u16 },
+
+	MissingChunk(String),
+	MalformedChunk { chunk: String, reason: String },
+
+	/// The state was made with another game.
+	RomMismatch { expected: String, found: String },
+
+	Encode(String)
+}
+
+impl fmt::Display for SaveStateError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			SaveStateError::Io(ref e) => write!(f, "I/O error: {}", e),
+			SaveStateError::NotASaveState => write!(f, "Not a Pyrite save state."),
+			SaveStateError::UnsupportedFormat(version) => write!(f, "Save state format version {} is not supported (current version is {}).", version, VERSION),
+			SaveStateError::UnsupportedChunkVersion { ref chunk, version, supported } => {
+				write!(f, "The {} section is version {} and can't be loaded (current version is {}).", chunk, version, supported)
+			},
+			SaveStateError::MissingChunk(ref chunk) => write!(f, "The save state has no {} section.", chunk),
+			SaveStateError::MalformedChunk { ref chunk, ref reason } => write!(f, "The {} section is corrupted: {}", chunk, reason),
+			SaveStateError::RomMismatch { ref expected, ref found } => {
+				write!(f, "The save state was made with game {} but game {} is loaded.", expected, found)
+			},
+			SaveStateError::Encode(ref e) => write!(f, "Failed to encode the save state: {}", e)
+		}
+	}
+}
+
+impl From<io::Error> for SaveStateError {
+	fn from(e: io::Error) -> SaveStateError {
+		SaveStateError::Io(e)
+	}
+}
+
+pub trait BinarySerialization {
+	fn serialize(&self, writer: &mut Write) -> Result<(), SaveStateError>;
+
+	/// Loads a state. If this fails the machine is left as it was.
+	fn deserialize(&mut self, reader: &mut Read) -> Result<(), SaveStateError>;
+
+	fn save_to_file(&self, file_path: &str) -> Result<(), SaveStateError>;
+	fn load_from_file(&mut self, file_path: &str) -> Result<(), SaveStateError>;
+}
+
+impl BinarySerialization for Gba {
+	fn save_to_file(&self, file_path: &str) -> Result<(), SaveStateError> {
+		if let Some(parent) = Path::new(file_path).parent() {
+			if parent != Path::new("") {
+				try!(create_dir_all(parent));
+			}
+		}
+		let mut f = try!(File::create(file_path));
+		return self.serialize(&mut f);
+	}
+
+	fn load_from_file(&mut self, file_path: &str) -> Result<(), SaveStateError> {
+		let mut f = try!(File::open(file_path));
+		return self.deserialize(&mut f);
+	}
+
+	fn serialize(&self, w_unwrapped: &mut Write) -> Result<(), SaveStateError> {
+		let chunks = try!(self.state_chunks());
+
+		let mut w = DeflateEncoder::new(w_unwrapped, Compression::Default);
+		try!(w.write_all(&PYRITE_HEADER));
+		try!(w.write_all(&[VERSION]));
+		try!(chunk::write_chunks(&mut w, &chunks));
+		try!(w.finish());
+		return Ok(())
+	}
+
+	fn deserialize(&mut self, r: &mut Read) -> Result<(), SaveStateError> {
+		let chunks = try!(read_state_chunks(r));
+		return self.apply_chunks(chunks);
+	}
+}
+
+fn read_state_chunks(r_unwrapped: &mut Read) -> Result<Vec<Chunk>, SaveStateError> {
+	let mut r = DeflateDecoder::new(r_unwrapped);
+
+	let mut header = [0u8; 8];
+	if r.read_exact(&mut header).is_err() || header != PYRITE_HEADER {
+		return Err(SaveStateError::NotASaveState);
+	}
+
+	let mut version = [0u8; 1];
+	try!(r.read_exact(&mut version));
+	match version[0] {
+		VERSION => chunk::read_chunks(&mut r),
+		LEGACY_VERSION => migrations::chunks_from_v2(&mut r),
+		v => Err(SaveStateError::UnsupportedFormat(v))
+	}
+}
+
+/// Reads the description of a state without loading it.
+/// States from before this was saved don't have one.
+pub fn read_state_meta(r: &mut Read) -> Result<Option<StateMeta>, SaveStateError> {
+	let chunks = try!(read_state_chunks(r));
+	match chunks.into_iter().find(|c| c.tag == META.tag) {
+		Some(mut chunk) => {
+			try!(migrations::upgrade(&mut chunk, &META));
+			decode_chunk(&chunk).map(Some)
+		},
+		None => Ok(None)
+	}
+}
+
+/// Reads the description of the state in a file, see `read_state_meta`.
+pub fn read_state_meta_from_file(file_path: &str) -> Result<Option<StateMeta>, SaveStateError> {
+	let mut f = try!(File::open(file_path));
+	return read_state_meta(&mut f);
+}
+
+impl Gba {
+	/// Information about the loaded game that is stored with save states.
+	pub fn state_info(&self) -> StateInfo {
+		StateInfo {
+			game_code: self.game_code(),
+			game_title: self.game_title(),
+			rom_hash: self.rom_hash()
+		}
+	}
+
+	/// The description of the current state that is shown in the save slot browser.
+	pub fn state_meta(&self) -> StateMeta {
+		StateMeta {
+			timestamp: time::get_time().sec,
+			play_time: self.cpu.clock.cycles / CYCLES_PER_SECOND,
+			thumbnail: self.thumbnail()
+		}
+	}
+
+	fn state_chunks(&self) -> Result<Vec<Chunk>, SaveStateError> {
+		let cpu = (self.cpu.branched, self.cpu.prefetch_wait, &self.cpu.registers);
+		Ok(vec![
+			Chunk::new(&INFO, try!(encode_chunk(&self.state_info()))),
+			Chunk::new(&META, try!(encode_chunk(&self.state_meta()))),
+			Chunk::new(&CPU, try!(encode_chunk(&cpu))),
+			Chunk::new(&CPU_CLOCK, try!(encode_chunk(&self.cpu.clock))),
+			Chunk::new(&JOYPAD, try!(encode_chunk(&self.joypad))),
+			Chunk::new(&INTERNAL_REGS, try!(encode_chunk(&self.cpu.memory.internal_regs))),
+			Chunk::new(&RAM, self.cpu.memory.internal_data[MEM_BIOS.size..].to_vec()),
+			Chunk::new(&LCD, self.screen_bytes()),
+			Chunk::new(&AUDIO, try!(encode_chunk(&(self.device.audio.sample_clock, self.device.audio.last_sample_cycle)))),
+			Chunk::new(&ROM_WRITES, try!(encode_chunk(&self.cpu.memory.rom_writes())))
+		])
+	}
+
+	/// Upgrades, checks and decodes every chunk before anything is changed,
+	/// so that a state that fails to load doesn't leave the machine half loaded.
+	fn apply_chunks(&mut self, mut chunks: Vec<Chunk>) -> Result<(), SaveStateError> {
+		for chunk in chunks.iter_mut() {
+			if let Some(kind) = CHUNK_KINDS.iter().find(|k| k.tag == chunk.tag) {
+				try!(migrations::upgrade(chunk, kind));
+			}
+		}
+
+		for kind in CHUNK_KINDS.iter().filter(|k| k.required) {
+			if !chunks.iter().any(|c| c.tag == kind.tag) {
+				return Err(SaveStateError::MissingChunk(chunk::tag_name(&kind.tag)));
+			}
+		}
+
+		let mut info: Option<StateInfo> = None;
+		let mut cpu: Option<(bool, u8, ArmRegisters)> = None;
+		let mut clock: Option<ArmCpuClock> = None;
+		let mut joypad: Option<GbaJoypad> = None;
+		let mut internal_regs: Option<InternalRegisters> = None;
+		let mut ram: Option<&[u8]> = None;
+		let mut screen: Option<&[u8]> = None;
+		let mut audio: Option<(u64, u64)> = None;
+		let mut rom_writes: Vec<(u32, u8)> = Vec::new();
+
+		for chunk in chunks.iter() {
+			if chunk.tag == INFO.tag { info = Some(try!(decode_chunk(chunk))) }
+			else if chunk.tag == META.tag { /* Only used by the save slot browser. */ }
+			else if chunk.tag == CPU.tag { cpu = Some(try!(decode_chunk(chunk))) }
+			else if chunk.tag == CPU_CLOCK.tag { clock = Some(try!(decode_chunk(chunk))) }
+			else if chunk.tag == JOYPAD.tag { joypad = Some(try!(decode_chunk(chunk))) }
+			else if chunk.tag == INTERNAL_REGS.tag { internal_regs = Some(try!(decode_chunk(chunk))) }
+			else if chunk.tag == RAM.tag { ram = Some(try!(chunk_bytes(chunk, RAM_SIZE))) }
+			else if chunk.tag == LCD.tag { screen = Some(try!(chunk_bytes(chunk, SCREEN_SIZE))) }
+			else if chunk.tag == AUDIO.tag { audio = Some(try!(decode_chunk(chunk))) }
+			else if chunk.tag == ROM_WRITES.tag { rom_writes = try!(decode_chunk(chunk)) }
+			else {
+				console_warn!("Skipping unknown save state section {}.", chunk.name());
+			}
+		}
+
+		if let Some(ref info) = info {
+			try!(self.check_state_info(info));
+		}
+
+		// The required chunks were checked above.
+		let (branched, prefetch_wait, registers) = cpu.unwrap();
+		self.cpu.branched = branched;
+		self.cpu.prefetch_wait = prefetch_wait;
+		self.cpu.registers = registers;
+		self.cpu.clock = clock.unwrap();
+		self.joypad = joypad.unwrap();
+		self.cpu.memory.internal_regs = internal_regs.unwrap();
+		self.cpu.memory.internal_data[MEM_BIOS.size..].clone_from_slice(ram.unwrap());
+		// States from before these were saved don't have any writes to the ROM.
+		self.cpu.memory.replace_rom_writes(&rom_writes);
+		if let Some(screen) = screen {
+			for (pixel, rgb) in self.lcd.screen_buffer.iter_mut().flat_map(|line| line.iter_mut()).zip(screen.chunks(3)) {
+				*pixel = (rgb[0], rgb[1], rgb[2]);
+			}
+		}
+		if let Some((sample_clock, last_sample_cycle)) = audio {
+			self.device.audio.sample_clock = sample_clock;
+			self.device.audio.last_sample_cycle = last_sample_cycle;
+		}
+		return Ok(())
+	}
+
+	fn screen_bytes(&self) -> Vec<u8> {
+		let mut bytes = Vec::with_capacity(SCREEN_SIZE);
+		for line in self.lcd.screen_buffer.iter() {
+			for &(r, g, b) in line.iter() {
+				bytes.push(r);
+				bytes.push(g);
+				bytes.push(b);
+			}
+		}
+		return bytes;
+	}
+
+	/// Averages every 2x2 block of the screen into one pixel.
+	fn thumbnail(&self) -> Vec<u8> {
+		let screen = &self.lcd.screen_buffer;
+		let mut bytes = Vec::with_capacity(THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT * 3);
+		for y in 0..THUMBNAIL_HEIGHT {
+			for x in 0..THUMBNAIL_WIDTH {
+				let (mut r, mut g, mut b) = (0u32, 0u32, 0u32);
+				for &(px, py) in [(0, 0), (1, 0), (0, 1), (1, 1)].iter() {
+					let (pr, pg, pb) = screen[y * 2 + py][x * 2 + px];
+					r += pr as u32;
+					g += pg as u32;
+					b += pb as u32;
+				}
+				bytes.push((r / 4) as u8);
+				bytes.push((g / 4) as u8);
+				bytes.push((b / 4) as u8);
+			}
+		}
+		return bytes;
+	}
+
+	/// States can only be loaded into the game they were made with.
+	/// A different revision or dump of the same game is allowed but might not work.
+	fn check_state_info(&self, info: &StateInfo) -> Result<(), SaveStateError> {
+		let game_code = self.game_code();
+		if info.game_code != game_code {
+			return Err(SaveStateError::RomMismatch {
+				expected: format!("{} ({})", info.game_title, info.game_code),
+				found: format!("{} ({})", self.game_title(), game_code)
+			});
+		}
+		if info.rom_hash != self.rom_hash() {
+			console_warn!("This save state was made with a different version of {}.", info.game_title);
+		}
+		return Ok(())
+	}
+}
+
+fn encode_chunk<T: Encodable>(value: &T) -> Result<Vec<u8>, SaveStateError> {
+	encode(value, SizeLimit::Infinite).map_err(|e| SaveStateError::Encode(format!("{}", e)))
+}
+
+/// Chunks that are just a block of memory have to be exactly the right size.
+fn chunk_bytes(chunk: &Chunk, size: usize) -> Result<&[u8], SaveStateError> {
+	if chunk.data.len() != size {
+		return Err(SaveStateError::MalformedChunk {
+			chunk: chunk.name(),
+			reason: format!("expected {} bytes, found {}", size, chunk.data.len())
+		});
+	}
+	return Ok(&chunk.data);
+}
+
+fn decode_chunk<T: Decodable>(chunk: &Chunk) -> Result<T, SaveStateError> {
+	decode(&chunk.data).map_err(|e| SaveStateError::MalformedChunk {
+		chunk: chunk.name(),
+		reason: format!("{}", e)
+	})
+}
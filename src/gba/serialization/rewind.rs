@@ -0,0 +1,189 @@
+//! Keeps the last few minutes of gameplay in memory so that it can be played backwards.
+//!
+//! Snapshots are the uncompressed chunks of a save state. Only the newest one is kept
+//! as is, every older one is stored as the deflated XOR of itself and the snapshot
+//! after it. Most of the memory doesn't change between two frames so those deltas are
+//! mostly zeros and compress down to a few kilobytes. Rewinding undoes the newest delta.
+
+use std::collections::VecDeque;
+use std::io::prelude::*;
+use flate2::Compression;
+use flate2::write::DeflateEncoder;
+use flate2::read::DeflateDecoder;
+use super::{Gba, SaveStateError, INFO, META};
+use super::chunk;
+
+/// Bytes used to keep track of each delta on top of its data.
+const DELTA_OVERHEAD: usize = 32;
+
+struct Delta {
+	/// The length of the older snapshot, snapshots can have different sizes.
+	len: usize,
+	data: Vec<u8>
+}
+
+pub struct RewindBuffer {
+	/// False for machines that can't rewind, e.g. headless ones.
+	pub available: bool,
+
+	/// The newest snapshot.
+	current: Vec<u8>,
+
+	/// Deltas from the oldest to the newest.
+	deltas: VecDeque<Delta>,
+
+	/// Memory used by the deltas.
+	used: usize,
+
+	/// The most memory that the deltas can use, the oldest are dropped past this.
+	capacity: usize,
+
+	/// Frames between snapshots.
+	interval: u32,
+	frames_since_snapshot: u32,
+
+	/// Frames left to show the snapshot that was rewound to.
+	frames_until_step_back: u32
+}
+
+impl RewindBuffer {
+	pub fn new() -> RewindBuffer {
+		RewindBuffer {
+			available: true,
+			current: Vec::new(),
+			deltas: VecDeque::new(),
+			used: 0,
+			capacity: 0,
+			interval: 1,
+			frames_since_snapshot: 0,
+			frames_until_step_back: 0
+		}
+	}
+
+	/// Sets the memory budget in bytes and the number of frames between snapshots.
+	pub fn configure(&mut self, capacity: usize, interval: u32) {
+		self.capacity = capacity;
+		self.interval = max!(1, interval);
+		self.drop_oldest();
+	}
+
+	/// The number of snapshots that can be rewound to.
+	pub fn len(&self) -> usize {
+		self.deltas.len()
+	}
+
+	/// Memory used by the snapshots.
+	pub fn memory_used(&self) -> usize {
+		self.used + self.current.len()
+	}
+
+	/// The number of frames that can be rewound.
+	pub fn frames(&self) -> usize {
+		self.deltas.len() * self.interval as usize
+	}
+
+	pub fn clear(&mut self) {
+		self.current.clear();
+		self.deltas.clear();
+		self.used = 0;
+		self.frames_since_snapshot = 0;
+		self.frames_until_step_back = 0;
+	}
+
+	/// Called at the end of every frame, takes a snapshot if enough frames have gone by.
+	pub fn should_snapshot(&mut self) -> bool {
+		self.frames_until_step_back = 0;
+		self.frames_since_snapshot += 1;
+		if self.frames_since_snapshot >= self.interval {
+			self.frames_since_snapshot = 0;
+			return true;
+		}
+		return false;
+	}
+
+	/// Called for every frame while rewinding. Snapshots are `interval` frames apart, so each
+	/// one is shown for that many frames to rewind at the speed the game was played.
+	pub fn should_step_back(&mut self) -> bool {
+		if self.frames_until_step_back > 0 {
+			self.frames_until_step_back -= 1;
+			return false;
+		}
+		self.frames_until_step_back = self.interval - 1;
+		return true;
+	}
+
+	pub fn push(&mut self, snapshot: Vec<u8>) {
+		if !self.current.is_empty() {
+			let xor = xor_padded(&self.current, &snapshot);
+			let mut w = DeflateEncoder::new(Vec::new(), Compression::Fast);
+			// Writing to a Vec can't fail.
+			w.write_all(&xor).expect("Failed to compress rewind snapshot.");
+			let data = w.finish().expect("Failed to compress rewind snapshot.");
+			self.used += data.len() + DELTA_OVERHEAD;
+			self.deltas.push_back(Delta { len: self.current.len(), data: data });
+		}
+		self.current = snapshot;
+		self.drop_oldest();
+	}
+
+	/// Steps back to the previous snapshot and returns it.
+	pub fn pop(&mut self) -> Option<&[u8]> {
+		let delta = match self.deltas.pop_back() {
+			Some(d) => d,
+			None => return None
+		};
+		self.used -= delta.data.len() + DELTA_OVERHEAD;
+
+		let mut xor = Vec::with_capacity(max!(delta.len, self.current.len()));
+		if DeflateDecoder::new(&delta.data[..]).read_to_end(&mut xor).is_err() {
+			// Only data that was compressed above is ever read back.
+			self.clear();
+			return None;
+		}
+		if self.current.len() < xor.len() {
+			self.current.resize(xor.len(), 0);
+		}
+		for (c, x) in self.current.iter_mut().zip(xor.iter()) {
+			*c ^= *x;
+		}
+		self.current.truncate(delta.len);
+		self.frames_since_snapshot = 0;
+		return Some(&self.current);
+	}
+
+	fn drop_oldest(&mut self) {
+		while self.used > self.capacity {
+			match self.deltas.pop_front() {
+				Some(d) => self.used -= d.data.len() + DELTA_OVERHEAD,
+				None => break
+			}
+		}
+	}
+}
+
+/// XORs two snapshots, the shorter one is padded with zeros.
+fn xor_padded(a: &[u8], b: &[u8]) -> Vec<u8> {
+	let (longer, shorter) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+	let mut xor = longer.to_vec();
+	for (x, s) in xor.iter_mut().zip(shorter.iter()) {
+		*x ^= *s;
+	}
+	return xor;
+}
+
+impl Gba {
+	/// The machine state without the game info and description of a save state.
+	pub fn rewind_snapshot(&self) -> Result<Vec<u8>, SaveStateError> {
+		let chunks: Vec<_> = try!(self.state_chunks()).into_iter()
+			.filter(|c| c.tag != INFO.tag && c.tag != META.tag)
+			.collect();
+		let mut data = Vec::new();
+		try!(chunk::write_chunks(&mut data, &chunks));
+		return Ok(data);
+	}
+
+	pub fn load_rewind_snapshot(&mut self, mut snapshot: &[u8]) -> Result<(), SaveStateError> {
+		let chunks = try!(chunk::read_chunks(&mut snapshot));
+		return self.apply_chunks(chunks);
+	}
+}
@@ -0,0 +1,343 @@
+use std::io::prelude::*;
+use bincode::rustc_serialize::encode;
+use bincode::SizeLimit;
+use flate2::Compression;
+use flate2::write::DeflateEncoder;
+use super::super::Gba;
+use super::super::core::memory::MEM_BIOS;
+use super::chunk::{self, Chunk};
+use super::migrations::FifoV2;
+use super::{BinarySerialization, SaveStateError, PYRITE_HEADER, VERSION, LEGACY_VERSION, CPU_CLOCK, RAM};
+use super::{read_state_meta, THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT};
+use super::rewind::RewindBuffer;
+use super::super::CYCLES_PER_SECOND;
+
+/// A ROM that is just a cartridge header with the given title and game code.
+fn fake_rom(title: &str, code: &str, revision: u8) -> Vec<u8> {
+	let mut rom = vec![0u8; 0x200];
+	for (idx, b) in title.bytes().enumerate() { rom[0xA0 + idx] = b; }
+	for (idx, b) in code.bytes().enumerate() { rom[0xAC + idx] = b; }
+	rom[0xBC] = revision;
+	return rom;
+}
+
+fn new_gba(rom: Vec<u8>) -> Box<Gba> {
+	let mut gba = Box::new(Gba::headless());
+	gba.load_cartridge(rom);
+	return gba;
+}
+
+/// Puts something recognizable into the parts of the machine that are saved.
+fn scribble(gba: &mut Gba) {
+	for r in 0..15 {
+		gba.cpu.registers.set(r, 0x1000 + r);
+	}
+	gba.cpu.clock.cycles = 123456789;
+	gba.cpu.prefetch_wait = 3;
+	gba.cpu.memory.internal_regs.timers[2].reload = 0xbeef;
+	for (idx, b) in gba.cpu.memory.internal_data[MEM_BIOS.size..].iter_mut().enumerate() {
+		*b = (idx * 7) as u8;
+	}
+}
+
+fn assert_same_state(a: &Gba, b: &Gba) {
+	for r in 0..16 {
+		assert_eq!(a.cpu.registers.get(r), b.cpu.registers.get(r));
+	}
+	assert_eq!(a.cpu.clock.cycles, b.cpu.clock.cycles);
+	assert_eq!(a.cpu.prefetch_wait, b.cpu.prefetch_wait);
+	assert_eq!(a.cpu.memory.internal_regs.timers[2].reload, b.cpu.memory.internal_regs.timers[2].reload);
+	assert!(&a.cpu.memory.internal_data[..] == &b.cpu.memory.internal_data[..], "RAM differs");
+}
+
+/// Wraps a body in the compressed header that every state starts with.
+fn raw_state(version: u8, body: &[u8]) -> Vec<u8> {
+	let mut w = DeflateEncoder::new(Vec::new(), Compression::Default);
+	w.write_all(&PYRITE_HEADER).unwrap();
+	w.write_all(&[version]).unwrap();
+	w.write_all(body).unwrap();
+	return w.finish().unwrap();
+}
+
+fn chunk_state(chunks: &[Chunk]) -> Vec<u8> {
+	let mut body = Vec::new();
+	chunk::write_chunks(&mut body, chunks).unwrap();
+	return raw_state(VERSION, &body);
+}
+
+#[test]
+fn round_trip() {
+	let rom = fake_rom("PYRITE TEST", "PYRE", 0);
+	let mut saved = new_gba(rom.clone());
+	scribble(&mut saved);
+	let mut data = Vec::new();
+	saved.serialize(&mut data).unwrap();
+
+	let mut loaded = new_gba(rom);
+	loaded.deserialize(&mut &data[..]).unwrap();
+	assert_same_state(&saved, &loaded);
+}
+
+/// A state in the layout that format version 2 wrote, with the FIFOs in their old layout.
+fn v2_state(gba: &Gba, fifo_a: &FifoV2, fifo_b: &FifoV2) -> Vec<u8> {
+	let regs = &gba.cpu.memory.internal_regs;
+	let mut body = vec![if gba.cpu.branched { 1 } else { 0 }, gba.cpu.prefetch_wait];
+	body.extend(encode(&gba.cpu.registers, SizeLimit::Infinite).unwrap());
+	body.extend(encode(&gba.cpu.clock, SizeLimit::Infinite).unwrap());
+	body.extend(encode(&gba.joypad, SizeLimit::Infinite).unwrap());
+	body.extend(encode(&(regs.bg2x, regs.bg2y, regs.bg3x, regs.bg3y, regs.halted, regs.stopped, regs.dma_dirty), SizeLimit::Infinite).unwrap());
+	body.extend(encode(&regs.dma_registers, SizeLimit::Infinite).unwrap());
+	body.extend(encode(&regs.timers, SizeLimit::Infinite).unwrap());
+	body.extend(encode(&(&regs.audio_channel1, &regs.audio_channel2, &regs.audio_channel3, &regs.audio_channel4), SizeLimit::Infinite).unwrap());
+	body.extend(encode(fifo_a, SizeLimit::Infinite).unwrap());
+	body.extend(encode(fifo_b, SizeLimit::Infinite).unwrap());
+	body.extend(gba.cpu.memory.internal_data[MEM_BIOS.size..].iter().cloned());
+	return raw_state(LEGACY_VERSION, &body);
+}
+
+#[test]
+fn loads_v2_states() {
+	let rom = fake_rom("PYRITE TEST", "PYRE", 0);
+	let mut saved = new_gba(rom.clone());
+	scribble(&mut saved);
+	saved.cpu.memory.internal_regs.bg2x = 0x1234;
+
+	let mut fifo_a = FifoV2::default();
+	fifo_a.timer = 1;
+	fifo_a.sample = -5;
+	fifo_a.frequency = 32768.0;
+	fifo_a.freq_acc = 0.5;
+	fifo_a.out_write_cursor = 100;
+	fifo_a.out_size = 100;
+	fifo_a.data[3] = 42;
+	fifo_a.data[4] = -42;
+	fifo_a.read_cursor = 3;
+	fifo_a.write_cursor = 5;
+	fifo_a.size = 2;
+	let mut fifo_b = FifoV2::default();
+	fifo_b.enable_left = true;
+	fifo_b.sample = 7;
+
+	let mut loaded = new_gba(rom);
+	loaded.deserialize(&mut &v2_state(&saved, &fifo_a, &fifo_b)[..]).unwrap();
+	assert_same_state(&saved, &loaded);
+	assert_eq!(loaded.cpu.memory.internal_regs.bg2x, 0x1234);
+
+	let regs = &mut loaded.cpu.memory.internal_regs;
+	assert_eq!(regs.audio_fifo_a.timer, 1);
+	assert_eq!(regs.audio_fifo_a.sample, -5);
+	assert_eq!(regs.audio_fifo_a.frequency, 32768.0);
+	assert_eq!(regs.audio_fifo_a.remaining(), 2);
+	assert_eq!(regs.audio_fifo_a.pop(), 42);
+	assert_eq!(regs.audio_fifo_a.pop(), -42);
+	assert!(regs.audio_fifo_b.enable_left);
+	assert_eq!(regs.audio_fifo_b.sample, 7);
+	assert_eq!(regs.audio_fifo_b.remaining(), 0);
+}
+
+#[test]
+fn states_carry_a_description() {
+	let mut gba = new_gba(fake_rom("PYRITE TEST", "PYRE", 0));
+	gba.cpu.clock.cycles = CYCLES_PER_SECOND * 3725 + 5;
+	gba.lcd.screen_buffer[2][4] = (200, 100, 0);
+	gba.lcd.screen_buffer[3][5] = (0, 100, 200);
+	let mut data = Vec::new();
+	gba.serialize(&mut data).unwrap();
+
+	let meta = read_state_meta(&mut &data[..]).unwrap().expect("The state has no description.");
+	assert_eq!(meta.play_time, 3725);
+	assert!(meta.timestamp > 0);
+	assert_eq!(meta.thumbnail.len(), THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT * 3);
+	// Pixels (4, 2) to (5, 3) are averaged into (2, 1).
+	let idx = (THUMBNAIL_WIDTH + 2) * 3;
+	assert_eq!(&meta.thumbnail[idx..(idx + 3)], &[50, 50, 50]);
+
+	// Legacy states don't have one.
+	let legacy = v2_state(&gba, &FifoV2::default(), &FifoV2::default());
+	assert!(read_state_meta(&mut &legacy[..]).unwrap().is_none());
+}
+
+#[test]
+fn rejects_other_games() {
+	let mut saved = new_gba(fake_rom("PYRITE TEST", "PYRE", 0));
+	scribble(&mut saved);
+	let mut data = Vec::new();
+	saved.serialize(&mut data).unwrap();
+
+	let mut loaded = new_gba(fake_rom("OTHER GAME", "OTHR", 0));
+	match loaded.deserialize(&mut &data[..]) {
+		Err(SaveStateError::RomMismatch { .. }) => {},
+		other => panic!("Expected a ROM mismatch, got {:?}", other)
+	}
+	assert_eq!(loaded.cpu.clock.cycles, 0);
+
+	// Another revision of the same game is allowed.
+	let mut revision = new_gba(fake_rom("PYRITE TEST", "PYRE", 1));
+	revision.deserialize(&mut &data[..]).unwrap();
+	assert_same_state(&saved, &revision);
+}
+
+#[test]
+fn rejects_garbage_and_unknown_formats() {
+	let mut gba = new_gba(fake_rom("PYRITE TEST", "PYRE", 0));
+	match gba.deserialize(&mut &b"definitely not a save state"[..]) {
+		Err(SaveStateError::NotASaveState) => {},
+		other => panic!("Expected NotASaveState, got {:?}", other)
+	}
+	match gba.deserialize(&mut &raw_state(VERSION + 1, &[])[..]) {
+		Err(SaveStateError::UnsupportedFormat(v)) => assert_eq!(v, VERSION + 1),
+		other => panic!("Expected UnsupportedFormat, got {:?}", other)
+	}
+}
+
+#[test]
+fn rejects_newer_chunks() {
+	let gba = new_gba(fake_rom("PYRITE TEST", "PYRE", 0));
+	let mut chunks = gba.state_chunks().unwrap();
+	for chunk in chunks.iter_mut().filter(|c| c.tag == CPU_CLOCK.tag) {
+		chunk.version = CPU_CLOCK.version + 1;
+	}
+
+	let mut loaded = new_gba(fake_rom("PYRITE TEST", "PYRE", 0));
+	match loaded.deserialize(&mut &chunk_state(&chunks)[..]) {
+		Err(SaveStateError::UnsupportedChunkVersion { version, supported, .. }) => {
+			assert_eq!(version, CPU_CLOCK.version + 1);
+			assert_eq!(supported, CPU_CLOCK.version);
+		},
+		other => panic!("Expected UnsupportedChunkVersion, got {:?}", other)
+	}
+}
+
+#[test]
+fn skips_unknown_chunks_and_requires_known_ones() {
+	let rom = fake_rom("PYRITE TEST", "PYRE", 0);
+	let mut saved = new_gba(rom.clone());
+	scribble(&mut saved);
+	let mut chunks = saved.state_chunks().unwrap();
+	chunks.push(Chunk { tag: [b'N', b'E', b'W', b'!'], version: 1, data: vec![1, 2, 3] });
+
+	let mut loaded = new_gba(rom.clone());
+	loaded.deserialize(&mut &chunk_state(&chunks)[..]).unwrap();
+	assert_same_state(&saved, &loaded);
+
+	chunks.retain(|c| c.tag != RAM.tag);
+	let mut partial = new_gba(rom);
+	match partial.deserialize(&mut &chunk_state(&chunks)[..]) {
+		Err(SaveStateError::MissingChunk(ref name)) => assert_eq!(name, "RAM"),
+		other => panic!("Expected MissingChunk, got {:?}", other)
+	}
+	// Nothing should have been loaded.
+	assert_eq!(partial.cpu.clock.cycles, 0);
+}
+
+/// Mode 3 with a loop that keeps filling the screen with an incrementing color
+/// and also writes the color into the ROM, so every frame is different.
+const DRAW_LOOP: [u32; 14] = [
+	0xE3A00301, // mov r0, #0x04000000
+	0xE3A01B01, // mov r1, #0x400
+	0xE3811003, // orr r1, r1, #3
+	0xE1C010B0, // strh r1, [r0]          ; DISPCNT = mode 3, BG2 on
+	0xE3A05302, // mov r5, #0x08000000
+	0xE3A03000, // mov r3, #0
+	0xE3A02406, // frame: mov r2, #0x06000000
+	0xE2824B4B, // add r4, r2, #0x12C00   ; the end of the mode 3 frame buffer
+	0xE0C230B2, // pixel: strh r3, [r2], #2
+	0xE2833001, // add r3, r3, #1
+	0xE5C53010, // strb r3, [r5, #16]
+	0xE1520004, // cmp r2, r4
+	0xBAFFFFFA, // blt pixel
+	0xEAFFFFF7  // b frame
+];
+
+fn new_running_gba() -> Box<Gba> {
+	let mut gba = new_gba(fake_rom("PYRITE TEST", "PYRE", 0));
+	for (idx, word) in DRAW_LOOP.iter().enumerate() {
+		for byte in 0..4 {
+			gba.cpu.memory.internal_data[idx * 4 + byte] = (word >> (byte * 8)) as u8;
+		}
+	}
+	gba.init();
+	return gba;
+}
+
+fn assert_same_output(a: &Gba, b: &Gba) {
+	for (line_a, line_b) in a.screen_buffer().iter().zip(b.screen_buffer().iter()) {
+		assert!(&line_a[..] == &line_b[..], "The frame buffers differ.");
+	}
+	assert!(&a.cpu.memory.internal_data[..] == &b.cpu.memory.internal_data[..], "RAM differs");
+	assert!(a.cpu.memory.rom == b.cpu.memory.rom, "ROM writes differ");
+	assert_same_state(a, b);
+}
+
+#[test]
+fn loaded_state_runs_like_the_original() {
+	let mut continuous = new_running_gba();
+	for _ in 0..3 { continuous.tick(); }
+	let mut data = Vec::new();
+	continuous.serialize(&mut data).unwrap();
+
+	let mut restored = new_running_gba();
+	restored.deserialize(&mut &data[..]).unwrap();
+	assert_same_output(&continuous, &restored);
+
+	for _ in 0..5 {
+		continuous.tick();
+		restored.tick();
+	}
+	assert_same_output(&continuous, &restored);
+}
+
+#[test]
+fn rewind_steps_back_through_snapshots() {
+	let mut gba = new_running_gba();
+	let mut rewind = RewindBuffer::new();
+	rewind.configure(64 * 1024 * 1024, 1);
+	let mut snapshots = Vec::new();
+	for _ in 0..4 {
+		gba.tick();
+		let snapshot = gba.rewind_snapshot().unwrap();
+		rewind.push(snapshot.clone());
+		snapshots.push(snapshot);
+	}
+
+	assert_eq!(rewind.len(), 3);
+	for expected in snapshots[..3].iter().rev() {
+		let snapshot = rewind.pop().expect("Ran out of rewind snapshots.");
+		assert!(snapshot == &expected[..], "The rewind snapshot differs.");
+	}
+	assert!(rewind.pop().is_none());
+
+	gba.load_rewind_snapshot(&snapshots[0]).unwrap();
+	let mut one_frame = new_running_gba();
+	one_frame.tick();
+	assert_same_output(&gba, &one_frame);
+}
+
+#[test]
+fn rewind_drops_the_oldest_snapshots() {
+	let mut gba = new_running_gba();
+	let mut rewind = RewindBuffer::new();
+	rewind.configure(64 * 1024 * 1024, 1);
+	for _ in 0..4 {
+		gba.tick();
+		rewind.push(gba.rewind_snapshot().unwrap());
+	}
+	assert_eq!(rewind.len(), 3);
+
+	rewind.configure(0, 1);
+	assert_eq!(rewind.len(), 0);
+	assert!(rewind.pop().is_none());
+}
+
+#[test]
+fn rewind_shows_each_snapshot_for_the_interval() {
+	let mut rewind = RewindBuffer::new();
+	rewind.configure(64 * 1024 * 1024, 3);
+	let steps: Vec<bool> = (0..7).map(|_| rewind.should_step_back()).collect();
+	assert_eq!(steps, vec![true, false, false, true, false, false, true]);
+
+	// Playing again starts over, the next rewind steps back right away.
+	rewind.should_step_back();
+	rewind.should_snapshot();
+	assert!(rewind.should_step_back());
+}
@@ -0,0 +1,164 @@
+//! Keeps the emulator running at the GBA's real frame rate, or faster or slower than it.
+
+use std::thread;
+use std::time::Duration;
+use time;
+use super::{CYCLES_PER_SECOND, GbaExtras};
+
+/// 228 lines of 1232 cycles each.
+pub const CYCLES_PER_FRAME: u64 = 280896;
+
+/// The length of a frame at normal speed, about 16.74ms (59.73Hz).
+pub const FRAME_DURATION_NS: u64 = CYCLES_PER_FRAME * 1000000000 / CYCLES_PER_SECOND;
+
+/// If the emulator falls further behind than this it stops trying
+/// to catch up, otherwise it would run fast for a while after a hiccup.
+const MAX_LAG_NS: u64 = FRAME_DURATION_NS * 6;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Speed {
+	Normal,
+
+	/// A multiple of the normal speed, 0 runs as fast as possible.
+	FastForward(u32),
+
+	/// A percentage of the normal speed.
+	SlowMotion(u32)
+}
+
+impl Speed {
+	/// The speed picked by the keys and menu, as configured in the settings.
+	pub fn current(extras: &GbaExtras) -> Speed {
+		if extras.fast_forward_held || extras.fast_forward_toggled {
+			Speed::FastForward(psetting!(fast_forward_speed))
+		} else if extras.slow_motion {
+			Speed::SlowMotion(max!(1, min!(psetting!(slow_motion_speed), 100)))
+		} else {
+			Speed::Normal
+		}
+	}
+
+	/// How long a frame should take, None if there is no limit.
+	pub fn frame_duration(&self) -> Option<u64> {
+		if !psetting!(frame_limit) { return None }
+		match *self {
+			Speed::Normal => Some(FRAME_DURATION_NS),
+			Speed::FastForward(0) => None,
+			Speed::FastForward(multiplier) => Some(FRAME_DURATION_NS / multiplier as u64),
+			Speed::SlowMotion(percentage) => Some(FRAME_DURATION_NS * 100 / percentage as u64)
+		}
+	}
+
+	/// See `AudioDevice::stretch`. Fast-forwarded audio would just be dropped chunks
+	/// or chipmunks so it's muted, slow motion audio is stretched to keep it playing.
+	pub fn audio_stretch(&self) -> f32 {
+		match *self {
+			Speed::Normal => 1.0,
+			Speed::FastForward(_) => 0.0,
+			Speed::SlowMotion(percentage) => 100.0 / percentage as f32
+		}
+	}
+}
+
+/// The most frames that are skipped in a row.
+pub const MAX_FRAME_SKIP: u32 = 9;
+
+/// Automatic frame skip goes down again once frames take less than this much of their time.
+const AUTO_SKIP_HEADROOM: f64 = 0.7;
+
+/// Skips rendering some frames while still emulating them.
+pub struct FrameSkip {
+	/// False for machines without a window, they always render every frame.
+	pub available: bool,
+
+	/// Frames skipped since the last one that was rendered.
+	skipped: u32,
+
+	/// The number of frames to skip picked by automatic frame skip.
+	pub auto_skip: u32,
+
+	/// Running average of the time that each frame takes in milliseconds.
+	average_frame_time: f64
+}
+
+impl FrameSkip {
+	pub fn new() -> FrameSkip {
+		FrameSkip {
+			available: true,
+			skipped: 0,
+			auto_skip: 0,
+			average_frame_time: 0.0
+		}
+	}
+
+	/// The number of frames skipped after each rendered one, as configured in the settings.
+	pub fn skip(&self) -> u32 {
+		if !self.available { return 0 }
+		if psetting!(auto_frame_skip) {
+			self.auto_skip
+		} else {
+			min!(psetting!(frame_skip), MAX_FRAME_SKIP)
+		}
+	}
+
+	/// Called before every frame with the time the last one took (`DebugData::full_frame_time`)
+	/// and how long a frame is allowed to take. Returns false if the frame shouldn't be rendered.
+	pub fn should_render(&mut self, last_frame_time: f64, frame_duration: Option<u64>) -> bool {
+		self.average_frame_time = self.average_frame_time * 0.9 + last_frame_time * 0.1;
+
+		let skip = self.skip();
+		if self.skipped < skip {
+			self.skipped += 1;
+			return false;
+		}
+		self.skipped = 0;
+
+		// Only adjusted once per rendered frame, the average includes the skipped frames.
+		if let Some(duration) = frame_duration {
+			let target = duration as f64 / 1000000.0;
+			if self.average_frame_time > target && self.auto_skip < MAX_FRAME_SKIP {
+				self.auto_skip += 1;
+			} else if self.average_frame_time < target * AUTO_SKIP_HEADROOM && self.auto_skip > 0 {
+				self.auto_skip -= 1;
+			}
+		} else {
+			// Running as fast as possible, there's no speed to keep up with.
+			self.auto_skip = 0;
+		}
+		return true;
+	}
+}
+
+pub struct FrameLimiter {
+	/// When the next frame should start.
+	next_frame_ns: u64
+}
+
+impl FrameLimiter {
+	pub fn new() -> FrameLimiter {
+		FrameLimiter {
+			next_frame_ns: time::precise_time_ns()
+		}
+	}
+
+	/// Called after every frame, sleeps until the next one should start.
+	/// Frames are scheduled from when the last one should have started rather than when
+	/// it finished so that the errors from sleeping don't add up over time.
+	pub fn wait(&mut self, frame_duration: Option<u64>) {
+		let now = time::precise_time_ns();
+		let frame_duration = match frame_duration {
+			Some(d) => d,
+			None => {
+				self.next_frame_ns = now;
+				return
+			}
+		};
+
+		self.next_frame_ns += frame_duration;
+		if self.next_frame_ns > now {
+			thread::sleep(Duration::new(0, (self.next_frame_ns - now) as u32));
+		} else if now - self.next_frame_ns > MAX_LAG_NS {
+			self.next_frame_ns = now;
+		}
+	}
+}
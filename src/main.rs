@@ -17,9 +17,15 @@ extern crate ansi_term;
 use std::io::prelude::*;
 use std::env;
 use std::fs::File;
+use std::path::Path;
 
 use gba::core::memory::GbaMemory;
 use gba::Gba;
+use gba::movie::Movie;
+use gba::device::GbaDevice;
+use gba::device::audio::{AudioSink, NullSink, WavSink};
+use gba::device::video::{GlVideoSink, HeadlessVideoSink};
+use pyrite::headless::{HeadlessOptions, InputScript};
 
 const BIOS_PATH: &'static str = "roms/legal/gba.bin";
 
@@ -60,6 +66,10 @@ pub fn load_rom(gba: &mut Gba, rom_path: String) {
 		Err(error) => panic!("Error while reading file `{}`: {}", filepath, error)
 	}
 	gba.load_cartridge(buffer);
+	gba.extras.rom_name = Path::new(&filepath).file_stem()
+		.map(|stem| stem.to_string_lossy().into_owned())
+		.unwrap_or(String::new());
+	gba.load_cheats();
 	debug_info!("Loaded ROM {}.", filepath);
 }
 
@@ -80,6 +90,30 @@ pub fn load_memory(rom_path: String) -> GbaMemory {
 	return memory;
 }
 
+fn start_recording(gba: &mut Gba, path: &Option<String>) {
+	if let Some(ref path) = *path {
+		if let Err(e) = gba.device.start_recording(path) {
+			println_err!("{}", e);
+			std::process::exit(1);
+		}
+	}
+}
+
+/// Starts playing or recording a movie from power-on if one was passed.
+fn start_movie(gba: &mut Gba, play_path: &Option<String>, record_path: &Option<String>) {
+	let result = if let Some(ref path) = *play_path {
+		Movie::load(path).and_then(|movie| gba.play_movie(movie))
+	} else if let Some(ref path) = *record_path {
+		gba.start_movie_recording(path, true)
+	} else {
+		Ok(())
+	};
+	if let Err(e) = result {
+		println_err!("{}", e);
+		std::process::exit(1);
+	}
+}
+
 pub fn run_gba(gba: &mut Gba) {
 	gba.run();
 }
@@ -92,42 +126,106 @@ const USAGE: &'static str = "
 Pyrite
 
 Usage:
-	pyrite <rom>
+	pyrite [--settings <file>] <rom>
+	pyrite run [--settings <file>] [--headless] [--frames <n>] [--screenshot <png>] [--screenshot-scale <n>] [--input <script>] [--save-state <file>] [--wav <file>] [--record <file>] [--movie <file> | --record-movie <file>] <rom>
 	pyrite (-d | --disasm) [(-t | --thumb)] <rom>
 	pyrite (-h | --help)
 	pyrite (-v | --version)
 
 Options:
-	-d --disasm     Disassembles the ROM.
-	-t --thumb      Will disassemble in thumb mode.
-	-h --help       Show this screen.
-	-v --version    Prints the version and exits.
+	-d --disasm          Disassembles the ROM.
+	-t --thumb           Will disassemble in thumb mode.
+	-h --help            Show this screen.
+	-v --version         Prints the version and exits.
+
+Run Options:
+	--settings <file>    Loads and saves settings in <file> instead of data/settings.json.
+	--headless           Runs without a window or sound device.
+	--frames <n>         The number of frames to run in headless mode. [default: 60]
+	--screenshot <png>   Writes the last frame to a PNG file in headless mode.
+	--screenshot-scale <n>  The integer scale to write the screenshot at. [default: 1]
+	--input <script>     Plays back an input script in headless mode.
+	--save-state <file>  Writes a save state after the last frame in headless mode.
+	--wav <file>         Writes the audio to a WAV file instead of playing it.
+	--record <file>      Records every frame to <file>.y4m and the audio to <file>.wav.
+	--movie <file>       Plays back an input movie from power-on.
+	--record-movie <file>  Records the input of every frame from power-on into a movie.
 ";
 
+const DEFAULT_HEADLESS_FRAMES: u64 = 60;
 
 #[derive(Default)]
 struct Args {
 	arg_rom: Option<String>,
+	cmd_run: bool,
 	flag_version: bool,
 	flag_disasm: bool,
 	flag_thumb: bool,
-	flag_help: bool
+	flag_help: bool,
+	flag_headless: bool,
+	flag_frames: Option<u64>,
+	flag_screenshot: Option<String>,
+	flag_screenshot_scale: Option<u32>,
+	flag_input: Option<String>,
+	flag_save_state: Option<String>,
+	flag_wav: Option<String>,
+	flag_record: Option<String>,
+	flag_movie: Option<String>,
+	flag_record_movie: Option<String>,
+	flag_settings: Option<String>
+}
+
+/// Returns the value that comes after an option and moves past it.
+fn option_value(args_list: &[String], index: &mut usize, option: &str) -> String {
+	if *index >= args_list.len() {
+		usage_error(&format!("Expected a value after {}.", option));
+	}
+	*index += 1;
+	return args_list[*index - 1].clone();
+}
+
+/// Prints what was wrong with the arguments and how to use Pyrite, then exits.
+fn usage_error(message: &str) -> ! {
+	println_err!("{}", message);
+	println_err!("{}", USAGE);
+	std::process::exit(1);
 }
 
 fn main() {
 	let mut args: Args = Default::default();
 	let args_list: Vec<String> = env::args().collect();
 
-	for (index, arg) in args_list.iter().enumerate() {
-		if index == 0 { continue; } // This is the executable.
+	let mut index = 1; // The first argument is the executable.
+	while index < args_list.len() {
+		let arg = &args_list[index];
+		index += 1;
 		if arg.starts_with("-") {
 			match arg.as_ref() {
 				"-d" | "--disasm"	=> args.flag_disasm = true,
 				"-t" | "--thumb"	=> args.flag_thumb = true,
 				"-v" | "--version"	=> args.flag_version = true,
 				"-h" | "--help"		=> args.flag_help = true,
-				_ => { panic!("Unexpected option {}", arg) }
+				"--headless"		=> args.flag_headless = true,
+				"--frames"			=> {
+					let frames = option_value(&args_list, &mut index, arg);
+					args.flag_frames = Some(frames.parse().unwrap_or_else(|_| usage_error("--frames must be a number.")));
+				},
+				"--screenshot"		=> args.flag_screenshot = Some(option_value(&args_list, &mut index, arg)),
+				"--screenshot-scale"	=> {
+					let scale = option_value(&args_list, &mut index, arg);
+					args.flag_screenshot_scale = Some(scale.parse().unwrap_or_else(|_| usage_error("--screenshot-scale must be a number.")));
+				},
+				"--input"			=> args.flag_input = Some(option_value(&args_list, &mut index, arg)),
+				"--save-state"		=> args.flag_save_state = Some(option_value(&args_list, &mut index, arg)),
+				"--wav"				=> args.flag_wav = Some(option_value(&args_list, &mut index, arg)),
+				"--record"			=> args.flag_record = Some(option_value(&args_list, &mut index, arg)),
+				"--movie"			=> args.flag_movie = Some(option_value(&args_list, &mut index, arg)),
+				"--record-movie"	=> args.flag_record_movie = Some(option_value(&args_list, &mut index, arg)),
+				"--settings"		=> args.flag_settings = Some(option_value(&args_list, &mut index, arg)),
+				_ => usage_error(&format!("Unexpected option {}", arg))
 			}
+		} else if arg == "run" && !args.cmd_run && args.arg_rom.is_none() {
+			args.cmd_run = true;
 		} else {
 			args.arg_rom = Some(arg.clone());
 		}
@@ -143,18 +241,78 @@ fn main() {
 		return;
 	}
 
+	if !(args.cmd_run && args.flag_headless) {
+		let headless_only = [
+			("--frames", args.flag_frames.is_some()),
+			("--screenshot", args.flag_screenshot.is_some()),
+			("--screenshot-scale", args.flag_screenshot_scale.is_some()),
+			("--input", args.flag_input.is_some()),
+			("--save-state", args.flag_save_state.is_some())
+		];
+		for &(option, passed) in headless_only.iter() {
+			if passed {
+				usage_error(&format!("{} only works with `run --headless`.", option));
+			}
+		}
+	}
+
+	if let Some(ref path) = args.flag_settings {
+		pyrite::settings::set_settings_path(path);
+	}
+
 	if let Some(rom_file) = args.arg_rom {
 		debug_info!("Emulating ROM: {}", rom_file);
 		if args.flag_disasm {
 			let mut memory = load_memory(rom_file);
 			// load_bios(&mut memory);
 			disasm_gba_rom(&mut memory, args.flag_thumb);
+		} else if args.cmd_run && args.flag_headless {
+			pyrite::load_settings();
+			debug_info!("Loaded pyrite settings.");
+			let audio_sink: Box<AudioSink> = match args.flag_wav {
+				Some(ref path) => Box::new(WavSink::new(path)),
+				None => Box::new(NullSink::new())
+			};
+			let mut gba = Box::new(Gba::with_device(GbaDevice::with_sinks(Box::new(HeadlessVideoSink::new()), audio_sink)));
+			load_bios(&mut gba.cpu.memory);
+			load_rom(&mut gba, rom_file);
+			// The game's profile isn't loaded, headless runs only use the global settings
+			// so that they do the same thing on every machine.
+			start_recording(&mut gba, &args.flag_record);
+			start_movie(&mut gba, &args.flag_movie, &args.flag_record_movie);
+
+			let options = HeadlessOptions {
+				frames: args.flag_frames.unwrap_or(DEFAULT_HEADLESS_FRAMES),
+				screenshot_path: args.flag_screenshot,
+				screenshot_scale: args.flag_screenshot_scale.unwrap_or(1),
+				save_state_path: args.flag_save_state,
+				input: match args.flag_input {
+					Some(ref path) => match InputScript::load(path) {
+						Ok(script) => script,
+						Err(e) => {
+							println_err!("Failed to load input script: {}", e);
+							std::process::exit(1);
+						}
+					},
+					None => Default::default()
+				}
+			};
+			if let Err(e) = pyrite::headless::run(&mut gba, &options) {
+				println_err!("{}", e);
+				std::process::exit(1);
+			}
 		} else {
 			pyrite::load_settings();
 			debug_info!("Loaded pyrite settings.");
-			let mut gba = Box::new(Gba::new());
+			let mut gba = match args.flag_wav {
+				Some(ref path) => Box::new(Gba::with_device(GbaDevice::with_sinks(Box::new(GlVideoSink::new()), Box::new(WavSink::new(path))))),
+				None => Box::new(Gba::new())
+			};
 			load_bios(&mut gba.cpu.memory);
 			load_rom(&mut gba, rom_file);
+			pyrite::profiles::load(&gba.game_code());
+			start_recording(&mut gba, &args.flag_record);
+			start_movie(&mut gba, &args.flag_movie, &args.flag_record_movie);
 			debug_info!("Press H to open up the debugger.");
 			run_gba(&mut gba);
 		}
@@ -0,0 +1,214 @@
+//! Keyboard and gamepad bindings for the GBA's buttons and the emulator's hotkeys.
+//!
+//! They're kept in `PyriteSettings::key_bindings` as the name of each action and the
+//! indices of the keys bound to it (see `events::vkc_to_idx`). Shift can't be bound, holding
+//! it turns the save state keys into save keys and makes the fast-forward key toggle.
+//! Gamepad inputs (see `gamepad::button_input`) are kept the same way in `gamepad_bindings`.
+
+use std::collections::BTreeMap;
+use glutin::VirtualKeyCode;
+use ::gba::hw::joypad::*;
+use ::gba::device::events::{idx_to_vkc, vkc_to_idx, KEY_COUNT};
+use ::gba::device::gamepad::{button_input, axis_input};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+	/// A GBA button, as its mask in KEYINPUT.
+	Button(u16),
+
+	/// Presses and releases GBA buttons every few frames while it's held.
+	Turbo(u16),
+
+	/// Plays a macro, or starts and stops recording it while shift is held.
+	Macro(usize),
+
+	Exit,
+	Pause,
+	Rewind,
+	FastForward,
+	SlowMotion,
+	Screenshot,
+
+	/// Loads a save state slot, or saves into it while shift is held.
+	Slot(usize),
+
+	DynamicDebug,
+	RegisterDump,
+	ToggleLayer(usize),
+	AllLayersOff,
+	AllLayersOn
+}
+
+pub struct Binding {
+	/// Stored in the settings, so it shouldn't change.
+	pub name: &'static str,
+	pub action: Action,
+	pub default_keys: &'static [VirtualKeyCode]
+}
+
+macro_rules! binding {
+	($name:expr, $action:expr, $($key:ident),*) => (
+		Binding { name: $name, action: $action, default_keys: &[$(VirtualKeyCode::$key),*] }
+	)
+}
+
+pub static BINDINGS: &'static [Binding] = &[
+	binding!("A", Action::Button(GBA_BTN_A), Z),
+	binding!("B", Action::Button(GBA_BTN_B), X),
+	binding!("Select", Action::Button(GBA_BTN_SELECT), Back),
+	binding!("Start", Action::Button(GBA_BTN_START), Return),
+	binding!("Right", Action::Button(GBA_BTN_RIGHT), Right),
+	binding!("Left", Action::Button(GBA_BTN_LEFT), Left),
+	binding!("Up", Action::Button(GBA_BTN_UP), Up),
+	binding!("Down", Action::Button(GBA_BTN_DOWN), Down),
+	binding!("R", Action::Button(GBA_BTN_R), S),
+	binding!("L", Action::Button(GBA_BTN_L), A),
+	binding!("Turbo A", Action::Turbo(GBA_BTN_A), C),
+	binding!("Turbo B", Action::Turbo(GBA_BTN_B), V),
+	binding!("Turbo L", Action::Turbo(GBA_BTN_L), Q),
+	binding!("Turbo R", Action::Turbo(GBA_BTN_R), W),
+
+	binding!("Macro 1", Action::Macro(1), Key6),
+	binding!("Macro 2", Action::Macro(2), Key7),
+	binding!("Macro 3", Action::Macro(3), Key8),
+	binding!("Macro 4", Action::Macro(4), Key9),
+
+	binding!("Exit", Action::Exit, Escape),
+	binding!("Pause", Action::Pause, P),
+	binding!("Rewind", Action::Rewind, Grave),
+	binding!("Fast Forward", Action::FastForward, Tab),
+	binding!("Slow Motion", Action::SlowMotion, Minus),
+	binding!("Screenshot", Action::Screenshot, F12),
+
+	binding!("Slot 1", Action::Slot(1), F1),
+	binding!("Slot 2", Action::Slot(2), F2),
+	binding!("Slot 3", Action::Slot(3), F3),
+	binding!("Slot 4", Action::Slot(4), F4),
+	binding!("Slot 5", Action::Slot(5), F5),
+	binding!("Slot 6", Action::Slot(6), F6),
+	binding!("Slot 7", Action::Slot(7), F7),
+	binding!("Slot 8", Action::Slot(8), F8),
+	binding!("Slot 9", Action::Slot(9), F9),
+	binding!("Slot 10", Action::Slot(10), F10),
+
+	binding!("Dynamic Debug", Action::DynamicDebug, D),
+	binding!("Register Dump", Action::RegisterDump, R),
+	binding!("Toggle BG 0", Action::ToggleLayer(0), Key1),
+	binding!("Toggle BG 1", Action::ToggleLayer(1), Key2),
+	binding!("Toggle BG 2", Action::ToggleLayer(2), Key3),
+	binding!("Toggle BG 3", Action::ToggleLayer(3), Key4),
+	binding!("Toggle OBJ", Action::ToggleLayer(4), Key5),
+	binding!("All Layers Off", Action::AllLayersOff, L),
+	binding!("All Layers On", Action::AllLayersOn, K),
+];
+
+pub fn default_bindings() -> BTreeMap<String, Vec<usize>> {
+	BINDINGS.iter().map(|b| {
+		(b.name.to_string(), b.default_keys.iter().map(|&k| vkc_to_idx(k)).collect())
+	}).collect()
+}
+
+/// Gives actions that aren't in the settings yet their default keys
+/// and drops keys that don't exist, e.g. from a file that was edited by hand.
+pub fn fix_bindings(bindings: &mut BTreeMap<String, Vec<usize>>) {
+	for b in BINDINGS.iter() {
+		if !bindings.contains_key(b.name) {
+			bindings.insert(b.name.to_string(), b.default_keys.iter().map(|&k| vkc_to_idx(k)).collect());
+		}
+	}
+	for keys in bindings.values_mut() {
+		keys.retain(|&k| k < KEY_COUNT);
+	}
+}
+
+/// The usual layout of an Xbox style controller on Linux. The d-pad is
+/// bound to the left stick as well as the hat, which shows up as axes 6 and 7.
+fn default_gamepad_inputs(name: &str) -> Vec<u32> {
+	match name {
+		"A" => vec![button_input(0)],
+		"B" => vec![button_input(1)],
+		"L" => vec![button_input(4)],
+		"R" => vec![button_input(5)],
+		"Select" => vec![button_input(6)],
+		"Start" => vec![button_input(7)],
+		"Left" => vec![axis_input(0, false), axis_input(6, false)],
+		"Right" => vec![axis_input(0, true), axis_input(6, true)],
+		"Up" => vec![axis_input(1, false), axis_input(7, false)],
+		"Down" => vec![axis_input(1, true), axis_input(7, true)],
+		_ => Vec::new()
+	}
+}
+
+pub fn default_gamepad_bindings() -> BTreeMap<String, Vec<u32>> {
+	BINDINGS.iter().map(|b| (b.name.to_string(), default_gamepad_inputs(b.name))).collect()
+}
+
+pub fn fix_gamepad_bindings(bindings: &mut BTreeMap<String, Vec<u32>>) {
+	for b in BINDINGS.iter() {
+		if !bindings.contains_key(b.name) {
+			bindings.insert(b.name.to_string(), default_gamepad_inputs(b.name));
+		}
+	}
+}
+
+/// The actions that a key is bound to.
+pub fn actions_for_key(key: VirtualKeyCode) -> Vec<Action> {
+	let key = vkc_to_idx(key);
+	let bindings = &::pyrite::get_settings().key_bindings;
+	BINDINGS.iter()
+		.filter(|b| bindings.get(b.name).map_or(false, |keys| keys.contains(&key)))
+		.map(|b| b.action)
+		.collect()
+}
+
+pub fn bind(name: &str, key: VirtualKeyCode) {
+	let key = vkc_to_idx(key);
+	let keys = ::pyrite::get_settings().key_bindings.entry(name.to_string()).or_insert(Vec::new());
+	if !keys.contains(&key) {
+		keys.push(key);
+		::pyrite::settings_changed();
+	}
+}
+
+/// The actions that a gamepad input is bound to.
+pub fn gamepad_actions(input: u32) -> Vec<Action> {
+	let bindings = &::pyrite::get_settings().gamepad_bindings;
+	BINDINGS.iter()
+		.filter(|b| bindings.get(b.name).map_or(false, |inputs| inputs.contains(&input)))
+		.map(|b| b.action)
+		.collect()
+}
+
+pub fn bind_gamepad(name: &str, input: u32) {
+	let inputs = ::pyrite::get_settings().gamepad_bindings.entry(name.to_string()).or_insert(Vec::new());
+	if !inputs.contains(&input) {
+		inputs.push(input);
+		::pyrite::settings_changed();
+	}
+}
+
+pub fn unbind_gamepad(name: &str, input: u32) {
+	if let Some(inputs) = ::pyrite::get_settings().gamepad_bindings.get_mut(name) {
+		inputs.retain(|&i| i != input);
+	}
+	::pyrite::settings_changed();
+}
+
+pub fn unbind(name: &str, key: usize) {
+	if let Some(keys) = ::pyrite::get_settings().key_bindings.get_mut(name) {
+		keys.retain(|&k| k != key);
+	}
+	::pyrite::settings_changed();
+}
+
+pub fn key_name(key: usize) -> String {
+	format!("{:?}", idx_to_vkc(key))
+}
+
+/// The keys bound to an action, for showing next to it.
+pub fn key_names(name: &str) -> String {
+	match ::pyrite::get_settings().key_bindings.get(name) {
+		Some(keys) if !keys.is_empty() => keys.iter().map(|&k| key_name(k)).collect::<Vec<_>>().join(", "),
+		_ => "unbound".to_string()
+	}
+}
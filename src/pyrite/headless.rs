@@ -0,0 +1,138 @@
+//! Runs the emulator without a window for a fixed number of frames.
+//! Used for scripting reproductions and for running ROMs in CI.
+
+use ::gba::Gba;
+use ::gba::hw::joypad;
+use ::gba::input::InputSource;
+use ::gba::serialization::BinarySerialization;
+
+#[derive(Default)]
+pub struct HeadlessOptions {
+	/// The number of frames to run for.
+	pub frames: u64,
+
+	/// Where to write a PNG of the last frame.
+	pub screenshot_path: Option<String>,
+
+	/// The integer scale the screenshot is written at, 1 is native resolution.
+	pub screenshot_scale: u32,
+
+	/// Where to write a save state after the last frame.
+	pub save_state_path: Option<String>,
+
+	/// The input script to play back.
+	pub input: InputScript
+}
+
+/// A timeline of buttons to hold down.
+///
+/// Each line of a script is a frame or an inclusive range of frames followed by
+/// the names of the buttons that are held during those frames:
+/// ```text
+/// # Comments start with a hash.
+/// 120 start
+/// 200-260 right b
+/// 300 a l
+/// ```
+/// Button names are A, B, Select, Start, Right, Left, Up, Down, R and L (case insensitive).
+/// Frames are counted from 0.
+#[derive(Default, Clone)]
+pub struct InputScript {
+	entries: Vec<(u64, u64, u16)>,
+
+	/// The frame that is played next.
+	frame: u64
+}
+
+impl InputScript {
+	pub fn load(path: &str) -> Result<InputScript, String> {
+		let source = try!(::util::io::read_file_into_string(path));
+		return InputScript::parse(&source);
+	}
+
+	pub fn parse(source: &str) -> Result<InputScript, String> {
+		let mut script: InputScript = Default::default();
+		for (line_index, line) in source.lines().enumerate() {
+			let line = match line.find('#') {
+				Some(comment_start) => &line[0..comment_start],
+				None => line
+			};
+
+			let mut parts = line.split_whitespace();
+			let frames = match parts.next() {
+				Some(f) => f,
+				None => continue // empty line
+			};
+
+			let (start, end) = match frames.find('-') {
+				Some(dash) => (parse_frame(&frames[0..dash], line_index), parse_frame(&frames[(dash+1)..], line_index)),
+				None => (parse_frame(frames, line_index), parse_frame(frames, line_index))
+			};
+			let start = try!(start);
+			let end = try!(end);
+			if end < start {
+				return Err(format!("line {}: frame range `{}` ends before it starts", line_index + 1, frames));
+			}
+
+			let mut buttons = 0;
+			for name in parts {
+				match joypad::button_from_name(name) {
+					Some(mask) => buttons |= mask,
+					None => return Err(format!("line {}: unknown button `{}`", line_index + 1, name))
+				}
+			}
+			script.entries.push((start, end, buttons));
+		}
+		return Ok(script);
+	}
+
+	/// Returns the buttons that should be held down during the given frame.
+	pub fn buttons_at(&self, frame: u64) -> u16 {
+		let mut buttons = 0;
+		for &(start, end, mask) in self.entries.iter() {
+			if frame >= start && frame <= end {
+				buttons |= mask;
+			}
+		}
+		return buttons;
+	}
+}
+
+impl InputSource for InputScript {
+	fn next_frame(&mut self) -> u16 {
+		let buttons = self.buttons_at(self.frame);
+		self.frame += 1;
+		return buttons;
+	}
+}
+
+fn parse_frame(s: &str, line_index: usize) -> Result<u64, String> {
+	s.parse::<u64>().map_err(|_| format!("line {}: invalid frame number `{}`", line_index + 1, s))
+}
+
+/// Runs the GBA for the number of frames in `options`, then writes the
+/// screenshot and save state if they were requested.
+/// The BIOS and ROM should already be loaded.
+pub fn run(gba: &mut Gba, options: &HeadlessOptions) -> Result<(), String> {
+	gba.init();
+	gba.input.add_source(Box::new(options.input.clone()));
+	for _ in 0..options.frames {
+		gba.tick();
+		if gba.request_exit { break }
+	}
+	gba.stop_movie();
+	gba.device.close();
+	debug_info!("Ran {} frames.", options.frames);
+
+	if let Some(ref path) = options.screenshot_path {
+		try!(::pyrite::screenshot::write_png(gba.screen_buffer(), path, options.screenshot_scale));
+		debug_info!("Wrote screenshot to {}", path);
+	}
+
+	if let Some(ref path) = options.save_state_path {
+		try!(gba.save_to_file(path).map_err(|e| format!("Failed to write save state to {}: {}", path, e)));
+		debug_info!("Wrote save state to {}", path);
+	}
+
+	return Ok(())
+}
@@ -4,7 +4,17 @@
 //! it gets to stay where it is for now.
 
 pub mod settings;
+pub mod headless;
+pub mod screenshot;
+pub mod save_slots;
+pub mod bindings;
+pub mod profiles;
 
+#[cfg(test)]
+mod regression;
+
+use std::path::{Path, PathBuf};
+use time;
 use ::util::sync_unsafe_cell::SyncUnsafeCell;
 
 lazy_static! {
@@ -39,6 +49,25 @@ pub fn get_settings() -> &'static mut settings::PyriteSettings {
 	}
 }
 
+/// Picks a file name in `dir` made from the ROM's name and the current time.
+/// A number is added to the end if a file was already written this second.
+pub fn output_path(dir: &str, rom_name: &str, extension: &str) -> PathBuf {
+	let rom_name = if rom_name.is_empty() { "pyrite" } else { rom_name };
+	let timestamp = match time::now().strftime("%Y-%m-%d_%H-%M-%S") {
+		Ok(t) => format!("{}", t),
+		Err(_) => format!("{}", time::get_time().sec)
+	};
+
+	let base = format!("{}_{}", rom_name, timestamp);
+	let mut path = Path::new(dir).join(format!("{}.{}", base, extension));
+	let mut index = 1;
+	while path.exists() {
+		index += 1;
+		path = Path::new(dir).join(format!("{}_{}.{}", base, index, extension));
+	}
+	return path;
+}
+
 #[macro_export]
 macro_rules! psetting {
 	($setting_name:ident, $setting_value:expr) => ({
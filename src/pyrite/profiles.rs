@@ -0,0 +1,194 @@
+//! Settings for one game, layered on top of the global ones.
+//!
+//! A profile is kept in `data/profiles/<game code>.json` as the settings it overrides, written
+//! the same way as in the settings file. When a ROM is loaded the overrides replace those settings,
+//! and while they're in use changing one of them changes the profile instead of the global settings.
+//!
+//! Every setting can be overridden, including the BIOS skip, the audio channels and the key
+//! bindings. Cheats are kept for each game by the cheat engine instead. There's no cartridge
+//! backup, RTC or color correction emulation yet, so those have no settings to override; when
+//! they get settings, the settings are overridable like any other.
+//! Profiles are only used with a window, headless runs always use the global settings.
+
+use rustc_serialize::json::{self, Json};
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use ::pyrite::settings;
+
+pub const PROFILE_DIR: &'static str = "data/profiles";
+
+lazy_static! {
+	static ref ACTIVE_PROFILE: Mutex<Option<GameProfile>> = Mutex::new(None);
+}
+
+pub struct GameProfile {
+	/// The 4 character code from the cartridge header.
+	pub game_code: String,
+
+	/// The settings that are overridden, with their values for this game.
+	overrides: json::Object,
+
+	/// The global values of the overridden settings.
+	globals: json::Object
+}
+
+pub fn profile_path(game_code: &str) -> PathBuf {
+	PathBuf::from(PROFILE_DIR).join(format!("{}.json", game_code))
+}
+
+/// Loads the profile for a game and applies it on top of the global settings.
+/// Games without a game code (homebrew, test ROMs) don't get a profile.
+pub fn load(game_code: &str) {
+	let game_code = game_code.trim();
+	if game_code.is_empty() { return }
+
+	let mut overrides = json::Object::new();
+	let path = profile_path(game_code);
+	if let Ok(mut file) = File::open(&path) {
+		let mut buffer = String::new();
+		match file.read_to_string(&mut buffer).map_err(|e| format!("{}", e)).and_then(|_| Json::from_str(&buffer).map_err(|e| format!("{}", e))) {
+			Ok(Json::Object(o)) => overrides = o,
+			Ok(_) => console_error!("The profile in {} should be a JSON object.", path.display()),
+			Err(e) => console_error!("Failed to read the profile in {}: {}", path.display(), e)
+		}
+	}
+
+	let mut profile = GameProfile {
+		game_code: game_code.to_string(),
+		overrides: json::Object::new(),
+		globals: json::Object::new()
+	};
+
+	let current = match settings::to_object(::pyrite::get_settings()) {
+		Ok(o) => o,
+		Err(e) => { console_error!("{}", e); return }
+	};
+	let mut merged = current.clone();
+	let mut notes = Vec::new();
+	let replaced = settings::merge_fields(&mut merged, overrides, &mut notes);
+	for note in notes.iter() {
+		console_warn!("Profile {}: {}", game_code, note);
+	}
+
+	// Settings in the profile that couldn't be read aren't overridden.
+	for key in replaced {
+		if let (Some(value), Some(global)) = (merged.get(&key), current.get(&key)) {
+			profile.overrides.insert(key.clone(), value.clone());
+			profile.globals.insert(key.clone(), global.clone());
+		}
+	}
+
+	if !profile.overrides.is_empty() {
+		console_log!("Using the profile for {} ({} settings overridden).", game_code, profile.overrides.len());
+	}
+	replace_settings(merged);
+	*ACTIVE_PROFILE.lock().unwrap() = Some(profile);
+}
+
+fn replace_settings(object: json::Object) {
+	match settings::from_object(object) {
+		Ok(mut new_settings) => {
+			let current = ::pyrite::get_settings();
+			new_settings.changed = current.changed;
+			new_settings.init();
+			*current = new_settings;
+		},
+		Err(e) => console_error!("Failed to apply the profile: {}", e)
+	}
+}
+
+/// The game code of the profile in use, if there is one.
+pub fn active_game_code() -> Option<String> {
+	ACTIVE_PROFILE.lock().unwrap().as_ref().map(|p| p.game_code.clone())
+}
+
+pub fn is_overridden(key: &str) -> bool {
+	ACTIVE_PROFILE.lock().unwrap().as_ref().map_or(false, |p| p.overrides.contains_key(key))
+}
+
+/// The global value of an overridden setting.
+pub fn global_value(key: &str) -> Option<Json> {
+	ACTIVE_PROFILE.lock().unwrap().as_ref().and_then(|p| p.globals.get(key).cloned())
+}
+
+/// Starts or stops overriding a setting for the running game. It starts out with the value
+/// it has now, and gets its global value back when it isn't overridden anymore.
+pub fn set_overridden(key: &str, overridden: bool) {
+	let mut current = match settings::to_object(::pyrite::get_settings()) {
+		Ok(o) => o,
+		Err(e) => { console_error!("{}", e); return }
+	};
+
+	{
+		let mut active = ACTIVE_PROFILE.lock().unwrap();
+		let profile = match active.as_mut() {
+			Some(p) => p,
+			None => return
+		};
+		let value = match current.get(key) {
+			Some(v) => v.clone(),
+			None => return
+		};
+
+		if overridden {
+			profile.overrides.insert(key.to_string(), value.clone());
+			profile.globals.insert(key.to_string(), value);
+		} else {
+			profile.overrides.remove(key);
+			if let Some(global) = profile.globals.remove(key) {
+				current.insert(key.to_string(), global);
+			}
+		}
+
+		if let Err(e) = save_profile(profile) {
+			console_error!("{}", e);
+		}
+	}
+
+	if !overridden {
+		replace_settings(current);
+	}
+	::pyrite::settings_changed();
+}
+
+/// Called when the settings are saved. The running game's values of the settings it overrides are
+/// moved into its profile, and the global values are put back in their place.
+pub fn split_overrides(object: &mut json::Object) {
+	let mut active = ACTIVE_PROFILE.lock().unwrap();
+	let profile = match active.as_mut() {
+		Some(p) => p,
+		None => return
+	};
+
+	let keys: Vec<String> = profile.overrides.keys().cloned().collect();
+	for key in keys {
+		if let Some(value) = object.get(&key).cloned() {
+			profile.overrides.insert(key.clone(), value);
+		}
+		if let Some(global) = profile.globals.get(&key) {
+			object.insert(key.clone(), global.clone());
+		}
+	}
+
+	if let Err(e) = save_profile(profile) {
+		console_error!("{}", e);
+	}
+}
+
+fn save_profile(profile: &GameProfile) -> Result<(), String> {
+	let path = profile_path(&profile.game_code);
+	if profile.overrides.is_empty() {
+		if path.exists() {
+			try!(fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e)));
+		}
+		return Ok(())
+	}
+
+	try!(fs::create_dir_all(PROFILE_DIR).map_err(|e| format!("Failed to create {}: {}", PROFILE_DIR, e)));
+	let mut file = try!(File::create(&path).map_err(|e| format!("Failed to create {}: {}", path.display(), e)));
+	let json = format!("{}", Json::Object(profile.overrides.clone()).pretty());
+	try!(file.write_all(json.as_bytes()).map_err(|e| format!("Failed to write {}: {}", path.display(), e)));
+	return Ok(())
+}
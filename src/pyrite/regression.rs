@@ -0,0 +1,139 @@
+//! Boots the bundled test ROMs headlessly and compares their framebuffers
+//! against checked-in golden hashes. The ROMs to run are listed in
+//! `tests/regression/roms.txt`.
+
+use std::env;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+use ::gba::Gba;
+use ::gba::hw::lcd::GbaLcdScreenBuffer;
+use super::headless::{self, HeadlessOptions, InputScript};
+
+const MANIFEST_PATH: &'static str = "tests/regression/roms.txt";
+const GOLDENS_PATH: &'static str = "tests/regression/goldens.txt";
+const INPUTS_DIR: &'static str = "tests/regression/inputs";
+const BIOS_PATH: &'static str = "roms/legal/gba.bin";
+
+/// Set this environment variable to record new goldens instead of checking them.
+const BLESS_VAR: &'static str = "PYRITE_BLESS";
+
+struct RegressionEntry {
+	name: String,
+	rom: String,
+	frames: u64,
+	input: Option<String>
+}
+
+fn load_manifest() -> Vec<RegressionEntry> {
+	let source = ::util::io::read_file_into_string(MANIFEST_PATH).expect("Failed to read the regression manifest.");
+	let mut entries = Vec::new();
+	for line in source.lines() {
+		let line = line.trim();
+		if line.is_empty() || line.starts_with("#") { continue }
+		let parts: Vec<&str> = line.split_whitespace().collect();
+		assert!(parts.len() == 4, "Malformed regression manifest line: `{}`", line);
+		entries.push(RegressionEntry {
+			name: parts[0].to_string(),
+			rom: parts[1].to_string(),
+			frames: parts[2].parse().expect("Invalid frame count in the regression manifest."),
+			input: if parts[3] == "-" { None } else { Some(format!("{}/{}", INPUTS_DIR, parts[3])) }
+		});
+	}
+	return entries;
+}
+
+fn load_goldens() -> BTreeMap<String, u64> {
+	let mut goldens = BTreeMap::new();
+	let source = match ::util::io::read_file_into_string(GOLDENS_PATH) {
+		Ok(s) => s,
+		Err(_) => return goldens
+	};
+	for line in source.lines() {
+		let line = line.trim();
+		if line.is_empty() || line.starts_with("#") { continue }
+		let parts: Vec<&str> = line.split_whitespace().collect();
+		assert!(parts.len() == 2, "Malformed goldens line: `{}`", line);
+		let hash = u64::from_str_radix(parts[1], 16).expect("Invalid hash in goldens file.");
+		goldens.insert(parts[0].to_string(), hash);
+	}
+	return goldens;
+}
+
+fn save_goldens(goldens: &BTreeMap<String, u64>) {
+	let mut out = String::new();
+	out.push_str("# Framebuffer hashes recorded from a known good build.\n");
+	out.push_str("# Regenerate with: PYRITE_BLESS=1 cargo test -- --ignored rom_regression\n");
+	out.push_str("#\n");
+	out.push_str("# name                      hash\n");
+	for (name, hash) in goldens.iter() {
+		out.push_str(&format!("{:<27} {:016x}\n", name, hash));
+	}
+	let mut f = File::create(GOLDENS_PATH).expect("Failed to create goldens file.");
+	f.write_all(out.as_bytes()).expect("Failed to write goldens file.");
+}
+
+/// FNV-1a over the RGB values of every pixel.
+fn hash_screen(buffer: &GbaLcdScreenBuffer) -> u64 {
+	let mut hash: u64 = 0xcbf29ce484222325;
+	for line in buffer.iter() {
+		for &(r, g, b) in line.iter() {
+			for &c in [r, g, b].iter() {
+				hash ^= c as u64;
+				hash = hash.wrapping_mul(0x100000001b3);
+			}
+		}
+	}
+	return hash;
+}
+
+fn run_entry(entry: &RegressionEntry) -> u64 {
+	let mut gba = Box::new(Gba::headless());
+	::load_bios(&mut gba.cpu.memory);
+	::load_rom(&mut gba, entry.rom.clone());
+	let options = HeadlessOptions {
+		frames: entry.frames,
+		input: match entry.input {
+			Some(ref path) => InputScript::load(path).expect("Failed to load regression input script."),
+			None => Default::default()
+		},
+		..Default::default()
+	};
+	headless::run(&mut gba, &options).expect("Failed to run regression ROM.");
+	return hash_screen(gba.screen_buffer());
+}
+
+// Ignored because the BIOS can't be distributed and the test ROMs call into it, so CI can't
+// run this. It's run by hand where one has been put in `roms/legal`, see "Regression tests"
+// in the README: `cargo test -- --ignored rom_regression` (with PYRITE_BLESS=1 to record the goldens).
+#[test]
+#[ignore]
+fn rom_regression() {
+	assert!(Path::new(BIOS_PATH).exists(), "The ROM regression tests need a BIOS at {}.", BIOS_PATH);
+
+	let bless = env::var(BLESS_VAR).is_ok();
+	let mut goldens = load_goldens();
+	let mut failures = Vec::new();
+
+	for entry in load_manifest() {
+		let hash = run_entry(&entry);
+		if bless {
+			goldens.insert(entry.name.clone(), hash);
+			continue
+		}
+
+		match goldens.get(&entry.name) {
+			Some(&expected) if expected == hash => {},
+			Some(&expected) => failures.push(format!("{}: expected {:016x}, got {:016x}", entry.name, expected, hash)),
+			None => failures.push(format!("{}: no golden recorded (got {:016x}), run with {}=1 to record it", entry.name, hash, BLESS_VAR))
+		}
+	}
+
+	if bless {
+		save_goldens(&goldens);
+		println!("Recorded {} goldens in {}.", goldens.len(), GOLDENS_PATH);
+	}
+
+	assert!(failures.is_empty(), "ROM regressions:\n{}", failures.join("\n"));
+}
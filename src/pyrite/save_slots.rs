@@ -0,0 +1,55 @@
+//! Numbered save state slots, kept in a directory per ROM.
+
+use std::path::{Path, PathBuf};
+use time;
+use ::gba::serialization::{StateMeta, read_state_meta_from_file};
+
+/// Where save states go, the same place the old terminal debugger used.
+pub const SAVE_STATE_DIR: &'static str = "data/sav";
+
+/// Slots are numbered from 1 to `SLOT_COUNT` and map to F1 through F10.
+pub const SLOT_COUNT: usize = 10;
+
+pub enum SlotStatus {
+	Empty,
+
+	/// A state from before descriptions were saved with them.
+	NoDescription,
+
+	Used(StateMeta),
+
+	/// The file is there but couldn't be read.
+	Unreadable(String)
+}
+
+pub fn slot_path(rom_name: &str, slot: usize) -> PathBuf {
+	let rom_name = if rom_name.is_empty() { "pyrite" } else { rom_name };
+	Path::new(SAVE_STATE_DIR).join(rom_name).join(format!("slot-{}.psav", slot))
+}
+
+/// Reads the description of every slot for a ROM.
+pub fn read_slots(rom_name: &str) -> Vec<SlotStatus> {
+	(1..(SLOT_COUNT + 1)).map(|slot| {
+		let path = slot_path(rom_name, slot);
+		if !path.exists() { return SlotStatus::Empty }
+		match read_state_meta_from_file(&path.to_string_lossy()) {
+			Ok(Some(meta)) => SlotStatus::Used(meta),
+			Ok(None) => SlotStatus::NoDescription,
+			Err(e) => SlotStatus::Unreadable(format!("{}", e))
+		}
+	}).collect()
+}
+
+/// The local time that a state was saved at.
+pub fn format_timestamp(timestamp: i64) -> String {
+	let tm = time::at(time::Timespec::new(timestamp, 0));
+	match tm.strftime("%Y-%m-%d %H:%M:%S") {
+		Ok(t) => format!("{}", t),
+		Err(_) => format!("{}", timestamp)
+	}
+}
+
+/// Play time as hours, minutes and seconds.
+pub fn format_play_time(seconds: u64) -> String {
+	format!("{}:{:02}:{:02}", seconds / 3600, (seconds / 60) % 60, seconds % 60)
+}
@@ -0,0 +1,65 @@
+//! Saves the LCD's screen buffer as PNG screenshots.
+
+use std::fs;
+use std::fs::File;
+use std::io::BufWriter;
+use ::gba::hw::lcd::GbaLcdScreenBuffer;
+
+/// Where screenshots taken while running go.
+pub const SCREENSHOT_DIR: &'static str = "screenshots";
+
+const SCREEN_WIDTH: usize = 240;
+const SCREEN_HEIGHT: usize = 160;
+
+/// The largest integer scale a screenshot can be written at.
+pub const MAX_SCALE: u32 = 8;
+
+/// Converts the screen buffer into packed 8bit RGB, repeating every pixel
+/// `scale` times horizontally and every line `scale` times vertically.
+pub fn screen_to_rgb(buffer: &GbaLcdScreenBuffer, scale: u32) -> Vec<u8> {
+	let scale = max!(1, min!(scale, MAX_SCALE)) as usize;
+	let stride = SCREEN_WIDTH * scale * 3;
+	let mut rgb = Vec::with_capacity(stride * SCREEN_HEIGHT * scale);
+	for line in buffer.iter() {
+		let line_start = rgb.len();
+		for &(r, g, b) in line.iter() {
+			for _ in 0..scale {
+				rgb.push(r);
+				rgb.push(g);
+				rgb.push(b);
+			}
+		}
+		for _ in 1..scale {
+			for idx in line_start..(line_start + stride) {
+				let value = rgb[idx];
+				rgb.push(value);
+			}
+		}
+	}
+	return rgb;
+}
+
+/// Writes the screen buffer to a PNG file, `scale` should be 1 for native resolution.
+pub fn write_png(buffer: &GbaLcdScreenBuffer, path: &str, scale: u32) -> Result<(), String> {
+	let scale = max!(1, min!(scale, MAX_SCALE));
+	let rgb = screen_to_rgb(buffer, scale);
+
+	let mut f = match File::create(path) {
+		Ok(file) => BufWriter::new(file),
+		Err(e) => return Err(format!("Failed to create {}: {}", path, e))
+	};
+	return ::util::png::write_rgb(&mut f, SCREEN_WIDTH as u32 * scale, SCREEN_HEIGHT as u32 * scale, &rgb)
+		.map_err(|e| format!("Failed to write {}: {}", path, e));
+}
+
+/// Saves a screenshot into the screenshot directory at the scale
+/// in the settings and returns the path that it was written to.
+pub fn save_screenshot(buffer: &GbaLcdScreenBuffer, rom_name: &str) -> Result<String, String> {
+	if let Err(e) = fs::create_dir_all(SCREENSHOT_DIR) {
+		return Err(format!("Failed to create the screenshot directory {}: {}", SCREENSHOT_DIR, e));
+	}
+	let path = ::pyrite::output_path(SCREENSHOT_DIR, rom_name, "png");
+	let path = path.to_string_lossy().into_owned();
+	try!(write_png(buffer, &path, psetting!(screenshot_scale)));
+	return Ok(path);
+}
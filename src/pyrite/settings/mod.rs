@@ -0,0 +1,340 @@
+//! The emulator's settings, saved as JSON.
+//!
+//! Settings files are read one field at a time on top of the defaults, so a file from an
+//! older or newer version keeps every setting that can still be read. Fields that are
+//! missing or can't be read get their defaults and unknown fields are dropped, all of which
+//! is reported in the console. Files carry `settings_version` so that settings that change
+//! meaning can be moved over by a migration before they're read. A file from a newer version
+//! keeps its version and the fields this one doesn't know when it's saved again.
+
+use rustc_serialize::json::{self, Json};
+use rustc_serialize::Decodable;
+use std::fs::{self, File};
+use std::io::prelude::*;
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use ::util::atomic::Atomic;
+
+#[cfg(test)]
+mod tests;
+
+lazy_static! {
+	pub static ref ATOMIC_MASTER_VOLUME: Arc<Atomic<f32>> = Arc::new(Atomic::new(0.0));
+	static ref SETTINGS_PATH: Mutex<String> = Mutex::new(DEFAULT_SETTINGS_PATH.to_string());
+}
+
+pub const DEFAULT_SETTINGS_PATH: &'static str = "data/settings.json";
+
+/// The version that settings are saved as.
+pub const SETTINGS_VERSION: u32 = 1;
+
+/// Moves a settings object from the version at its index to the next one.
+const MIGRATIONS: &'static [fn(&mut json::Object)] = &[migrate_v0];
+
+/// Version 0 is every file that was saved before settings had a version.
+/// Nothing in them changed meaning, the fields they're missing get their defaults.
+fn migrate_v0(_: &mut json::Object) {}
+
+/// Where settings are loaded from and saved to, `data/settings.json` unless `--settings` was passed.
+pub fn settings_path() -> String {
+	SETTINGS_PATH.lock().unwrap().clone()
+}
+
+pub fn set_settings_path(path: &str) {
+	*SETTINGS_PATH.lock().unwrap() = path.to_string();
+}
+
+#[derive(RustcEncodable, RustcDecodable)]
+pub struct PyriteSettings {
+	/// The version of the file these were loaded from, see `SETTINGS_VERSION`.
+	pub settings_version: u32,
+
+	// UI SETTINGS:
+	pub window_width: u32,
+	pub window_height: u32,
+
+	/// Only read when the window is created.
+	pub vsync: bool,
+
+	// SYSTEM SETTINGS:
+	/// Starts at the ROM instead of running the BIOS intro, read when the GBA starts.
+	pub skip_bios: bool,
+
+	// SPEED SETTINGS:
+	/// Runs at the GBA's frame rate instead of as fast as possible.
+	pub frame_limit: bool,
+
+	/// Multiple of the normal speed to fast-forward at, 0 is as fast as possible.
+	pub fast_forward_speed: u32,
+
+	/// Percentage of the normal speed to run at in slow motion.
+	pub slow_motion_speed: u32,
+
+	/// Frames to skip drawing after each one that is drawn.
+	pub frame_skip: u32,
+
+	/// Picks the frame skip from how long frames take instead of using `frame_skip`.
+	pub auto_frame_skip: bool,
+
+	// SOUND SETTINGS:
+	pub master_volume: f32,
+	pub sound_enabled: bool,
+	pub channel1_enabled: bool,
+	pub channel2_enabled: bool,
+	pub channel3_enabled: bool,
+	pub channel4_enabled: bool,
+	pub channela_enabled: bool,
+	pub channelb_enabled: bool,
+
+	// SCREENSHOT SETTINGS:
+	pub screenshot_scale: u32,
+
+	// REWIND SETTINGS:
+	pub rewind_enabled: bool,
+
+	/// Megabytes of memory that rewind snapshots can use.
+	pub rewind_buffer_size: u32,
+
+	/// Frames between rewind snapshots.
+	pub rewind_interval: u32,
+
+	// INPUT SETTINGS:
+	/// The keys bound to each action by name, see `pyrite::bindings`.
+	pub key_bindings: BTreeMap<String, Vec<usize>>,
+
+	/// The gamepad inputs bound to each action by name, see `gba::device::gamepad`.
+	pub gamepad_bindings: BTreeMap<String, Vec<u32>>,
+
+	/// How far a stick has to be pushed, as a percentage, to press the buttons bound to it.
+	pub gamepad_axis_threshold: u32,
+
+	/// Frames that turbo buttons stay pressed and then released for.
+	pub turbo_rate: u32,
+
+	/// The buttons of every frame of each recorded macro, by the name of its binding.
+	pub input_macros: BTreeMap<String, Vec<u16>>,
+
+	/// The fields of a file from a newer version that aren't settings in this one, as JSON.
+	/// They're written back when saving so that the newer version doesn't lose them.
+	pub newer_fields: BTreeMap<String, String>,
+
+	pub changed: bool,
+}
+
+impl PyriteSettings {
+	pub fn init(&mut self) {
+		self.commit_volume();
+		::pyrite::bindings::fix_bindings(&mut self.key_bindings);
+		::pyrite::bindings::fix_gamepad_bindings(&mut self.gamepad_bindings);
+	}
+
+	pub fn commit_volume(&self) {
+		use std::sync::atomic::Ordering;
+		ATOMIC_MASTER_VOLUME.store(self.master_volume, Ordering::Relaxed);
+	}
+
+	/// Saves only if there are changes to save.
+	pub fn save_changes(&mut self) -> bool {
+		if self.changed {
+			self.save();
+			return true;
+		} else {
+			return false;
+		}
+	}
+
+	/// Settings that the running game overrides are saved to its profile instead.
+	pub fn save(&mut self) {
+		self.changed = false;
+		self.settings_version = max!(self.settings_version, SETTINGS_VERSION);
+		let mut save_file = File::create(settings_path()).expect("Failed to open save file for writing.");
+		match to_object(self) {
+			Ok(mut object) => {
+				::pyrite::profiles::split_overrides(&mut object);
+				object.remove("newer_fields");
+				for (key, value) in self.newer_fields.iter() {
+					if let Ok(value) = Json::from_str(value) {
+						object.insert(key.clone(), value);
+					}
+				}
+				write!(save_file, "{}", Json::Object(object)).expect("Failed to write json to file.")
+			},
+			Err(e) => panic!("Failed to serialize settings. ERROR: {}", e),
+		}
+	}
+
+	pub fn load() -> PyriteSettings {
+		let path = settings_path();
+		let mut buffer = String::new();
+		match File::open(&path) {
+			Ok(mut file) => {
+				if let Err(e) = file.read_to_string(&mut buffer) {
+					console_error!("Error while reading settings: {}", e);
+					return Default::default()
+				}
+			},
+			Err(_) => return Default::default()
+		}
+
+		match PyriteSettings::decode_partial(&buffer) {
+			Ok((settings, notes)) => {
+				for note in notes.iter() {
+					console_warn!("Settings: {}", note);
+				}
+				return settings
+			},
+			Err(e) => {
+				// Kept so that saving the defaults doesn't lose it.
+				let backup = format!("{}.bak", path);
+				console_error!("Failed to read settings from {}, using the defaults: {}", path, e);
+				match fs::copy(&path, &backup) {
+					Ok(_) => console_warn!("The old settings were copied to {}", backup),
+					Err(e) => console_error!("Failed to copy the old settings to {}: {}", backup, e)
+				}
+				return Default::default()
+			}
+		}
+	}
+
+	/// Reads every field of a settings file that can be read on top of the defaults.
+	/// Returns the settings and what had to be changed to read them.
+	pub fn decode_partial(source: &str) -> Result<(PyriteSettings, Vec<String>), String> {
+		let mut loaded = match try!(Json::from_str(source).map_err(|e| format!("{}", e))) {
+			Json::Object(o) => o,
+			_ => return Err("Settings should be a JSON object.".to_string())
+		};
+
+		let mut notes = Vec::new();
+		let version = loaded.get("settings_version").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+		if version > SETTINGS_VERSION {
+			notes.push(format!("they were saved by a newer version of Pyrite (settings version {}).", version));
+		}
+		for v in version..SETTINGS_VERSION {
+			MIGRATIONS[v as usize](&mut loaded);
+		}
+
+		let mut merged = try!(to_object(&PyriteSettings::default()));
+		let loaded_keys: Vec<String> = loaded.keys().cloned().collect();
+
+		// Kept for the newer version instead of being dropped.
+		let mut newer_fields = BTreeMap::new();
+		if version > SETTINGS_VERSION {
+			for key in loaded_keys.iter() {
+				if is_setting(key) && !merged.contains_key(key) {
+					if let Some(value) = loaded.remove(key) {
+						notes.push(format!("`{}` isn't a setting in this version and is kept for the newer one.", key));
+						newer_fields.insert(key.clone(), value.to_string());
+					}
+				}
+			}
+		}
+		merge_fields(&mut merged, loaded, &mut notes);
+
+		for key in merged.keys() {
+			if is_setting(key) && !loaded_keys.contains(key) {
+				notes.push(format!("`{}` wasn't saved and was set to its default.", key));
+			}
+		}
+
+		let mut settings = try!(from_object(merged));
+		settings.settings_version = max!(version, SETTINGS_VERSION);
+		settings.newer_fields = newer_fields;
+		// Saved again so that the file is up to date, a newer file is left alone until a setting changes.
+		settings.changed = version <= SETTINGS_VERSION && !notes.is_empty();
+		return Ok((settings, notes));
+	}
+}
+
+/// False for the fields that are saved with the settings without being a setting.
+pub fn is_setting(key: &str) -> bool {
+	key != "settings_version" && key != "changed" && key != "newer_fields"
+}
+
+/// Replaces the fields of a settings object with the ones in `fields` that can be read,
+/// the others are left as they were and noted. Returns the names of the fields that were replaced.
+pub fn merge_fields(merged: &mut json::Object, fields: json::Object, notes: &mut Vec<String>) -> Vec<String> {
+	let mut replaced = Vec::new();
+	for (key, value) in fields.into_iter() {
+		if !is_setting(&key) { continue }
+		let previous = match merged.insert(key.clone(), value) {
+			Some(p) => p,
+			None => {
+				merged.remove(&key);
+				notes.push(format!("`{}` isn't a setting and was dropped.", key));
+				continue
+			}
+		};
+		if let Err(e) = from_object(merged.clone()) {
+			merged.insert(key.clone(), previous);
+			notes.push(format!("`{}` couldn't be read and was left as it was ({}).", key, e));
+		} else {
+			replaced.push(key);
+		}
+	}
+	return replaced;
+}
+
+pub fn to_object(settings: &PyriteSettings) -> Result<json::Object, String> {
+	let encoded = try!(json::encode(settings).map_err(|e| format!("{}", e)));
+	match Json::from_str(&encoded) {
+		Ok(Json::Object(o)) => Ok(o),
+		_ => Err("Settings didn't encode as a JSON object.".to_string())
+	}
+}
+
+pub fn from_object(object: json::Object) -> Result<PyriteSettings, String> {
+	let mut decoder = json::Decoder::new(Json::Object(object));
+	Decodable::decode(&mut decoder).map_err(|e| format!("{}", e))
+}
+
+impl Default for PyriteSettings {
+	fn default() -> PyriteSettings {
+		PyriteSettings {
+			settings_version: SETTINGS_VERSION,
+
+			// UI SETTINGS:
+			window_width: 240 * 3,
+			window_height: 160 * 3,
+			vsync: false,
+
+			// SYSTEM SETTINGS:
+			skip_bios: false,
+
+			// SPEED SETTINGS:
+			frame_limit: true,
+			fast_forward_speed: 4,
+			slow_motion_speed: 50,
+			frame_skip: 0,
+			auto_frame_skip: false,
+
+			// SOUND SETTINGS
+			master_volume: 1.0,
+			sound_enabled: true,
+			channel1_enabled: true,
+			channel2_enabled: true,
+			channel3_enabled: true,
+			channel4_enabled: true,
+			channela_enabled: true,
+			channelb_enabled: true,
+
+			// SCREENSHOT SETTINGS:
+			screenshot_scale: 1,
+
+			// REWIND SETTINGS:
+			rewind_enabled: true,
+			rewind_buffer_size: 64,
+			rewind_interval: 2,
+
+			// INPUT SETTINGS:
+			key_bindings: ::pyrite::bindings::default_bindings(),
+			gamepad_bindings: ::pyrite::bindings::default_gamepad_bindings(),
+			gamepad_axis_threshold: 50,
+			turbo_rate: 2,
+			input_macros: BTreeMap::new(),
+
+			newer_fields: BTreeMap::new(),
+			changed: false,
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,62 @@
+use std::env;
+use std::fs;
+use super::{PyriteSettings, SETTINGS_VERSION, DEFAULT_SETTINGS_PATH, set_settings_path};
+
+#[test]
+fn old_files_keep_what_can_be_read() {
+	// No version, a field of the wrong type, one that doesn't exist anymore and most missing.
+	let (settings, notes) = PyriteSettings::decode_partial(r#"{
+		"window_width": 960,
+		"frame_limit": false,
+		"fast_forward_speed": "fast",
+		"old_setting": 1
+	}"#).unwrap();
+
+	assert_eq!(settings.window_width, 960);
+	assert_eq!(settings.frame_limit, false);
+	assert_eq!(settings.fast_forward_speed, PyriteSettings::default().fast_forward_speed);
+	assert_eq!(settings.settings_version, SETTINGS_VERSION);
+	assert!(settings.changed, "Upgraded settings should be saved again.");
+
+	assert!(notes.iter().any(|n| n.contains("`fast_forward_speed` couldn't be read")));
+	assert!(notes.iter().any(|n| n.contains("`old_setting` isn't a setting")));
+	assert!(notes.iter().any(|n| n.contains("`vsync` wasn't saved")));
+}
+
+#[test]
+fn saved_settings_load_without_notes() {
+	let mut saved = PyriteSettings::default();
+	saved.master_volume = 0.25;
+	saved.key_bindings.insert("A".to_string(), vec![1, 2]);
+	let json = ::rustc_serialize::json::encode(&saved).unwrap();
+
+	let (settings, notes) = PyriteSettings::decode_partial(&json).unwrap();
+	assert!(notes.is_empty(), "{:?}", notes);
+	assert_eq!(settings.master_volume, 0.25);
+	assert_eq!(settings.key_bindings["A"], vec![1, 2]);
+	assert!(!settings.changed);
+}
+
+#[test]
+fn newer_files_arent_overwritten() {
+	let source = format!(r#"{{ "settings_version": {}, "frame_skip": 3, "future_setting": true }}"#, SETTINGS_VERSION + 1);
+	let (mut settings, notes) = PyriteSettings::decode_partial(&source).unwrap();
+	assert_eq!(settings.frame_skip, 3);
+	assert!(!settings.changed);
+	assert!(notes[0].contains("newer version"));
+	assert!(PyriteSettings::decode_partial("[1, 2, 3]").is_err());
+
+	// Changing a setting saves the file again without losing what the newer version added.
+	let path = env::temp_dir().join("pyrite_newer_settings.json").to_string_lossy().into_owned();
+	set_settings_path(&path);
+	settings.frame_skip = 4;
+	settings.save();
+	set_settings_path(DEFAULT_SETTINGS_PATH);
+	let saved = ::util::io::read_file_into_string(&path).unwrap();
+	let _ = fs::remove_file(&path);
+
+	let (settings, _) = PyriteSettings::decode_partial(&saved).unwrap();
+	assert_eq!(settings.frame_skip, 4);
+	assert_eq!(settings.settings_version, SETTINGS_VERSION + 1);
+	assert_eq!(settings.newer_fields.get("future_setting").map(|v| &v[..]), Some("true"));
+}
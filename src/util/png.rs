@@ -0,0 +1,73 @@
+//! Just enough of a PNG encoder to write 8bit RGB images.
+
+use std::io;
+use std::io::prelude::*;
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// Writes an 8bit RGB image to `w` as a PNG.
+/// `rgb` must contain exactly `width * height * 3` bytes.
+pub fn write_rgb<W: Write>(w: &mut W, width: u32, height: u32, rgb: &[u8]) -> io::Result<()> {
+	let stride = (width * 3) as usize;
+	if rgb.len() != stride * (height as usize) {
+		return Err(io::Error::new(io::ErrorKind::InvalidInput, "RGB data does not match the image dimensions."));
+	}
+
+	try!(w.write_all(&PNG_SIGNATURE));
+
+	let mut ihdr = Vec::with_capacity(13);
+	push_u32_be(&mut ihdr, width);
+	push_u32_be(&mut ihdr, height);
+	ihdr.push(8); // bit depth
+	ihdr.push(2); // color type: RGB
+	ihdr.push(0); // compression method
+	ihdr.push(0); // filter method
+	ihdr.push(0); // interlace method
+	try!(write_chunk(w, b"IHDR", &ihdr));
+
+	// Every scanline starts with its filter type, we don't filter anything.
+	let mut encoder = ZlibEncoder::new(Vec::new(), Compression::Default);
+	for line in rgb.chunks(stride) {
+		try!(encoder.write_all(&[0]));
+		try!(encoder.write_all(line));
+	}
+	let idat = try!(encoder.finish());
+	try!(write_chunk(w, b"IDAT", &idat));
+
+	try!(write_chunk(w, b"IEND", &[]));
+	return Ok(())
+}
+
+fn write_chunk<W: Write>(w: &mut W, chunk_type: &[u8; 4], data: &[u8]) -> io::Result<()> {
+	let mut header = Vec::with_capacity(8);
+	push_u32_be(&mut header, data.len() as u32);
+	header.extend_from_slice(chunk_type);
+	try!(w.write_all(&header));
+	try!(w.write_all(data));
+
+	let crc = crc32_update(crc32_update(0xffffffff, chunk_type), data) ^ 0xffffffff;
+	let mut footer = Vec::with_capacity(4);
+	push_u32_be(&mut footer, crc);
+	return w.write_all(&footer);
+}
+
+fn push_u32_be(v: &mut Vec<u8>, value: u32) {
+	v.push((value >> 24) as u8);
+	v.push((value >> 16) as u8);
+	v.push((value >> 8) as u8);
+	v.push(value as u8);
+}
+
+/// Bitwise CRC-32 (ISO 3309) as used by PNG.
+/// This is slow but we only ever write a few chunks at a time.
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+	for &b in data {
+		crc ^= b as u32;
+		for _ in 0..8 {
+			crc = if (crc & 1) != 0 { 0xedb88320 ^ (crc >> 1) } else { crc >> 1 };
+		}
+	}
+	return crc;
+}